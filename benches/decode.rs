@@ -0,0 +1,72 @@
+//! Compares decoding by walking the tree with decoding canonical codes through
+//! a lookup table, on a few megabytes of skewed bytes.
+//!
+//! Run with `cargo bench`; the time of each is the best of several runs.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use huffman::{CodeTable, Tree};
+
+const SIZE: usize = 4 << 20;
+const RUNS: usize = 5;
+
+/// Bytes from a seeded generator, each group of eight half as frequent as the
+/// one before, so that the codes range from a few bits to well past the
+/// table's.
+fn input() -> Vec<u8> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    (0..SIZE)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ((state >> 3).trailing_zeros().min(31) << 3) as u8 | (state & 7) as u8
+        })
+        .collect()
+}
+
+fn best(mut run: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, time: Duration) {
+    println!("{:<12} {:>8.2} ms {:>8.1} MB/s", name, time.as_secs_f64() * 1e3, SIZE as f64 / time.as_secs_f64() / 1e6);
+}
+
+fn main() {
+    let input = input();
+    let tree = Tree::from_frequencies(&huffman::get_byte_frequencies(&input)).unwrap();
+    let tree_codes = CodeTable::from_tree(&tree);
+    let canonical_codes = tree_codes.canonicalize();
+    let tree_encoded = huffman::encode(input.iter().copied(), &tree_codes).unwrap();
+    let canonical_encoded = huffman::encode(input.iter().copied(), &canonical_codes).unwrap();
+    println!(
+        "{} bytes, {} symbols, codes of {} to {} bits",
+        SIZE,
+        canonical_codes.len(),
+        canonical_codes.iter().map(|(_, code)| code.len()).min().unwrap(),
+        canonical_codes.iter().map(|(_, code)| code.len()).max().unwrap(),
+    );
+
+    let tree_walk = best(|| {
+        let decoded = huffman::decode(black_box(&tree_encoded), &tree, SIZE).unwrap();
+        assert_eq!(decoded, input);
+    });
+    report("tree walk", tree_walk);
+
+    let table = best(|| {
+        let decoded = huffman::decode_canonical(black_box(&canonical_encoded), &canonical_codes, SIZE).unwrap();
+        assert_eq!(decoded, input);
+    });
+    report("table", table);
+
+    println!("speedup: {:.1}x", tree_walk.as_secs_f64() / table.as_secs_f64());
+}
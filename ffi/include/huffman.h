@@ -0,0 +1,42 @@
+/* The C interface to huffman-rs; see ffi/src/lib.rs for the rules. */
+
+#ifndef HUFFMAN_H
+#define HUFFMAN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define HUFFMAN_OK 0
+#define HUFFMAN_ERROR_NULL_POINTER 1
+#define HUFFMAN_ERROR_BUFFER_TOO_SMALL 2
+#define HUFFMAN_ERROR_TRUNCATED 3
+#define HUFFMAN_ERROR_BAD_HEADER 4
+#define HUFFMAN_ERROR_CORRUPT 5
+#define HUFFMAN_ERROR_CHECKSUM 6
+#define HUFFMAN_ERROR_MISSING_TABLE 7
+#define HUFFMAN_ERROR_INVALID_INPUT 8
+#define HUFFMAN_ERROR_PANIC 9
+
+/*
+ * Compresses the len bytes at buf into out, which holds *out_len bytes, and
+ * sets *out_len to the size of the output. Returns HUFFMAN_OK, or
+ * HUFFMAN_ERROR_BUFFER_TOO_SMALL if it did not fit; a null out with an
+ * *out_len of 0 asks for the size.
+ */
+int32_t huffman_compress(const uint8_t *buf, size_t len, uint8_t *out, size_t *out_len);
+
+/* Decompresses the len bytes at buf into out, like huffman_compress. */
+int32_t huffman_decompress(const uint8_t *buf, size_t len, uint8_t *out, size_t *out_len);
+
+/* What a code means, as a static string that is not to be freed. */
+const char *huffman_error_message(int32_t code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,245 @@
+//! A C interface to the coder, for C and C++ programs and for Python through
+//! `ctypes`. It builds as a shared and a static library, declared by
+//! `include/huffman.h`:
+//!
+//! ```c
+//! size_t size = 0;
+//! huffman_compress(input, input_len, NULL, &size);  // HUFFMAN_ERROR_BUFFER_TOO_SMALL
+//! uint8_t *compressed = malloc(size);
+//! int code = huffman_compress(input, input_len, compressed, &size);
+//! if (code != HUFFMAN_OK) {
+//!     fprintf(stderr, "%s\n", huffman_error_message(code));
+//! }
+//! ```
+//!
+//! All memory is the caller's. Each function reads the `len` bytes at `buf`
+//! and writes at most `*out_len` bytes to `out`, keeping neither pointer once
+//! it returns and leaving nothing to free. On return `*out_len` is the size of
+//! the output, whether it was written or, with
+//! [`HUFFMAN_ERROR_BUFFER_TOO_SMALL`], did not fit; passing a null `out` and
+//! an `*out_len` of 0 asks for the size. The output is made again on the
+//! second call.
+//!
+//! Nothing panics across the boundary: failures are returned as the codes
+//! below, and a panic, which would be a bug, as [`HUFFMAN_ERROR_PANIC`].
+
+#![deny(unsafe_op_in_unsafe_fn)]
+
+use std::ffi::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use huffman::HuffmanError;
+
+/// Success.
+pub const HUFFMAN_OK: i32 = 0;
+/// `out_len`, `buf` with a `len` other than 0, or `out` with an `*out_len`
+/// other than 0, was null.
+pub const HUFFMAN_ERROR_NULL_POINTER: i32 = 1;
+/// The output is larger than `*out_len`, which is now its size.
+pub const HUFFMAN_ERROR_BUFFER_TOO_SMALL: i32 = 2;
+/// The compressed data ended early.
+pub const HUFFMAN_ERROR_TRUNCATED: i32 = 3;
+/// The data is not compressed, or by a newer version.
+pub const HUFFMAN_ERROR_BAD_HEADER: i32 = 4;
+/// The compressed data is damaged.
+pub const HUFFMAN_ERROR_CORRUPT: i32 = 5;
+/// The data decompressed, but not to what was compressed.
+pub const HUFFMAN_ERROR_CHECKSUM: i32 = 6;
+/// The data was compressed with a shared table, which this interface does
+/// not take.
+pub const HUFFMAN_ERROR_MISSING_TABLE: i32 = 7;
+/// Any other failure.
+pub const HUFFMAN_ERROR_INVALID_INPUT: i32 = 8;
+/// The library panicked, which is a bug.
+pub const HUFFMAN_ERROR_PANIC: i32 = 9;
+
+/// Compresses the `len` bytes at `buf` into `out`, as
+/// [`huffman::compress_bytes`] does.
+///
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes unless `len` is 0, `out_len` to a
+/// writable `size_t`, and `out` to `*out_len` writable bytes unless
+/// `*out_len` is 0. None may overlap.
+#[no_mangle]
+pub unsafe extern "C" fn huffman_compress(buf: *const u8, len: usize, out: *mut u8, out_len: *mut usize) -> i32 {
+    // SAFETY: passed on from the caller.
+    unsafe { call(buf, len, out, out_len, |input| Ok(huffman::compress_bytes(input))) }
+}
+
+/// Decompresses the `len` bytes at `buf` into `out`, as
+/// [`huffman::decompress_bytes`] does.
+///
+/// # Safety
+///
+/// As for [`huffman_compress`].
+#[no_mangle]
+pub unsafe extern "C" fn huffman_decompress(buf: *const u8, len: usize, out: *mut u8, out_len: *mut usize) -> i32 {
+    // SAFETY: passed on from the caller.
+    unsafe { call(buf, len, out, out_len, huffman::decompress_bytes) }
+}
+
+/// What `code` means, as a static NUL-terminated string that is not to be
+/// freed.
+#[no_mangle]
+pub extern "C" fn huffman_error_message(code: i32) -> *const c_char {
+    let message = match code {
+        HUFFMAN_OK => c"Success",
+        HUFFMAN_ERROR_NULL_POINTER => c"A pointer was null",
+        HUFFMAN_ERROR_BUFFER_TOO_SMALL => c"The output buffer is too small",
+        HUFFMAN_ERROR_TRUNCATED => c"Compressed data ended early",
+        HUFFMAN_ERROR_BAD_HEADER => c"Not compressed data, or from a newer version",
+        HUFFMAN_ERROR_CORRUPT => c"Compressed data is corrupt",
+        HUFFMAN_ERROR_CHECKSUM => c"The data does not match its checksum",
+        HUFFMAN_ERROR_MISSING_TABLE => c"Compressed with a shared table",
+        HUFFMAN_ERROR_INVALID_INPUT => c"Invalid input",
+        HUFFMAN_ERROR_PANIC => c"The library panicked",
+        _ => c"Unknown error code",
+    };
+
+    message.as_ptr()
+}
+
+/// The code returned for `error`.
+fn code(error: &HuffmanError) -> i32 {
+    match error {
+        HuffmanError::Truncated => HUFFMAN_ERROR_TRUNCATED,
+        HuffmanError::BadMagic(_) | HuffmanError::UnsupportedVersion { .. } | HuffmanError::BadHeader(_) => {
+            HUFFMAN_ERROR_BAD_HEADER
+        }
+        HuffmanError::CorruptStream(_) => HUFFMAN_ERROR_CORRUPT,
+        HuffmanError::ChecksumMismatch => HUFFMAN_ERROR_CHECKSUM,
+        HuffmanError::MissingTable => HUFFMAN_ERROR_MISSING_TABLE,
+        HuffmanError::Context { error, .. } => code(error),
+        _ => HUFFMAN_ERROR_INVALID_INPUT,
+    }
+}
+
+/// Checks the pointers, runs `function` on the input, and copies its output
+/// out if it fits.
+///
+/// # Safety
+///
+/// As for [`huffman_compress`].
+unsafe fn call(
+    buf: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_len: *mut usize,
+    function: impl FnOnce(&[u8]) -> huffman::Result<Vec<u8>>,
+) -> i32 {
+    if out_len.is_null() || buf.is_null() && len != 0 {
+        return HUFFMAN_ERROR_NULL_POINTER;
+    }
+    // SAFETY: `out_len` is not null, and the caller made it writable.
+    let capacity = unsafe { *out_len };
+    if out.is_null() && capacity != 0 {
+        return HUFFMAN_ERROR_NULL_POINTER;
+    }
+
+    let input = match len {
+        0 => &[][..],
+        // SAFETY: `buf` is not null, and the caller made it `len` readable bytes.
+        _ => unsafe { slice::from_raw_parts(buf, len) },
+    };
+    // The input is only read, so a panic leaves nothing half changed.
+    let output = match panic::catch_unwind(AssertUnwindSafe(|| function(input))) {
+        Ok(Ok(output)) => output,
+        Ok(Err(error)) => return code(&error),
+        Err(_) => return HUFFMAN_ERROR_PANIC,
+    };
+
+    // SAFETY: as above.
+    unsafe { *out_len = output.len() };
+    if output.len() > capacity {
+        return HUFFMAN_ERROR_BUFFER_TOO_SMALL;
+    }
+    if !output.is_empty() {
+        // SAFETY: `out` is not null as `capacity` is not 0, the caller made it
+        // `capacity` writable bytes, and they do not overlap `output`.
+        unsafe { ptr::copy_nonoverlapping(output.as_ptr(), out, output.len()) };
+    }
+
+    HUFFMAN_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use huffman::format::SymbolKind;
+    use huffman::SharedTable;
+    use std::ffi::CStr;
+
+    type Function = unsafe extern "C" fn(*const u8, usize, *mut u8, *mut usize) -> i32;
+
+    /// Runs `function` on `input` as a C program would: asking for the size,
+    /// then with a buffer of it.
+    fn twice(function: Function, input: &[u8]) -> Result<Vec<u8>, i32> {
+        let mut size = 0;
+        match unsafe { function(input.as_ptr(), input.len(), ptr::null_mut(), &mut size) } {
+            HUFFMAN_OK | HUFFMAN_ERROR_BUFFER_TOO_SMALL => {}
+            code => return Err(code),
+        }
+
+        let mut output = vec![0; size];
+        match unsafe { function(input.as_ptr(), input.len(), output.as_mut_ptr(), &mut size) } {
+            HUFFMAN_OK => Ok(output[..size].to_vec()),
+            code => Err(code),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for input in [&b"abbcccdddd"[..], b"", &[7; 1000], &(0..=255).collect::<Vec<u8>>()] {
+            let compressed = twice(huffman_compress, input).unwrap();
+            assert_eq!(compressed, huffman::compress_bytes(input));
+            assert_eq!(twice(huffman_decompress, &compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_buffer_too_small() {
+        let compressed = huffman::compress_bytes(b"abbcccdddd");
+        let mut output = [0xAA; 4];
+        let mut size = output.len();
+        let code = unsafe { huffman_decompress(compressed.as_ptr(), compressed.len(), output.as_mut_ptr(), &mut size) };
+        assert_eq!(code, HUFFMAN_ERROR_BUFFER_TOO_SMALL);
+        assert_eq!(size, 10);
+        assert_eq!(output, [0xAA; 4]);
+    }
+
+    #[test]
+    fn test_null_pointers() {
+        let mut size = 4;
+        let mut output = [0; 4];
+        unsafe {
+            assert_eq!(huffman_compress(ptr::null(), 1, output.as_mut_ptr(), &mut size), HUFFMAN_ERROR_NULL_POINTER);
+            assert_eq!(huffman_compress(b"a".as_ptr(), 1, ptr::null_mut(), &mut size), HUFFMAN_ERROR_NULL_POINTER);
+            assert_eq!(huffman_compress(b"a".as_ptr(), 1, output.as_mut_ptr(), ptr::null_mut()), HUFFMAN_ERROR_NULL_POINTER);
+        }
+        // An empty input may be null.
+        assert_eq!(unsafe { huffman_compress(ptr::null(), 0, ptr::null_mut(), &mut 0) }, HUFFMAN_ERROR_BUFFER_TOO_SMALL);
+    }
+
+    #[test]
+    fn test_errors() {
+        let compressed = huffman::compress_bytes(&b"abbcccdddd".repeat(100));
+        assert_eq!(twice(huffman_decompress, b"not compressed"), Err(HUFFMAN_ERROR_BAD_HEADER));
+        assert_eq!(twice(huffman_decompress, &compressed[..compressed.len() - 3]), Err(HUFFMAN_ERROR_TRUNCATED));
+
+        let table = SharedTable::from_input(SymbolKind::Bytes, b"abcd").unwrap();
+        let shared = table.compress(&b"abbcccdddd".repeat(100)).unwrap();
+        assert_eq!(twice(huffman_decompress, &shared), Err(HUFFMAN_ERROR_MISSING_TABLE));
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let message = |code| unsafe { CStr::from_ptr(huffman_error_message(code)) }.to_str().unwrap();
+        assert_eq!(message(HUFFMAN_ERROR_TRUNCATED), "Compressed data ended early");
+        assert_eq!(message(-1), "Unknown error code");
+        for code in HUFFMAN_OK..=HUFFMAN_ERROR_PANIC {
+            assert_ne!(message(code), "Unknown error code");
+        }
+    }
+}
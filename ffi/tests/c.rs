@@ -0,0 +1,42 @@
+//! Builds a C program against `include/huffman.h` and the static library, to
+//! check that the header declares what the library exports. Skipped where
+//! there is no C compiler.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The directory the libraries are built to, which holds the test's own
+/// `deps` directory.
+fn libraries() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    exe.parent().and_then(Path::parent).unwrap().to_path_buf()
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "runs a C compiler")]
+fn test_c_round_trip() {
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        eprintln!("No C compiler; skipping");
+        return;
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let program = libraries().join(format!("huffman-ffi-round-trip-{}", std::process::id()));
+    let output = Command::new(&cc)
+        .arg(root.join("tests/round_trip.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg(libraries().join("libhuffman_ffi.a"))
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&program)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new(&program).arg("abbcccdddd").output().unwrap();
+    std::fs::remove_file(&program).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let size = huffman::compress_bytes(b"abbcccdddd").len();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("{} abbcccdddd\nCompressed data ended early\n", size));
+}
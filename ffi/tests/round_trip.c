@@ -0,0 +1,42 @@
+/* Compresses and decompresses its argument through the C interface. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "huffman.h"
+
+int main(int argc, char **argv) {
+    const uint8_t *input = (const uint8_t *)(argc > 1 ? argv[1] : "");
+    size_t input_len = strlen((const char *)input);
+
+    size_t compressed_len = 0;
+    int32_t code = huffman_compress(input, input_len, NULL, &compressed_len);
+    if (code != HUFFMAN_ERROR_BUFFER_TOO_SMALL) {
+        fprintf(stderr, "sizing: %s\n", huffman_error_message(code));
+        return 1;
+    }
+    uint8_t *compressed = malloc(compressed_len);
+    code = huffman_compress(input, input_len, compressed, &compressed_len);
+    if (code != HUFFMAN_OK) {
+        fprintf(stderr, "compress: %s\n", huffman_error_message(code));
+        return 1;
+    }
+
+    uint8_t *output = malloc(input_len + 1);
+    size_t output_len = input_len;
+    code = huffman_decompress(compressed, compressed_len, output, &output_len);
+    if (code != HUFFMAN_OK) {
+        fprintf(stderr, "decompress: %s\n", huffman_error_message(code));
+        return 1;
+    }
+    output[output_len] = 0;
+    printf("%zu %s\n", compressed_len, (const char *)output);
+
+    code = huffman_decompress(compressed, compressed_len / 2, output, &output_len);
+    printf("%s\n", huffman_error_message(code));
+
+    free(compressed);
+    free(output);
+    return 0;
+}
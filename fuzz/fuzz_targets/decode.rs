@@ -0,0 +1,21 @@
+//! Arbitrary bytes as a whole compressed stream, also decoded on several
+//! threads and with a shared table, and as a JSON table.
+
+#![no_main]
+
+use std::io::Read;
+
+use huffman::format::SymbolKind;
+use huffman::{HuffmanDecoder, SharedTable};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = huffman::decompress_bytes(data);
+    let _ = HuffmanDecoder::with_threads(data, 2).read_to_end(&mut Vec::new());
+    if let Ok(table) = SharedTable::from_input(SymbolKind::Bytes, b"abbcccdddd") {
+        let _ = table.decompress(data);
+    }
+    if let Ok(json) = std::str::from_utf8(data) {
+        let _ = SharedTable::from_json(json);
+    }
+});
@@ -0,0 +1,20 @@
+//! Arbitrary bytes as a file header and the block headers after it, and as
+//! the index of an archive.
+
+#![no_main]
+
+use huffman::archive;
+use huffman::format::{BlockHeader, Header};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut rest = data;
+    let Ok(header) = Header::read(&mut rest) else {
+        return;
+    };
+    while let Ok(Some(block)) = BlockHeader::read(&header, &mut rest) {
+        let encoded_size = block.encoded_size.unwrap_or(rest.len() as u64).min(rest.len() as u64);
+        rest = &rest[encoded_size as usize..];
+    }
+    let _ = archive::read_index(&mut &data[..]);
+});
@@ -0,0 +1,13 @@
+//! Arbitrary bytes compressed each way decompress to themselves.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    assert_eq!(huffman::decompress_bytes(&huffman::compress_bytes(data)).unwrap(), data);
+    assert_eq!(huffman::decompress_bytes(&huffman::compress_adaptive(data)).unwrap(), data);
+    if let Ok(text) = std::str::from_utf8(data) {
+        assert_eq!(huffman::decompress(&huffman::compress(text)).unwrap(), text);
+    }
+});
@@ -0,0 +1,374 @@
+//! Adaptive Huffman coding (the FGK algorithm), which needs no frequency table
+//! and so codes its input in a single pass.
+//!
+//! Encoder and decoder start from the same empty tree and update it the same
+//! way after every byte. A byte seen for the first time is coded as the path
+//! to the not-yet-transmitted (NYT) leaf followed by its 8 bits.
+
+use std::io::Write;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::compress::DEFAULT_BLOCK_SIZE;
+use crate::crc::Crc32;
+use crate::encoder::after_failure;
+use crate::error::{HuffmanError, Result};
+use crate::format::{self, AdaptiveBlockHeader, Coding, Header, SymbolKind};
+
+const NONE: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Node {
+    weight: u64,
+    parent: usize,
+    /// The left and right child, or `NONE` for a leaf.
+    children: (usize, usize),
+}
+
+/// The tree shared by both ends of an adaptive stream.
+///
+/// Nodes are indexed by rank, the root first: weights never increase with the
+/// index and siblings are adjacent (the sibling property), which is what makes
+/// the tree a Huffman tree for the counts so far.
+#[derive(Debug, Clone)]
+pub(crate) struct AdaptiveModel {
+    nodes: Vec<Node>,
+    /// The leaf of each byte seen so far.
+    leaves: [usize; 256],
+    /// The byte of each leaf, by index.
+    symbols: Vec<Option<u8>>,
+    nyt: usize,
+}
+
+impl AdaptiveModel {
+    pub(crate) fn new() -> AdaptiveModel {
+        AdaptiveModel {
+            nodes: vec![Node { weight: 0, parent: NONE, children: (NONE, NONE) }],
+            leaves: [NONE; 256],
+            symbols: vec![None],
+            nyt: 0,
+        }
+    }
+
+    pub(crate) fn encode(&mut self, byte: u8, writer: &mut BitWriter) {
+        let leaf = self.leaves[byte as usize];
+        if leaf == NONE {
+            self.write_path(self.nyt, writer);
+            writer.write_bits(byte as u32, 8);
+        } else {
+            self.write_path(leaf, writer);
+        }
+
+        self.update(byte);
+    }
+
+    pub(crate) fn decode(&mut self, reader: &mut BitReader) -> Result<u8> {
+        let mut node = 0;
+        while self.nodes[node].children.0 != NONE {
+            let (left, right) = self.nodes[node].children;
+            node = if reader.read_bit().ok_or(HuffmanError::Truncated)? { right } else { left };
+        }
+
+        let byte = match self.symbols[node] {
+            Some(byte) => byte,
+            None => reader.read_bits(8).ok_or(HuffmanError::Truncated)? as u8,
+        };
+
+        self.update(byte);
+        Ok(byte)
+    }
+
+    /// Decodes the `length` bytes of a block from its encoded bits.
+    pub(crate) fn decode_block(&mut self, bits: &[u8], length: u64) -> Result<Vec<u8>> {
+        let mut reader = BitReader::new(bits);
+        let mut output = Vec::new();
+        for _ in 0..length {
+            output.push(self.decode(&mut reader)?);
+        }
+
+        Ok(output)
+    }
+
+    fn write_path(&self, node: usize, writer: &mut BitWriter) {
+        let mut path = Vec::new();
+        let mut node = node;
+        while self.nodes[node].parent != NONE {
+            let parent = self.nodes[node].parent;
+            path.push(self.nodes[parent].children.1 == node);
+            node = parent;
+        }
+
+        for &bit in path.iter().rev() {
+            writer.write_bit(bit);
+        }
+    }
+
+    /// Counts one more `byte`, giving it a leaf first if it is new.
+    fn update(&mut self, byte: u8) {
+        let mut node = self.leaves[byte as usize];
+        if node == NONE {
+            node = self.split_nyt(byte);
+        }
+
+        let mut parent_leads = false;
+        loop {
+            // Swap with the first node of the same weight, so incrementing
+            // keeps the weights in order. The parent can only share the weight
+            // of a sibling of the NYT leaf; it then stays first, and takes the
+            // same increment next, so nothing needs to be searched for it.
+            if !parent_leads {
+                let weight = self.nodes[node].weight;
+                let mut leader = self.nodes.partition_point(|other| other.weight > weight);
+                parent_leads = leader == self.nodes[node].parent;
+                if parent_leads {
+                    leader += 1;
+                }
+                if leader != node {
+                    self.swap(leader, node);
+                    node = leader;
+                }
+            } else {
+                parent_leads = false;
+            }
+
+            self.nodes[node].weight += 1;
+            match self.nodes[node].parent {
+                NONE => break,
+                parent => node = parent,
+            }
+        }
+    }
+
+    /// Turns the NYT leaf into a parent of a new NYT leaf and a leaf for
+    /// `byte`, and returns the latter.
+    fn split_nyt(&mut self, byte: u8) -> usize {
+        let parent = self.nyt;
+        let leaf = self.nodes.len();
+        let nyt = leaf + 1;
+
+        self.nodes[parent].children = (nyt, leaf);
+        self.nodes.push(Node { weight: 0, parent, children: (NONE, NONE) });
+        self.nodes.push(Node { weight: 0, parent, children: (NONE, NONE) });
+        self.symbols[parent] = None;
+        self.symbols.extend([Some(byte), None]);
+
+        self.leaves[byte as usize] = leaf;
+        self.nyt = nyt;
+        leaf
+    }
+
+    /// Swaps the subtrees at `a` and `b`, which are not ancestors of each
+    /// other. The two places keep their parents; only what hangs there moves.
+    fn swap(&mut self, a: usize, b: usize) {
+        self.nodes.swap(a, b);
+        let parent = self.nodes[a].parent;
+        self.nodes[a].parent = self.nodes[b].parent;
+        self.nodes[b].parent = parent;
+        self.symbols.swap(a, b);
+
+        for node in [a, b] {
+            let (left, right) = self.nodes[node].children;
+            if left != NONE {
+                self.nodes[left].parent = node;
+                self.nodes[right].parent = node;
+            }
+            match self.symbols[node] {
+                Some(byte) => self.leaves[byte as usize] = node,
+                None if left == NONE => self.nyt = node,
+                None => {}
+            }
+        }
+    }
+}
+
+/// Compresses the bytes written to it into `W` in a single pass with adaptive
+/// Huffman coding, without a frequency table.
+///
+/// This suits input that cannot be read twice, such as a pipe; on data that
+/// can, [`HuffmanEncoder`](crate::HuffmanEncoder) usually compresses a little
+/// better and [`HuffmanDecoder`](crate::HuffmanDecoder) reads both.
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut encoder = huffman::AdaptiveEncoder::new(Vec::new());
+/// encoder.write_all(b"abbcccdddd").unwrap();
+/// let compressed = encoder.finish().unwrap();
+///
+/// assert_eq!(huffman::decompress_bytes(&compressed).unwrap(), b"abbcccdddd");
+/// ```
+#[derive(Debug)]
+pub struct AdaptiveEncoder<W: Write> {
+    inner: W,
+    model: AdaptiveModel,
+    bits: BitWriter,
+    length: u64,
+    started: bool,
+    crc: Crc32,
+    /// Whether writing to the inner writer failed, as for
+    /// [`HuffmanEncoder`](crate::HuffmanEncoder).
+    failed: bool,
+}
+
+impl<W: Write> AdaptiveEncoder<W> {
+    pub fn new(inner: W) -> AdaptiveEncoder<W> {
+        AdaptiveEncoder {
+            inner,
+            model: AdaptiveModel::new(),
+            bits: BitWriter::new(),
+            length: 0,
+            started: false,
+            crc: Crc32::new(),
+            failed: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Writing to the inner writer directly will corrupt the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Writes the buffered bits, the end of the stream and its checksum, and
+    /// returns the inner writer. Fails if any write to it failed before.
+    pub fn finish(mut self) -> Result<W> {
+        self.write_buffered()?;
+        AdaptiveBlockHeader::write_end(&mut self.inner)?;
+        format::write_checksum(&mut self.inner, self.crc.finish())?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+    fn write_buffered(&mut self) -> Result<()> {
+        if self.failed {
+            return Err(after_failure());
+        }
+
+        self.failed = true;
+        if !self.started {
+            Header { coding: Coding::Adaptive, ..Header::new(SymbolKind::Bytes) }.write(&mut self.inner)?;
+            self.started = true;
+        }
+
+        if self.length > 0 {
+            let bytes = std::mem::take(&mut self.bits).finish();
+            AdaptiveBlockHeader { length: self.length, encoded_size: bytes.len() as u64 }.write(&mut self.inner)?;
+            self.inner.write_all(&bytes)?;
+            self.length = 0;
+        }
+        self.failed = false;
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for AdaptiveEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // Blocks only bound the buffered bits; the model carries on across
+        // them.
+        if self.length == DEFAULT_BLOCK_SIZE as u64 {
+            self.write_buffered()?;
+        }
+
+        let length = buf.len().min(DEFAULT_BLOCK_SIZE - self.length as usize);
+        for &byte in &buf[..length] {
+            self.model.encode(byte, &mut self.bits);
+        }
+        self.length += length as u64;
+        self.crc.update(&buf[..length]);
+
+        Ok(length)
+    }
+
+    /// Writes out the bits coded so far, padded to a whole byte.
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.write_buffered()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(input: &[u8]) -> Vec<u8> {
+        let mut model = AdaptiveModel::new();
+        let mut writer = BitWriter::new();
+        for &byte in input {
+            model.encode(byte, &mut writer);
+        }
+        let bytes = writer.finish();
+
+        let mut model = AdaptiveModel::new();
+        let mut reader = BitReader::new(&bytes);
+        (0..input.len()).map(|_| model.decode(&mut reader).unwrap()).collect()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "too slow under Miri")]
+    fn test_model_round_trip() {
+        let inputs: [&[u8]; 4] = [b"a", b"abbcccdddd", b"test\nmyreallycooltest", &[0xFF; 100]];
+        for input in inputs {
+            assert_eq!(round_trip(input), input);
+        }
+
+        let input: Vec<u8> = (0..20_000u32).map(|i| ((i * i % 251) ^ (i % 7)) as u8).collect();
+        assert_eq!(round_trip(&input), input);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "too slow under Miri")]
+    fn test_sibling_property() {
+        let mut model = AdaptiveModel::new();
+        let mut writer = BitWriter::new();
+        let input: Vec<u8> = (0..3000u32).map(|i| (i * i % 61 + i % 13) as u8).collect();
+        for &byte in b"abracadabra, mississippi".iter().chain(&input) {
+            model.encode(byte, &mut writer);
+
+            for (index, node) in model.nodes.iter().enumerate() {
+                if index > 0 {
+                    assert!(model.nodes[index - 1].weight >= node.weight);
+                }
+                if node.children.0 != NONE {
+                    let (left, right) = node.children;
+                    assert_eq!(node.weight, model.nodes[left].weight + model.nodes[right].weight);
+                    assert_eq!((model.nodes[left].parent, model.nodes[right].parent), (index, index));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_adapts() {
+        // The first byte is sent as a literal, and each repeat of it takes a
+        // single bit.
+        let mut model = AdaptiveModel::new();
+        let mut writer = BitWriter::new();
+        for _ in 0..17 {
+            model.encode(b'a', &mut writer);
+        }
+
+        assert_eq!(writer.finish().len(), 3);
+    }
+
+    #[test]
+    fn test_encoder_faulty_writer() {
+        use crate::faulty::{Faults, FaultyWriter};
+
+        let faults = Faults { short: Some(1), interrupt: true, ..Faults::default() };
+        let mut encoder = AdaptiveEncoder::new(FaultyWriter::new(Vec::new(), faults));
+        encoder.write_all(b"abracadabra").unwrap();
+        let compressed = encoder.finish().unwrap().inner;
+        assert_eq!(crate::decompress_bytes(&compressed).unwrap(), b"abracadabra");
+
+        let faults = Faults { fail_after: Some(20), ..Faults::default() };
+        let mut encoder = AdaptiveEncoder::new(FaultyWriter::new(Vec::new(), faults));
+        encoder.write_all(b"abracadabra").unwrap();
+        assert!(encoder.flush().is_err());
+        assert!(encoder.flush().is_err());
+        assert!(encoder.finish().is_err());
+    }
+}
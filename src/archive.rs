@@ -0,0 +1,171 @@
+//! Archives: several compressed streams in one file, each under a name.
+//!
+//! An archive starts with the [`MAGIC`] bytes, a version as a `u8` and the
+//! number of entries as a `u32`, followed by the index, which holds for each
+//! entry:
+//!
+//! - the length of its name in bytes as a `u32`, and the name as UTF-8,
+//! - the size of its decompressed contents as a `u64`,
+//! - the offset of its stream from the start of the archive as a `u64`,
+//! - the size of its stream as a `u64`.
+//!
+//! The streams follow the index, each a whole compressed file (see
+//! [`format`](crate::format)). As in that format, all integers are
+//! little-endian.
+//!
+//! The index is written before the streams are known, and written again with
+//! their offsets and sizes once they are:
+//!
+//! ```
+//! use huffman::archive::{self, Entry};
+//!
+//! let streams = [huffman::compress("abbcccdddd"), huffman::compress("霍夫曼")];
+//! let mut entries = vec![Entry::new("a.txt"), Entry::new("b.txt")];
+//!
+//! let mut offset = archive::index_size(&entries);
+//! for (entry, stream) in entries.iter_mut().zip(&streams) {
+//!     entry.offset = offset;
+//!     entry.compressed_size = stream.len() as u64;
+//!     offset += entry.compressed_size;
+//! }
+//!
+//! let mut bytes = Vec::new();
+//! archive::write_index(&mut bytes, &entries)?;
+//! bytes.extend(streams.concat());
+//!
+//! let index = archive::read_index(&mut bytes.as_slice())?;
+//! let start = index[1].offset as usize;
+//! let end = start + index[1].compressed_size as usize;
+//! assert_eq!(huffman::decompress(&bytes[start..end])?, "霍夫曼");
+//! # Ok::<(), huffman::HuffmanError>(())
+//! ```
+
+use std::io::{Read, Write};
+
+use crate::error::{Container, HuffmanError, Result};
+use crate::format::{fill, take};
+
+/// The start of every archive, telling it apart from a single compressed
+/// file.
+pub const MAGIC: [u8; 4] = *b"HUFA";
+
+/// The latest archive version.
+pub const VERSION: u8 = 1;
+
+/// A file stored in an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    /// The size of the decompressed contents in bytes.
+    pub size: u64,
+    /// Where the compressed stream starts, from the start of the archive.
+    pub offset: u64,
+    /// The size of the compressed stream in bytes.
+    pub compressed_size: u64,
+}
+
+impl Entry {
+    /// An entry for `name` whose sizes and offset are not yet known.
+    pub fn new(name: &str) -> Entry {
+        Entry { name: name.to_string(), size: 0, offset: 0, compressed_size: 0 }
+    }
+}
+
+/// The size in bytes of the start of an archive of `entries`, which is where
+/// the first stream goes.
+pub fn index_size(entries: &[Entry]) -> u64 {
+    let names: usize = entries.iter().map(|entry| entry.name.len()).sum();
+    (MAGIC.len() + 1 + 4 + entries.len() * (4 + 3 * 8) + names) as u64
+}
+
+/// Writes the start of an archive of `entries`.
+///
+/// Fails with [`HuffmanError::InvalidInput`] if there are too many entries or
+/// a name is too long to store.
+pub fn write_index<W: Write>(output: &mut W, entries: &[Entry]) -> Result<()> {
+    let count = u32::try_from(entries.len())
+        .map_err(|_| HuffmanError::InvalidInput("Too many entries for an archive".to_string()))?;
+
+    output.write_all(&MAGIC)?;
+    output.write_all(&[VERSION])?;
+    output.write_all(&count.to_le_bytes())?;
+    for entry in entries {
+        let length = u32::try_from(entry.name.len())
+            .map_err(|_| HuffmanError::InvalidInput(format!("Name too long for an archive: {}", entry.name)))?;
+        output.write_all(&length.to_le_bytes())?;
+        output.write_all(entry.name.as_bytes())?;
+        for value in [entry.size, entry.offset, entry.compressed_size] {
+            output.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the index from the start of an archive.
+///
+/// Fails with [`HuffmanError::BadMagic`] if this is not an archive, with
+/// [`HuffmanError::UnsupportedVersion`] if it is of another version, with
+/// [`HuffmanError::BadHeader`] if a name is not UTF-8, and with
+/// [`HuffmanError::Truncated`] if the index is cut short.
+pub fn read_index<R: Read>(input: &mut R) -> Result<Vec<Entry>> {
+    if take::<4>(input)? != MAGIC {
+        return Err(HuffmanError::BadMagic(Container::Archive));
+    }
+    let version = take::<1>(input)?[0];
+    if version != VERSION {
+        return Err(HuffmanError::UnsupportedVersion { container: Container::Archive, version });
+    }
+
+    let count = u32::from_le_bytes(take(input)?);
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let length = u32::from_le_bytes(take(input)?) as u64;
+        let mut name = Vec::new();
+        input.take(length).read_to_end(&mut name)?;
+        if name.len() as u64 != length {
+            return Err(HuffmanError::Truncated);
+        }
+        let name = String::from_utf8(name).map_err(|_| HuffmanError::BadHeader("Name is not UTF-8".to_string()))?;
+
+        let mut values = [0; 24];
+        fill(input, &mut values)?;
+        let value = |index: usize| u64::from_le_bytes(values[index * 8..index * 8 + 8].try_into().unwrap());
+        entries.push(Entry { name, size: value(0), offset: value(1), compressed_size: value(2) });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_write() {
+        let entries = vec![
+            Entry { name: "notes.txt".to_string(), size: 10, offset: 60, compressed_size: 40 },
+            Entry { name: "霍夫曼".to_string(), size: 0, offset: 100, compressed_size: 16 },
+        ];
+
+        let mut bytes = Vec::new();
+        write_index(&mut bytes, &entries).unwrap();
+        assert_eq!(bytes.len() as u64, index_size(&entries));
+        assert_eq!(read_index(&mut bytes.as_slice()).unwrap(), entries);
+
+        for length in [0, 5, bytes.len() - 1] {
+            assert!(matches!(read_index(&mut &bytes[..length]).unwrap_err(), HuffmanError::Truncated));
+        }
+    }
+
+    #[test]
+    fn test_not_an_archive() {
+        let error = read_index(&mut crate::compress("abc").as_slice()).unwrap_err();
+        assert!(matches!(error, HuffmanError::BadMagic(Container::Archive)));
+
+        let mut bytes = Vec::new();
+        write_index(&mut bytes, &[Entry::new("a")]).unwrap();
+        bytes[13] = 0xFF;
+        assert!(matches!(read_index(&mut bytes.as_slice()).unwrap_err(), HuffmanError::BadHeader(_)));
+    }
+}
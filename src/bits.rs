@@ -0,0 +1,154 @@
+use alloc::vec::Vec;
+
+/// Packs bits into bytes, most significant bit first.
+#[derive(Debug, Default)]
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    filled: u8,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> BitWriter {
+        BitWriter::default()
+    }
+
+    pub(crate) fn write_bit(&mut self, bit: bool) {
+        self.current = (self.current << 1) | bit as u8;
+        self.filled += 1;
+
+        if self.filled == 8 {
+            self.bytes.push(self.current);
+            self.current = 0;
+            self.filled = 0;
+        }
+    }
+
+    /// Writes the lowest `width` bits of `value`, most significant first.
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn write_bits(&mut self, value: u32, width: u8) {
+        for i in (0..width).rev() {
+            self.write_bit(value >> i & 1 == 1);
+        }
+    }
+
+    /// Pads the last byte with zero bits and returns the packed bytes.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.current << (8 - self.filled));
+        }
+
+        self.bytes
+    }
+}
+
+/// Reads bits out of bytes, most significant bit first.
+#[derive(Debug)]
+pub(crate) struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> BitReader<'a> {
+        BitReader { bytes, position: 0 }
+    }
+
+    /// Returns `None` once every bit has been read.
+    pub(crate) fn read_bit(&mut self) -> Option<bool> {
+        let byte = self.bytes.get(self.position / 8)?;
+        let bit = byte >> (7 - self.position % 8) & 1;
+        self.position += 1;
+
+        Some(bit == 1)
+    }
+
+    /// Returns the next `width` bits, between 1 and 24, without reading them,
+    /// padded with zeros past the end, along with the number of bits left.
+    pub(crate) fn peek_bits(&self, width: u8) -> (u32, usize) {
+        let start = self.position / 8;
+        let window = match self.bytes.get(start..start + 4) {
+            Some(window) => window.try_into().unwrap(),
+            None => {
+                let mut window = [0; 4];
+                let rest = &self.bytes[start.min(self.bytes.len())..];
+                window[..rest.len()].copy_from_slice(rest);
+                window
+            }
+        };
+        let bits = u32::from_be_bytes(window) << (self.position % 8);
+
+        (bits >> (32 - width), self.bytes.len() * 8 - self.position)
+    }
+
+    /// Skips `width` bits, which must be no more than are left.
+    pub(crate) fn skip(&mut self, width: usize) {
+        self.position += width;
+    }
+
+    /// Reads `width` bits, most significant first, as written by
+    /// [`BitWriter::write_bits`].
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn read_bits(&mut self, width: u8) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..width {
+            value = value << 1 | self.read_bit()? as u32;
+        }
+
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_bits() {
+        let mut writer = BitWriter::new();
+        for bit in [true, false, true, true, false, false, false, true, true, true] {
+            writer.write_bit(bit);
+        }
+
+        assert_eq!(writer.finish(), vec![0b1011_0001, 0b1100_0000]);
+    }
+
+    #[test]
+    fn test_read_bits() {
+        let mut reader = BitReader::new(&[0b1011_0001, 0b1100_0000]);
+        let bits: Vec<bool> = std::iter::from_fn(|| reader.read_bit()).collect();
+
+        assert_eq!(bits.len(), 16);
+        assert_eq!(&bits[..10], [true, false, true, true, false, false, false, true, true, true]);
+        assert!(bits[10..].iter().all(|bit| !bit));
+    }
+
+    #[test]
+    fn test_multi_bit_values() {
+        let mut writer = BitWriter::new();
+        writer.write_bit(true);
+        writer.write_bits(0x1F600, 21);
+        writer.write_bits(0xFFFF_FFFF, 32);
+        let bytes = writer.finish();
+
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.read_bit(), Some(true));
+        assert_eq!(reader.read_bits(21), Some(0x1F600));
+        assert_eq!(reader.read_bits(32), Some(0xFFFF_FFFF));
+        assert_eq!(reader.read_bits(8), None);
+    }
+
+    #[test]
+    fn test_peek_bits() {
+        let mut reader = BitReader::new(&[0b1011_0001, 0b1100_0000]);
+        assert_eq!(reader.peek_bits(3), (0b101, 16));
+        assert_eq!(reader.read_bit(), Some(true));
+        assert_eq!(reader.peek_bits(10), (0b01_1000_1110, 15));
+
+        reader.skip(13);
+        assert_eq!(reader.peek_bits(8), (0b0000_0000, 2));
+        reader.skip(2);
+        assert_eq!(reader.peek_bits(24), (0, 0));
+        assert_eq!(reader.read_bit(), None);
+    }
+}
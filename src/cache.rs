@@ -0,0 +1,164 @@
+//! A cache of the tables files were compressed with, keyed by a fingerprint
+//! of their contents, so compressing an unchanged file again skips choosing
+//! tables.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use huffman::Tables;
+
+/// The most bytes of tables kept; the least recently used are removed beyond
+/// this.
+pub const DEFAULT_LIMIT: u64 = 64 << 20;
+
+const ENTRY_EXTENSION: &str = "tables";
+
+#[derive(Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    limit: u64,
+}
+
+impl Cache {
+    /// The cache in `huffman-rs/cache` under the config directory, which is
+    /// `$XDG_CONFIG_HOME` or else `$HOME/.config`, or `None` if neither is set.
+    pub fn open() -> Option<Cache> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(Cache::at(config.join("huffman-rs").join("cache"), DEFAULT_LIMIT))
+    }
+
+    /// A cache in `dir` holding at most `limit` bytes.
+    pub fn at(dir: PathBuf, limit: u64) -> Cache {
+        Cache { dir, limit }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// The tables stored for `key`, if any and readable.
+    pub fn get(&self, key: u64) -> Option<Tables> {
+        let path = self.path(key);
+        let file = File::open(&path).ok()?;
+        let tables = Tables::read(&mut BufReader::new(&file)).ok()?;
+
+        // Keep recently used entries the longest.
+        let _ = file.set_modified(SystemTime::now());
+        Some(tables)
+    }
+
+    /// Stores `tables` for `key`, then removes the least recently used entries
+    /// beyond the size limit.
+    pub fn put(&self, key: u64, tables: &Tables) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        // Write to the side first, so no one reads half an entry.
+        let path = self.path(key);
+        let partial = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        tables.write(&mut writer)?;
+        writer.flush()?;
+        fs::rename(&partial, &path)?;
+
+        self.evict()
+    }
+
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.path().extension().is_some_and(|extension| extension == ENTRY_EXTENSION) {
+                let metadata = entry.metadata()?;
+                entries.push((metadata.modified()?, metadata.len(), entry.path()));
+            }
+        }
+        entries.sort_unstable();
+
+        let mut total: u64 = entries.iter().map(|&(_, size, _)| size).sum();
+        for (_, size, path) in entries {
+            if total <= self.limit {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+
+        Ok(())
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.{}", key, ENTRY_EXTENSION))
+    }
+}
+
+/// A 64-bit FNV-1a hash of `settings` and `contents`, telling apart inputs that
+/// would be compressed differently.
+pub fn fingerprint(contents: &[u8], settings: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in settings.as_bytes().iter().chain(&[0]).chain(contents) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str, limit: u64) -> Cache {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Cache::at(dir, limit)
+    }
+
+    #[test]
+    fn test_get_put() {
+        let cache = temp_cache("get-put", DEFAULT_LIMIT);
+        let tables = Tables::from_compressed(&huffman::compress("abbcccdddd")).unwrap();
+
+        assert_eq!(cache.get(1), None);
+        cache.put(1, &tables).unwrap();
+        assert_eq!(cache.get(1), Some(tables));
+        assert_eq!(cache.get(2), None);
+
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn test_limit() {
+        let tables = Tables::from_compressed(&huffman::compress("abbcccdddd")).unwrap();
+        let mut bytes = Vec::new();
+        tables.write(&mut bytes).unwrap();
+
+        // Room for two entries.
+        let cache = temp_cache("limit", 2 * bytes.len() as u64);
+        for key in 0..3 {
+            cache.put(key, &tables).unwrap();
+        }
+
+        let kept = (0..3).filter(|&key| cache.path(key).exists()).count();
+        assert_eq!(kept, 2);
+        assert!(cache.path(2).exists());
+
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(b"abc", "auto"), fingerprint(b"abc", "auto"));
+        assert_ne!(fingerprint(b"abc", "auto"), fingerprint(b"abd", "auto"));
+        assert_ne!(fingerprint(b"abc", "auto"), fingerprint(b"abc", "u16le"));
+    }
+}
@@ -0,0 +1,611 @@
+//! Command line parsing.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::messages::text;
+
+/// The extension given to compressed files.
+pub const EXTENSION: &str = "huf";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Compress {
+        input: PathBuf,
+        output: PathBuf,
+        symbols: Symbols,
+        adaptive: bool,
+        optimal: bool,
+        /// `None` to use every CPU.
+        threads: Option<usize>,
+        cache: bool,
+        if_changed: bool,
+        verify: bool,
+        /// Remove the input once the output is on disk.
+        remove: bool,
+        progress: bool,
+        /// Print the compression statistics.
+        stats: bool,
+        /// Where to write the frequencies of the input as a shared table.
+        dump_table: Option<PathBuf>,
+        /// The shared table to code with.
+        use_table: Option<PathBuf>,
+        /// Map the input into memory rather than read it.
+        mmap: bool,
+    },
+    Decompress {
+        input: PathBuf,
+        output: PathBuf,
+        /// `None` to use every CPU.
+        threads: Option<usize>,
+        progress: bool,
+        /// The shared table the input was coded with.
+        use_table: Option<PathBuf>,
+    },
+    /// Compress several files into one archive.
+    Archive {
+        inputs: Vec<PathBuf>,
+        output: PathBuf,
+        symbols: Symbols,
+        adaptive: bool,
+        optimal: bool,
+        /// `None` to use every CPU.
+        threads: Option<usize>,
+        /// Check each entry against its input once written.
+        verify: bool,
+        /// Remove the inputs once the archive is on disk.
+        remove: bool,
+    },
+    /// Restore the files of an archive into a directory.
+    Extract {
+        input: PathBuf,
+        output: PathBuf,
+        /// `None` to use every CPU.
+        threads: Option<usize>,
+    },
+    Stats { input: PathBuf, verbose: bool },
+    /// Predicts the size `compress` would make `input`, writing nothing.
+    DryRun { input: PathBuf, symbols: Symbols },
+    /// Copies the entries named `entries` of one archive into another as they
+    /// are compressed.
+    Copy { source: PathBuf, destination: PathBuf, entries: Vec<String> },
+    Help,
+    /// With `verbose`, also what this build supports.
+    Version { verbose: bool },
+}
+
+impl Command {
+    /// The command as typed, or the option asking for it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Compress { .. } | Command::Archive { .. } | Command::DryRun { .. } => "compress",
+            Command::Decompress { .. } => "decompress",
+            Command::Extract { .. } => "extract",
+            Command::Stats { .. } => "stats",
+            Command::Copy { .. } => "archive",
+            Command::Help => "--help",
+            Command::Version { .. } => "--version",
+        }
+    }
+}
+
+/// How `compress` splits the input into symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symbols {
+    Auto,
+    U16Le,
+}
+
+/// The command line, parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Parsed {
+    /// The command, or a message for the user if the arguments are not valid.
+    pub command: Result<Command, String>,
+    /// Whether `--json` asks for failures as JSON, which is known even if the
+    /// arguments are not valid, so that their error comes out as JSON too.
+    pub json: bool,
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Parsed {
+    let mut json = false;
+    let command = parse_command(args, &mut json);
+
+    Parsed { command, json }
+}
+
+/// Parses the arguments into a command, noting `--json` in `json`. An invalid
+/// option is only reported once all are read, so that a later `--json` counts.
+fn parse_command(args: impl IntoIterator<Item = String>, json: &mut bool) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut error = None;
+    let mut command = None;
+    let mut inputs = Vec::new();
+    let mut output = None;
+    let mut symbols = None;
+    let mut verbose = false;
+    let mut adaptive = false;
+    let mut optimal = false;
+    let mut threads = None;
+    let mut cache = false;
+    let mut if_changed = false;
+    let mut verify = false;
+    let mut archive = false;
+    let mut remove = false;
+    let mut progress = false;
+    let mut stats = false;
+    let mut dump_table = None;
+    let mut use_table = None;
+    let mut mmap = false;
+    let mut version = false;
+    let mut dry_run = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" if error.is_none() => return Ok(Command::Help),
+            "-V" | "--version" => version = true,
+            "-v" | "--verbose" => verbose = true,
+            "--adaptive" => adaptive = true,
+            "--optimal" => optimal = true,
+            "--cache" => cache = true,
+            "--if-changed" => if_changed = true,
+            "--verify" => verify = true,
+            "--archive" => archive = true,
+            "--rm" => remove = true,
+            "--progress" => progress = true,
+            "--stats" => stats = true,
+            "--mmap" => mmap = true,
+            "--dry-run" => dry_run = true,
+            // For any command.
+            "--json" => *json = true,
+            "--threads" => match args.next().map(|value| value.parse::<usize>()) {
+                Some(Ok(count)) if count > 0 => threads = Some(count),
+                Some(_) => _ = error.get_or_insert(text!(ThreadsNeedsPositive)),
+                None => _ = error.get_or_insert(text!(ThreadsNeedsNumber)),
+            },
+            "-o" | "--output" => match args.next() {
+                Some(value) => output = Some(PathBuf::from(value)),
+                None => _ = error.get_or_insert(text!(NeedsFileName, arg)),
+            },
+            "--dump-table" | "--use-table" => match args.next() {
+                Some(value) if arg == "--dump-table" => dump_table = Some(PathBuf::from(value)),
+                Some(value) => use_table = Some(PathBuf::from(value)),
+                None => _ = error.get_or_insert(text!(NeedsFileName, arg)),
+            },
+            "--symbols" => match args.next().as_deref() {
+                Some("auto") => symbols = Some(Symbols::Auto),
+                Some("u16le") => symbols = Some(Symbols::U16Le),
+                Some(kind) => _ = error.get_or_insert(text!(UnknownSymbolKind, kind)),
+                None => _ = error.get_or_insert(text!(SymbolsNeedsKind)),
+            },
+            option if option.starts_with('-') && option != "-" => _ = error.get_or_insert(text!(UnknownOption, option)),
+            _ if command.is_none() => command = Some(arg),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if let Some(error) = error {
+        return Err(error);
+    }
+    if command.as_deref() == Some("version") {
+        if let Some(input) = inputs.first() {
+            return Err(text!(UnexpectedArgument, input.display()));
+        }
+        version = true;
+    }
+    if version {
+        return Ok(Command::Version { verbose });
+    }
+
+    let command = command.ok_or_else(|| text!(NoCommand))?;
+    if inputs.is_empty() {
+        return Err(text!(NeedsFile, command));
+    }
+    archive |= inputs.len() > 1 && command != "archive";
+    if archive && command != "compress" {
+        return Err(match inputs.get(1) {
+            Some(arg) => text!(UnexpectedArgument, arg.display()),
+            None => text!(OnlyCompress, "--archive"),
+        });
+    }
+    if archive && inputs.len() > 1 && output.is_none() {
+        return Err(text!(ArchiveNeedsOutput));
+    }
+    if symbols.is_some() && command != "compress" {
+        return Err(text!(OnlyCompress, "--symbols"));
+    }
+    if adaptive && command != "compress" {
+        return Err(text!(OnlyCompress, "--adaptive"));
+    }
+    if optimal && command != "compress" {
+        return Err(text!(OnlyCompress, "--optimal"));
+    }
+    if adaptive && symbols.is_some() {
+        return Err(text!(AdaptiveNoSymbols));
+    }
+    if adaptive && optimal {
+        return Err(text!(AdaptiveNoOptimal));
+    }
+    if cache && (command != "compress" || adaptive || archive) {
+        return Err(text!(OnlyCompressWithTables, "--cache"));
+    }
+    if if_changed && (command != "compress" || adaptive || archive) {
+        return Err(text!(OnlyCompressWithTables, "--if-changed"));
+    }
+    if verify && command != "compress" {
+        return Err(text!(OnlyCompress, "--verify"));
+    }
+    if remove && command != "compress" {
+        return Err(text!(OnlyCompress, "--rm"));
+    }
+    if remove && if_changed {
+        return Err(text!(IfChangedNoRemove));
+    }
+    if progress && !(command == "compress" && !archive || command == "decompress") {
+        return Err(text!(OnlyCompressOrDecompress, "--progress"));
+    }
+    let piped = inputs.iter().any(|input| is_stdio(input)) || output.as_deref().is_some_and(is_stdio);
+    if piped && (archive || command == "extract" || command == "archive") {
+        return Err(text!(ArchivesNotPiped));
+    }
+    if piped && (if_changed || verify || remove) {
+        return Err(text!(FilesNotPiped));
+    }
+    if piped && command == "stats" {
+        return Err(text!(StatsNotPiped));
+    }
+    if dump_table.is_some() && (command != "compress" || adaptive || archive) {
+        return Err(text!(OnlyCompressWithTables, "--dump-table"));
+    }
+    if use_table.is_some() && !(command == "compress" && !archive || command == "decompress") {
+        return Err(text!(OnlyCompressOrDecompress, "--use-table"));
+    }
+    let shared = use_table.is_some() && command == "compress";
+    let has_blocks =
+        command == "compress" && !adaptive && !optimal && !shared || command == "decompress" || command == "extract";
+    if threads.is_some() && !has_blocks {
+        return Err(text!(OnlyFixedBlocks, "--threads"));
+    }
+    if verbose && command != "stats" && command != "compress" {
+        return Err(text!(OnlyStatsOrCompress, "--verbose"));
+    }
+    stats |= verbose && command == "compress";
+    if stats && (command != "compress" || adaptive || archive) {
+        return Err(text!(OnlyCompressWithTables, "--stats"));
+    }
+    if shared && (symbols.is_some() || adaptive || optimal || cache || stats || dump_table.is_some()) {
+        return Err(text!(UseTableNoOptions));
+    }
+    if mmap && (command != "compress" || adaptive || archive) {
+        return Err(text!(OnlyCompressWithTables, "--mmap"));
+    }
+    if mmap && inputs.iter().any(|input| is_stdio(input)) {
+        return Err(text!(MmapNotPiped));
+    }
+    if mmap && progress {
+        return Err(text!(MmapNoProgress));
+    }
+    if mmap && !cfg!(feature = "mmap") {
+        return Err(text!(MmapNotBuilt));
+    }
+
+    if dry_run && (command != "compress" || archive) {
+        return Err(text!(OnlyCompressOfOneFile, "--dry-run"));
+    }
+    let writes = output.is_some() || if_changed || verify || remove || dump_table.is_some() || mmap;
+    let codes = adaptive || optimal || threads.is_some() || cache || progress || use_table.is_some();
+    if dry_run && (writes || codes) {
+        return Err(text!(DryRunNoOptions));
+    }
+
+    let mut inputs = inputs.into_iter();
+    let input = inputs.next().expect("There is an input");
+    match command.as_str() {
+        "compress" if dry_run => Ok(Command::DryRun { input, symbols: symbols.unwrap_or(Symbols::Auto) }),
+        "compress" if archive => Ok(Command::Archive {
+            output: output.unwrap_or_else(|| compressed_name(&input)),
+            inputs: std::iter::once(input).chain(inputs).collect(),
+            symbols: symbols.unwrap_or(Symbols::Auto),
+            adaptive,
+            optimal,
+            threads,
+            verify,
+            remove,
+        }),
+        "compress" => Ok(Command::Compress {
+            output: output.unwrap_or_else(|| compressed_name(&input)),
+            input,
+            symbols: symbols.unwrap_or(Symbols::Auto),
+            adaptive,
+            optimal,
+            threads,
+            cache,
+            if_changed,
+            verify,
+            remove,
+            progress,
+            stats,
+            dump_table,
+            use_table,
+            mmap,
+        }),
+        "decompress" => Ok(Command::Decompress {
+            output: output.unwrap_or_else(|| decompressed_name(&input)),
+            input,
+            threads,
+            progress,
+            use_table,
+        }),
+        "extract" => Ok(Command::Extract { input, output: output.unwrap_or_else(|| PathBuf::from(".")), threads }),
+        "archive" if output.is_some() => Err(text!(CopyNoOutput)),
+        "archive" => match (input.to_str(), inputs.next(), inputs.next()) {
+            (Some("copy"), Some(source), Some(destination)) if inputs.len() > 0 => Ok(Command::Copy {
+                source,
+                destination,
+                entries: inputs.map(|entry| entry.to_string_lossy().into_owned()).collect(),
+            }),
+            (Some("copy"), ..) => Err(text!(CopyNeedsArguments)),
+            _ => Err(text!(UnknownArchiveCommand, input.display())),
+        },
+        "stats" if output.is_some() => Err(text!(StatsNoOutput)),
+        "stats" => Ok(Command::Stats { input, verbose }),
+        command => Err(text!(UnknownCommand, command)),
+    }
+}
+
+/// Whether `path` is `-`, which stands for standard input or output.
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// `input` with `.huf` appended, or `-` for `-`.
+fn compressed_name(input: &Path) -> PathBuf {
+    if is_stdio(input) {
+        return input.to_path_buf();
+    }
+    let mut name = OsString::from(input);
+    name.push(".");
+    name.push(EXTENSION);
+    PathBuf::from(name)
+}
+
+/// `input` without its `.huf` extension, or with `.out` appended if it has
+/// none, so the input is never overwritten; `-` for `-`.
+fn decompressed_name(input: &Path) -> PathBuf {
+    if is_stdio(input) {
+        input.to_path_buf()
+    } else if input.extension().is_some_and(|extension| extension == EXTENSION) {
+        input.with_extension("")
+    } else {
+        let mut name = OsString::from(input);
+        name.push(".out");
+        PathBuf::from(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<Command, String> {
+        parse(args.split_whitespace().map(String::from)).command
+    }
+
+    #[test]
+    fn test_default_names() {
+        assert_eq!(parse_args("compress notes.txt"), Ok(Command::Compress {
+            input: "notes.txt".into(),
+            output: "notes.txt.huf".into(),
+            symbols: Symbols::Auto,
+            adaptive: false,
+            optimal: false,
+            threads: None,
+            cache: false,
+            if_changed: false,
+            verify: false,
+            remove: false,
+            progress: false,
+            stats: false,
+            dump_table: None,
+            use_table: None,
+            mmap: false,
+        }));
+        assert_eq!(parse_args("decompress notes.txt.huf"), Ok(Command::Decompress {
+            input: "notes.txt.huf".into(),
+            output: "notes.txt".into(),
+            threads: None,
+            progress: false,
+            use_table: None,
+        }));
+        assert_eq!(parse_args("decompress notes"), Ok(Command::Decompress {
+            input: "notes".into(),
+            output: "notes.out".into(),
+            threads: None,
+            progress: false,
+            use_table: None,
+        }));
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(parse_args("compress -o out.bin --symbols u16le tokens"), Ok(Command::Compress {
+            input: "tokens".into(),
+            output: "out.bin".into(),
+            symbols: Symbols::U16Le,
+            adaptive: false,
+            optimal: false,
+            threads: None,
+            cache: false,
+            if_changed: false,
+            verify: false,
+            remove: false,
+            progress: false,
+            stats: false,
+            dump_table: None,
+            use_table: None,
+            mmap: false,
+        }));
+        assert_eq!(parse_args("compress --adaptive log"), Ok(Command::Compress {
+            input: "log".into(),
+            output: "log.huf".into(),
+            symbols: Symbols::Auto,
+            adaptive: true,
+            optimal: false,
+            threads: None,
+            cache: false,
+            if_changed: false,
+            verify: false,
+            remove: false,
+            progress: false,
+            stats: false,
+            dump_table: None,
+            use_table: None,
+            mmap: false,
+        }));
+        assert_eq!(parse_args("compress --optimal --symbols u16le tokens"), Ok(Command::Compress {
+            input: "tokens".into(),
+            output: "tokens.huf".into(),
+            symbols: Symbols::U16Le,
+            adaptive: false,
+            optimal: true,
+            threads: None,
+            cache: false,
+            if_changed: false,
+            verify: false,
+            remove: false,
+            progress: false,
+            stats: false,
+            dump_table: None,
+            use_table: None,
+            mmap: false,
+        }));
+        assert_eq!(parse_args("compress --threads 8 big.log"), Ok(Command::Compress {
+            input: "big.log".into(),
+            output: "big.log.huf".into(),
+            symbols: Symbols::Auto,
+            adaptive: false,
+            optimal: false,
+            threads: Some(8),
+            cache: false,
+            if_changed: false,
+            verify: false,
+            remove: false,
+            progress: false,
+            stats: false,
+            dump_table: None,
+            use_table: None,
+            mmap: false,
+        }));
+        assert!(matches!(parse_args("compress --cache nightly.tar"), Ok(Command::Compress { cache: true, .. })));
+        assert!(matches!(parse_args("decompress --threads 4 a.huf"), Ok(Command::Decompress { threads: Some(4), .. })));
+        assert!(matches!(parse_args("compress --if-changed a"), Ok(Command::Compress { if_changed: true, .. })));
+        assert!(matches!(parse_args("compress --adaptive --verify a"), Ok(Command::Compress { verify: true, .. })));
+        assert_eq!(parse_args("compress -o notes.huf a.txt b.txt"), Ok(Command::Archive {
+            inputs: vec!["a.txt".into(), "b.txt".into()],
+            output: "notes.huf".into(),
+            symbols: Symbols::Auto,
+            adaptive: false,
+            optimal: false,
+            threads: None,
+            verify: false,
+            remove: false,
+        }));
+        assert!(matches!(parse_args("compress --archive --rm a"), Ok(Command::Archive { remove: true, .. })));
+        let archive = parse_args("compress --archive --verify --rm a");
+        assert!(matches!(archive, Ok(Command::Archive { verify: true, remove: true, .. })));
+        assert!(matches!(parse_args("compress --verify --rm a"), Ok(Command::Compress { remove: true, .. })));
+        let archive = parse_args("compress --archive a");
+        assert!(matches!(archive, Ok(Command::Archive { output, .. }) if output == Path::new("a.huf")));
+        assert_eq!(parse_args("extract a.huf"), Ok(Command::Extract {
+            input: "a.huf".into(),
+            output: ".".into(),
+            threads: None,
+        }));
+        assert!(matches!(parse_args("decompress --progress a.huf"), Ok(Command::Decompress { progress: true, .. })));
+        assert!(matches!(parse_args("compress --progress -"), Ok(Command::Compress { progress: true, .. })));
+        assert!(matches!(parse_args("compress -v a"), Ok(Command::Compress { stats: true, .. })));
+        assert!(matches!(parse_args("compress --stats a"), Ok(Command::Compress { stats: true, .. })));
+        let compress = parse_args("compress --dump-table t.json a");
+        assert!(matches!(compress, Ok(Command::Compress { dump_table: Some(table), .. }) if table == Path::new("t.json")));
+        let compress = parse_args("compress --use-table t.json --verify a");
+        assert!(matches!(compress, Ok(Command::Compress { use_table: Some(table), .. }) if table == Path::new("t.json")));
+        let decompress = parse_args("decompress --use-table t.json --threads 2 a.huf");
+        assert!(matches!(decompress, Ok(Command::Decompress { use_table: Some(_), threads: Some(2), .. })));
+        assert!(matches!(parse_args("compress -"), Ok(Command::Compress { output, .. }) if output == Path::new("-")));
+        assert!(matches!(parse_args("compress --adaptive -o a.huf -"), Ok(Command::Compress { adaptive: true, .. })));
+        for args in ["decompress -", "decompress -o - a.huf"] {
+            assert!(matches!(parse_args(args), Ok(Command::Decompress { output, .. }) if output == Path::new("-")));
+        }
+        assert_eq!(parse_args("compress --dry-run notes.txt"), Ok(Command::DryRun {
+            input: "notes.txt".into(),
+            symbols: Symbols::Auto,
+        }));
+        let dry_run = parse_args("compress --dry-run -v --symbols u16le -");
+        assert_eq!(dry_run, Ok(Command::DryRun { input: "-".into(), symbols: Symbols::U16Le }));
+        assert_eq!(parse_args("stats notes.txt"), Ok(Command::Stats { input: "notes.txt".into(), verbose: false }));
+        assert_eq!(parse_args("stats -v notes.txt"), Ok(Command::Stats { input: "notes.txt".into(), verbose: true }));
+        assert_eq!(parse_args("compress --help"), Ok(Command::Help));
+        let copy = Command::Copy { source: "a.huf".into(), destination: "b.huf".into(), entries: vec!["x".into(), "y".into()] };
+        assert_eq!(parse_args("archive copy a.huf b.huf x y"), Ok(copy));
+        assert_eq!(parse_args("-V"), Ok(Command::Version { verbose: false }));
+        assert_eq!(parse_args("--version --verbose"), Ok(Command::Version { verbose: true }));
+        assert_eq!(parse_args("version -v"), Ok(Command::Version { verbose: true }));
+        assert_eq!(parse_args("compress -V a"), Ok(Command::Version { verbose: false }));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for args in ["", "version a", "archive", "archive list a.huf", "archive copy a.huf b.huf",
+            "archive copy -o c a.huf b.huf x", "archive copy --optimal a.huf b.huf x", "archive copy - b.huf x", "compress", "frobnicate file", "compress a b", "compress -x a", "compress a -o",
+            "decompress --symbols u16le a", "compress --symbols u8 a", "stats a -o b", "decompress -v a",
+            "compress --adaptive -v a", "compress --stats -o x a b", "stats --stats a",
+            "decompress --adaptive a", "compress --adaptive --symbols u16le a",
+            "stats --optimal a", "compress --adaptive --optimal a",
+            "compress --threads 0 a", "compress --threads many a", "stats --threads 2 a",
+            "compress --optimal --threads 2 a",
+            "compress --adaptive --cache a", "stats --cache a",
+            "compress --adaptive --if-changed a", "decompress --if-changed a", "decompress --verify a",
+            "decompress a b", "decompress --archive a", "compress -o x --cache a b",
+            "decompress --rm a.huf", "compress --if-changed --rm a",
+            "compress -o a.huf a -", "compress --archive -", "extract -", "compress --verify -", "compress -o - --rm a",
+            "stats -", "stats --progress a", "compress --progress -o x a b",
+            "compress --dump-table", "compress --adaptive --dump-table t a", "decompress --dump-table t a",
+            "compress --use-table t -o x a b", "extract --use-table t a", "compress --use-table t --optimal a",
+            "compress --use-table t --symbols u16le a", "compress --use-table t -v a", "compress --use-table t --threads 2 a",
+            "compress --use-table t --dump-table u a", "decompress --mmap a", "compress --mmap --adaptive a",
+            "compress --mmap -o x a b", "compress --mmap -", "compress --mmap --progress a",
+            "stats --dry-run a", "compress --dry-run a b", "compress --dry-run -o x a", "compress --dry-run --optimal a",
+            "compress --dry-run --threads 2 a", "compress --dry-run --use-table t a", "compress --dry-run --rm a"] {
+            assert!(parse_args(args).is_err(), "{}", args);
+        }
+    }
+
+    #[test]
+    fn test_mmap() {
+        let compress = parse_args("compress --mmap --optimal a");
+        if cfg!(feature = "mmap") {
+            assert!(matches!(compress, Ok(Command::Compress { mmap: true, optimal: true, .. })));
+        } else {
+            assert_eq!(compress, Err(text!(MmapNotBuilt)));
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(parse_args("decompress --json a.huf"), parse_args("decompress a.huf"));
+        assert_eq!(parse_args("--json stats a"), parse_args("stats a"));
+
+        let parse = |args: &str| parse(args.split_whitespace().map(String::from));
+        assert!(parse("compress --json a").json);
+        assert!(!parse("compress a").json);
+        // Only as an option, not as the value of one.
+        let parsed = parse("compress -o --json a");
+        assert!(!parsed.json);
+        assert!(matches!(parsed.command, Ok(Command::Compress { output, .. }) if output == Path::new("--json")));
+        // Known whatever else is wrong.
+        let parsed = parse("compress --threads 0 --frobnicate --json a");
+        assert!(parsed.json);
+        assert_eq!(parsed.command, Err(text!(ThreadsNeedsPositive)));
+        assert!(parse("compress --json -x").json);
+    }
+}
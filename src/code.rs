@@ -0,0 +1,208 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::symbol::Symbol;
+use crate::tree::{HuffmanNode, Tree};
+use crate::Map;
+
+/// The code of every symbol in a [`Tree`], as strings of `'0'` and `'1'`.
+#[derive(Debug, Clone)]
+pub struct CodeTable<S = char> {
+    codes: Map<S, String>,
+}
+
+impl<S> Default for CodeTable<S> {
+    fn default() -> CodeTable<S> {
+        CodeTable { codes: Map::new() }
+    }
+}
+
+impl<S: Symbol> CodeTable<S> {
+    pub fn from_tree(tree: &Tree<S>) -> CodeTable<S> {
+        let mut codes = Map::new();
+        match tree.root() {
+            // A lone symbol still needs one bit per occurrence.
+            HuffmanNode::Leaf { symbol, .. } => {
+                codes.insert(*symbol, String::from("0"));
+            }
+            root => generate_huffman_codes(root, String::new(), &mut codes),
+        }
+        CodeTable { codes }
+    }
+
+    /// Assigns canonical codes to symbols with the given code lengths.
+    ///
+    /// Codes are handed out in order of length, then symbol, each being the
+    /// previous code plus one, extended with zeros to the symbol's length. The
+    /// lengths therefore fully describe the table.
+    ///
+    /// Returns `None` if a length is zero or the lengths do not form a prefix
+    /// code, i.e. there are more codes of some length than can exist.
+    pub fn from_code_lengths(lengths: &Map<S, u8>) -> Option<CodeTable<S>> {
+        let mut symbols: Vec<(u8, S)> = lengths.iter().map(|(&symbol, &length)| (length, symbol)).collect();
+        symbols.sort_unstable();
+
+        if symbols.first().is_some_and(|&(length, _)| length == 0) {
+            return None;
+        }
+
+        let mut codes = Map::new();
+        let mut code = String::new();
+        for (i, &(length, symbol)) in symbols.iter().enumerate() {
+            if i > 0 && !increment(&mut code) {
+                return None;
+            }
+            while code.len() < length as usize {
+                code.push('0');
+            }
+            codes.insert(symbol, code.clone());
+        }
+
+        Some(CodeTable { codes })
+    }
+
+    /// Replaces the codes with canonical codes of the same lengths (see
+    /// [`CodeTable::from_code_lengths`]).
+    pub fn canonicalize(&self) -> CodeTable<S> {
+        CodeTable::from_code_lengths(&self.code_lengths()).expect("Huffman codes always form a prefix code")
+    }
+
+    /// The length of each symbol's code.
+    pub fn code_lengths(&self) -> Map<S, u8> {
+        // Depths beyond 255 would need frequencies adding up to more than u64::MAX.
+        self.codes.iter().map(|(&symbol, code)| (symbol, code.len() as u8)).collect()
+    }
+
+    pub fn get(&self, symbol: S) -> Option<&str> {
+        self.codes.get(&symbol).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (S, &str)> {
+        self.codes.iter().map(|(&symbol, code)| (symbol, code.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+}
+
+/// Adds one to a binary string, returning `false` if it overflows.
+fn increment(code: &mut String) -> bool {
+    let Some(position) = code.rfind('0') else { return false; };
+
+    let carried = code.len() - position - 1;
+    code.truncate(position);
+    code.push('1');
+    code.push_str(&"0".repeat(carried));
+    true
+}
+
+fn generate_huffman_codes<S: Symbol>(node: &HuffmanNode<S>, prefix: String, codes: &mut Map<S, String>) {
+    match node {
+        HuffmanNode::Leaf { symbol, .. } => {
+            codes.insert(*symbol, prefix);
+        }
+        HuffmanNode::Internal { left, right } => {
+            generate_huffman_codes(left, prefix.clone() + "0", codes);
+            generate_huffman_codes(right, prefix + "1", codes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_heap_from_frequencies() {
+        let mut frequencies: Map<char, usize> = Map::new();
+        frequencies.insert('C', 32);
+        frequencies.insert('D', 42);
+        frequencies.insert('E', 120);
+        frequencies.insert('K', 7);
+        frequencies.insert('L', 42);
+        frequencies.insert('M', 24);
+        frequencies.insert('U', 37);
+        frequencies.insert('Z', 2);
+
+        let root = Tree::from_frequencies(&frequencies).unwrap();
+        let huffman_codes = CodeTable::from_tree(&root);
+
+        let expected = Map::from([
+            ('M', "11111"),
+            ('D', "101"),
+            ('U', "100"),
+            ('C', "1110"),
+            ('E', "0"),
+            ('K', "111101"),
+            ('L', "110"),
+            ('Z', "111100")
+        ]);
+
+        assert_eq!(huffman_codes.len(), expected.len());
+        for (character, code) in huffman_codes.iter() {
+            assert!(expected.contains_key(&character));
+            assert_eq!(code, expected[&character]);
+        }
+    }
+
+    #[test]
+    fn test_single_symbol_code() {
+        let tree = Tree::from_frequencies(&Map::from([('a', 3)])).unwrap();
+        let codes = CodeTable::from_tree(&tree);
+
+        assert_eq!(codes.get('a'), Some("0"));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let frequencies = Map::from([
+            ('C', 32), ('D', 42), ('E', 120), ('K', 7), ('L', 42), ('M', 24), ('U', 37), ('Z', 2),
+        ]);
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&frequencies).unwrap()).canonicalize();
+
+        let expected = Map::from([
+            ('E', "0"),
+            ('D', "100"),
+            ('L', "101"),
+            ('U', "110"),
+            ('C', "1110"),
+            ('M', "11110"),
+            ('K', "111110"),
+            ('Z', "111111")
+        ]);
+
+        assert_eq!(codes.len(), expected.len());
+        for (character, code) in codes.iter() {
+            assert_eq!(code, expected[&character]);
+        }
+    }
+
+    #[test]
+    fn test_from_code_lengths_single_symbol() {
+        let codes = CodeTable::from_code_lengths(&Map::from([('a', 1)])).unwrap();
+
+        assert_eq!(codes.get('a'), Some("0"));
+    }
+
+    #[test]
+    fn test_byte_codes() {
+        let tree = Tree::from_frequencies(&Map::from([(0x00u8, 1), (0xFF, 2), (0x7F, 4)])).unwrap();
+        let codes = CodeTable::from_tree(&tree).canonicalize();
+
+        assert_eq!(codes.get(0x7F), Some("0"));
+        assert_eq!(codes.get(0x00), Some("10"));
+        assert_eq!(codes.get(0xFF), Some("11"));
+    }
+
+    #[test]
+    fn test_from_code_lengths_invalid() {
+        assert!(CodeTable::from_code_lengths(&Map::from([('a', 0)])).is_none());
+        assert!(CodeTable::from_code_lengths(&Map::from([('a', 1), ('b', 1), ('c', 1)])).is_none());
+        assert!(CodeTable::from_code_lengths(&Map::from([('a', 1), ('b', 2), ('c', 2), ('d', 2)])).is_none());
+    }
+}
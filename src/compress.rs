@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use crate::crc::{crc32, Crc32};
+use crate::error::{HuffmanError, Result};
+use crate::escape::{EscapeTable, Escaped};
+use crate::format::{self, BlockHeader, Escape, Header, SymbolKind, Table};
+use crate::frequency::count_symbols;
+use crate::optimize::{split_blocks, BlockPlan};
+use crate::{decode, AdaptiveEncoder, CodeTable, HuffmanDecoder, Symbol, Tree};
+
+/// The number of symbols per block written by [`compress`], [`compress_bytes`]
+/// and [`HuffmanEncoder::new`](crate::HuffmanEncoder::new).
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+
+/// Compresses `input` into blocks of canonically coded characters (see
+/// [`format`](crate::format)).
+pub fn compress(input: &str) -> Vec<u8> {
+    let mut chars = input.chars();
+    let blocks = std::iter::from_fn(|| {
+        let block: Vec<char> = chars.by_ref().take(DEFAULT_BLOCK_SIZE).collect();
+        (!block.is_empty()).then_some(block)
+    });
+
+    compress_blocks(SymbolKind::Chars, blocks, crc32(input.as_bytes()))
+}
+
+/// Compresses arbitrary bytes, coding each byte as a symbol.
+pub fn compress_bytes(input: &[u8]) -> Vec<u8> {
+    compress_blocks(SymbolKind::Bytes, input.chunks(DEFAULT_BLOCK_SIZE), crc32(input))
+}
+
+/// Compresses a stream of 16-bit symbols, such as token ids, coding each value
+/// as a symbol.
+pub fn compress_u16(input: &[u16]) -> Vec<u8> {
+    compress_blocks(SymbolKind::U16, input.chunks(DEFAULT_BLOCK_SIZE), u16_checksum(input))
+}
+
+/// Like [`compress`], with each block's boundaries chosen to make the output
+/// as small as possible rather than every [`DEFAULT_BLOCK_SIZE`] characters.
+///
+/// This weighs many possible blocks and so is much slower; it pays off on
+/// input whose statistics change along the way, such as archives of mixed
+/// files.
+pub fn compress_optimal(input: &str) -> Vec<u8> {
+    let chars: Vec<char> = input.chars().collect();
+    compress_split(SymbolKind::Chars, &chars, crc32(input.as_bytes()))
+}
+
+/// Like [`compress_bytes`], with blocks chosen as by [`compress_optimal`].
+pub fn compress_bytes_optimal(input: &[u8]) -> Vec<u8> {
+    compress_split(SymbolKind::Bytes, input, crc32(input))
+}
+
+/// Like [`compress_u16`], with blocks chosen as by [`compress_optimal`].
+pub fn compress_u16_optimal(input: &[u16]) -> Vec<u8> {
+    compress_split(SymbolKind::U16, input, u16_checksum(input))
+}
+
+/// Like [`compress`], coding blocks on up to `threads` threads at once. The
+/// output is the same.
+///
+/// # Panics
+///
+/// Panics if `threads` is 0.
+pub fn compress_parallel(input: &str, threads: usize) -> Vec<u8> {
+    let mut blocks = Vec::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let end = rest.char_indices().nth(DEFAULT_BLOCK_SIZE).map_or(rest.len(), |(index, _)| index);
+        let (block, tail) = rest.split_at(end);
+        blocks.push(block);
+        rest = tail;
+    }
+
+    let to_symbols = |block: &&str| block.chars().collect::<Vec<char>>();
+    compress_blocks_parallel(SymbolKind::Chars, &blocks, threads, to_symbols, crc32(input.as_bytes()))
+}
+
+/// Like [`compress_bytes`], coding blocks on up to `threads` threads at once.
+///
+/// # Panics
+///
+/// Panics if `threads` is 0.
+pub fn compress_bytes_parallel(input: &[u8], threads: usize) -> Vec<u8> {
+    let blocks: Vec<&[u8]> = input.chunks(DEFAULT_BLOCK_SIZE).collect();
+    compress_blocks_parallel(SymbolKind::Bytes, &blocks, threads, |block| *block, crc32(input))
+}
+
+/// Like [`compress_u16`], coding blocks on up to `threads` threads at once.
+///
+/// # Panics
+///
+/// Panics if `threads` is 0.
+pub fn compress_u16_parallel(input: &[u16], threads: usize) -> Vec<u8> {
+    let blocks: Vec<&[u16]> = input.chunks(DEFAULT_BLOCK_SIZE).collect();
+    compress_blocks_parallel(SymbolKind::U16, &blocks, threads, |block| *block, u16_checksum(input))
+}
+
+/// Compresses arbitrary bytes in a single pass with adaptive Huffman coding,
+/// like [`AdaptiveEncoder`].
+pub fn compress_adaptive(input: &[u8]) -> Vec<u8> {
+    let mut encoder = AdaptiveEncoder::new(Vec::new());
+    encoder.write_all(input).expect("Writing to a Vec cannot fail");
+    encoder.finish().expect("Writing to a Vec cannot fail")
+}
+
+/// Reverses [`compress`], or [`compress_bytes`] or [`compress_adaptive`] if
+/// the bytes are valid UTF-8.
+///
+/// Fails with [`HuffmanError::BadHeader`] or [`HuffmanError::CorruptStream`]
+/// if the input is malformed, with [`HuffmanError::Truncated`] if it is cut
+/// short, with [`HuffmanError::ChecksumMismatch`] if it decodes to other data
+/// than was compressed and with [`HuffmanError::NotUtf8`] if the output is not
+/// text.
+pub fn decompress(bytes: &[u8]) -> Result<String> {
+    String::from_utf8(decompress_bytes(bytes)?).map_err(|_| HuffmanError::NotUtf8)
+}
+
+/// Reverses [`compress_bytes`] and [`compress_adaptive`], or [`compress`] returning the text as UTF-8,
+/// or [`compress_u16`] returning each symbol as two little-endian bytes.
+///
+/// Fails like [`decompress`], except that any output is accepted.
+pub fn decompress_bytes(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    HuffmanDecoder::new(bytes).read_to_end(&mut output)?;
+
+    Ok(output)
+}
+
+/// Reverses [`compress_u16`].
+///
+/// Fails like [`decompress_bytes`], and with [`HuffmanError::WrongSymbolKind`]
+/// if the input does not hold `u16` symbols.
+pub fn decompress_u16(bytes: &[u8]) -> Result<Vec<u16>> {
+    if Header::read(&mut &bytes[..])?.kind != SymbolKind::U16 {
+        return Err(HuffmanError::WrongSymbolKind);
+    }
+
+    let output = decompress_bytes(bytes)?;
+    Ok(output.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect())
+}
+
+/// Writes a whole stream of the non-empty `blocks`, ending with `checksum`.
+fn compress_blocks<S: Symbol, B: AsRef<[S]>>(
+    kind: SymbolKind,
+    blocks: impl Iterator<Item = B>,
+    checksum: u32,
+) -> Vec<u8> {
+    let mut output = Vec::new();
+    let write = || -> Result<()> {
+        Header::new(kind).write(&mut output)?;
+        for block in blocks {
+            write_block(kind, block.as_ref(), &mut output)?;
+        }
+
+        BlockHeader::write_end(&mut output)?;
+        format::write_checksum(&mut output, checksum)
+    };
+
+    write().expect("Writing valid blocks to a Vec cannot fail");
+    output
+}
+
+/// Writes a whole stream of the non-empty `blocks`, giving each of up to
+/// `threads` threads a run of them to code into buffers that are then joined
+/// in order, ending with `checksum`.
+fn compress_blocks_parallel<B: Sync, S: Symbol, T: AsRef<[S]>>(
+    kind: SymbolKind,
+    blocks: &[B],
+    threads: usize,
+    to_symbols: impl Fn(&B) -> T + Sync,
+    checksum: u32,
+) -> Vec<u8> {
+    assert!(threads > 0, "Number of threads must not be 0");
+
+    let code = |block: &B| {
+        let mut output = Vec::new();
+        write_block(kind, to_symbols(block).as_ref(), &mut output).expect("Writing valid blocks to a Vec cannot fail");
+        output
+    };
+    let share = blocks.len().div_ceil(threads).max(1);
+    let coded: Vec<Vec<u8>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = blocks.chunks(share)
+            .map(|blocks| scope.spawn(|| blocks.iter().map(code).collect::<Vec<_>>()))
+            .collect();
+
+        workers.into_iter().flat_map(|worker| worker.join().expect("Coding a block panicked")).collect()
+    });
+
+    let mut output = Vec::new();
+    Header::new(kind).write(&mut output).expect("Writing to a Vec cannot fail");
+    for block in coded {
+        output.extend(block);
+    }
+    BlockHeader::write_end(&mut output).expect("Writing to a Vec cannot fail");
+    format::write_checksum(&mut output, checksum).expect("Writing to a Vec cannot fail");
+
+    output
+}
+
+fn compress_split<S: Symbol>(kind: SymbolKind, symbols: &[S], checksum: u32) -> Vec<u8> {
+    compress_blocks(kind, split_blocks(kind, symbols).into_iter().map(|block| &symbols[block]), checksum)
+}
+
+/// The CRC-32 of `symbols` as two little-endian bytes each, as
+/// [`decompress_u16`] reads them back.
+fn u16_checksum(symbols: &[u16]) -> u32 {
+    let mut crc = Crc32::new();
+    for symbol in symbols {
+        crc.update(&symbol.to_le_bytes());
+    }
+
+    crc.finish()
+}
+
+/// Codes `symbols` with their own table and writes them as one block.
+///
+/// Rare symbols are escaped and the table stored as its topology when that
+/// makes the block smaller.
+///
+/// `symbols` must not be empty.
+pub(crate) fn write_block<S: Symbol, W: Write>(kind: SymbolKind, symbols: &[S], output: &mut W) -> Result<()> {
+    let plan = BlockPlan::choose(kind, &count_symbols(symbols.iter().copied()));
+    write_planned_block(kind, &plan, symbols, output)
+}
+
+/// Codes `symbols` with `plan` and writes them as one block.
+///
+/// Every symbol must have a code or fit the escape, and `symbols` must not be
+/// empty.
+pub(crate) fn write_planned_block<S: Symbol, W: Write>(
+    kind: SymbolKind,
+    plan: &BlockPlan<S>,
+    symbols: &[S],
+    output: &mut W,
+) -> Result<()> {
+    let encoded = plan.table.encode(symbols);
+
+    plan.header(symbols.len() as u64, encoded.len() as u64).write(kind, output)?;
+    output.write_all(&encoded)?;
+
+    Ok(())
+}
+
+/// Decodes the symbols of `block` from its encoded bits.
+pub(crate) fn decode_block<S: Symbol>(block: &BlockHeader, bits: &[u8]) -> Result<Vec<S>> {
+    let length = usize::try_from(block.length)
+        .map_err(|_| HuffmanError::BadHeader("Block too large for this platform".to_string()))?;
+    if length == 0 {
+        return Ok(Vec::new());
+    }
+
+    match &block.table {
+        Table::Frequencies(frequencies) => {
+            let frequencies = to_symbols(frequencies)?;
+            decode(bits, &Tree::from_frequencies(&frequencies)?, length)
+        }
+        Table::CodeLengths(lengths) => block_table(lengths, block.escape)?.decode(bits, length),
+        Table::Shared(_) => Err(HuffmanError::MissingTable),
+    }
+}
+
+/// The codes of a block with the code `lengths` and `escape` of its header.
+pub(crate) fn block_table<S: Symbol>(lengths: &HashMap<u32, u8>, escape: Option<Escape>) -> Result<EscapeTable<S>> {
+    let mut lengths: HashMap<Escaped<S>, u8> =
+        to_symbols(lengths)?.into_iter().map(|(symbol, length)| (Escaped::Symbol(symbol), length)).collect();
+    if let Some(escape) = escape {
+        lengths.insert(Escaped::Escape, escape.code_length);
+    }
+
+    let codes = CodeTable::from_code_lengths(&lengths)
+        .ok_or_else(|| HuffmanError::BadHeader("Code lengths do not form a prefix code".to_string()))?;
+    let literal_width = escape.map_or(0, |escape| escape.literal_width);
+    Ok(EscapeTable { codes, literal_width })
+}
+
+fn to_symbols<S: Symbol, T: Copy>(table: &HashMap<u32, T>) -> Result<HashMap<S, T>> {
+    table.iter()
+        .map(|(&value, &entry)| S::from_u32(value).map(|symbol| (symbol, entry)))
+        .collect::<Option<_>>()
+        .ok_or_else(|| HuffmanError::BadHeader("Invalid symbol in header".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for input in ["", "a", "aaaa", "abbcccdddd", "test\nmyreallycooltest", "Les Misérables 霍夫曼"] {
+            assert_eq!(decompress(&compress(input)).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_bytes() {
+        let inputs: [&[u8]; 5] = [b"", b"a", &[0xFF; 7], &[0xC3, 0x28, 0x00, 0xFF, 0xFF], b"abbcccdddd"];
+        for input in inputs {
+            assert_eq!(decompress_bytes(&compress_bytes(input)).unwrap(), input);
+        }
+
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decompress_bytes(&compress_bytes(&all_bytes)).unwrap(), all_bytes);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "too slow under Miri")]
+    fn test_round_trip_u16() {
+        let inputs: [&[u16]; 4] = [&[], &[0xFFFF], &[1, 2, 2, 3, 3, 3], &[0x0100, 0x0001, 50_000, 50_000]];
+        for input in inputs {
+            assert_eq!(decompress_u16(&compress_u16(input)).unwrap(), input);
+        }
+
+        let all_u16: Vec<u16> = (0..=u16::MAX).chain(0..100).collect();
+        let compressed = compress_u16(&all_u16);
+        assert_eq!(decompress_u16(&compressed).unwrap(), all_u16);
+        assert_eq!(decompress_bytes(&compressed).unwrap()[..4], [0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let input: Vec<u8> = (0..5000u32).map(|i| (i * i % 251) as u8).collect();
+        let blocks: Vec<&[u8]> = input.chunks(300).collect();
+        let serial = compress_blocks(SymbolKind::Bytes, blocks.iter(), crc32(&input));
+
+        for threads in [1, 2, 7, 64] {
+            let parallel = compress_blocks_parallel(SymbolKind::Bytes, &blocks, threads, |block| *block, crc32(&input));
+            assert_eq!(parallel, serial);
+        }
+
+        assert_eq!(compress_parallel("Les Misérables 霍夫曼", 4), compress("Les Misérables 霍夫曼"));
+        assert_eq!(compress_bytes_parallel(b"", 4), compress_bytes(b""));
+        assert_eq!(compress_u16_parallel(&[1, 2, 2], 4), compress_u16(&[1, 2, 2]));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "too slow under Miri")]
+    fn test_round_trip_optimal() {
+        for input in ["", "a", "abbcccdddd", "Les Misérables 霍夫曼"] {
+            assert_eq!(decompress(&compress_optimal(input)).unwrap(), input);
+        }
+        assert_eq!(decompress_u16(&compress_u16_optimal(&[1, 2, 2, 50_000])).unwrap(), [1, 2, 2, 50_000]);
+
+        // Text and binary data share a block badly.
+        let mut input = b"quick brown fox ".repeat(1 << 14);
+        input.extend((0..1 << 18).map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+        input.extend(b"quick brown fox ".repeat(1 << 12));
+
+        let compressed = compress_bytes_optimal(&input);
+        assert_eq!(decompress_bytes(&compressed).unwrap(), input);
+        assert!(compressed.len() < compress_bytes(&input).len());
+    }
+
+    #[test]
+    fn test_round_trip_adaptive() {
+        let inputs: [&[u8]; 4] = [b"", b"a", b"abbcccdddd", "Les Misérables 霍夫曼".as_bytes()];
+        for input in inputs {
+            assert_eq!(decompress_bytes(&compress_adaptive(input)).unwrap(), input);
+        }
+
+        // Magic, version, symbol kind, coding and no source, then the end.
+        assert_eq!(compress_adaptive(b"").len(), 8 + 8 + 4);
+    }
+
+    #[test]
+    fn test_decompress_across_kinds() {
+        let text = "Les Misérables";
+
+        assert_eq!(decompress_bytes(&compress(text)).unwrap(), text.as_bytes());
+        assert_eq!(decompress(&compress_bytes(text.as_bytes())).unwrap(), text);
+
+        let error = decompress(&compress_bytes(&[0xC3, 0x28])).unwrap_err();
+        assert!(matches!(error, HuffmanError::NotUtf8));
+
+        let error = decompress_u16(&compress_bytes(&[0x00, 0x01])).unwrap_err();
+        assert!(matches!(error, HuffmanError::WrongSymbolKind));
+    }
+
+    #[test]
+    fn test_compressed_size() {
+        // Magic, version, symbol kind, coding and no source, then a block with
+        // its length, table encoding, symbol count, the 4 symbols in code
+        // order, no escape, the 7 bit tree shape, encoded size and 19 bits of
+        // codes, then the end.
+        assert_eq!(compress_bytes(b"abbcccdddd").len(), 8 + 13 + 4 + 1 + 1 + 8 + 3 + 8 + 4);
+        // Characters take more room in the table, so here they are all escaped
+        // with a 1 bit code and 7 bit literals, leaving no table to store.
+        assert_eq!(compress("abbcccdddd").len(), 8 + 13 + 2 + 8 + 10 + 8 + 4);
+        assert_eq!(compress("").len(), 8 + 8 + 4);
+    }
+
+    #[test]
+    fn test_decompress_v1() {
+        let input = "abbcccdddd";
+        let frequencies = crate::get_frequencies(input);
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&frequencies).unwrap());
+
+        let mut symbols: Vec<(char, usize)> = frequencies.into_iter().collect();
+        symbols.sort_unstable();
+
+        let mut compressed = b"HUFF\x01".to_vec();
+        compressed.extend_from_slice(&10u64.to_le_bytes());
+        compressed.extend_from_slice(&4u32.to_le_bytes());
+        for (character, frequency) in symbols {
+            compressed.extend_from_slice(&(character as u32).to_le_bytes());
+            compressed.extend_from_slice(&(frequency as u64).to_le_bytes());
+        }
+        compressed.extend(crate::encode(input.chars(), &codes).unwrap());
+
+        assert_eq!(decompress(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_decompress_v3() {
+        let input = [0x00, 0xFF, 0xFF];
+
+        let mut compressed = b"HUFF\x03\x01".to_vec();
+        compressed.extend_from_slice(&3u64.to_le_bytes());
+        compressed.extend_from_slice(&2u32.to_le_bytes());
+        compressed.extend_from_slice(&[0x00, 2, 0xFF, 1]);
+        compressed.push(0b1000_0000);
+
+        assert_eq!(decompress_bytes(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_decompress_v4() {
+        let input = [0x00, 0xFF, 0xFF];
+
+        let mut compressed = b"HUFF\x04\x01".to_vec();
+        compressed.extend_from_slice(&3u64.to_le_bytes());
+        compressed.extend_from_slice(&2u32.to_le_bytes());
+        compressed.extend_from_slice(&[0x00, 2, 0xFF, 1]);
+        compressed.extend_from_slice(&1u64.to_le_bytes());
+        compressed.push(0b1000_0000);
+        compressed.extend_from_slice(&0u64.to_le_bytes());
+
+        assert_eq!(decompress_bytes(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_escaped_text() {
+        let text = format!("{}{}", "the quick brown fox ".repeat(50), ('\u{1F600}'..'\u{1F650}').collect::<String>());
+        let compressed = compress(&text);
+
+        assert_eq!(decompress(&compressed).unwrap(), text);
+
+        // Without the escape, each emoji would take a 5 byte table entry.
+        let mut unescaped = Vec::new();
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&crate::get_frequencies(&text)).unwrap()).canonicalize();
+        let encoded = crate::encode(text.chars(), &codes).unwrap();
+        BlockHeader {
+            length: text.chars().count() as u64,
+            table: Table::CodeLengths(codes.code_lengths().into_iter().map(|(c, length)| (c as u32, length)).collect()),
+            encoding: crate::format::TableEncoding::CodeLengths,
+            escape: None,
+            encoded_size: Some(encoded.len() as u64),
+        }
+        .write(SymbolKind::Chars, &mut unescaped)
+        .unwrap();
+        unescaped.extend(encoded);
+        assert!(compressed.len() < 8 + unescaped.len() + 8 + 4);
+    }
+
+    #[test]
+    fn test_decompress_blocks() {
+        let mut compressed = Vec::new();
+        Header::new(SymbolKind::Chars).write(&mut compressed).unwrap();
+        write_block(SymbolKind::Chars, &['a', 'b', 'b'], &mut compressed).unwrap();
+        write_block(SymbolKind::Chars, &['霍'], &mut compressed).unwrap();
+        BlockHeader::write_end(&mut compressed).unwrap();
+        format::write_checksum(&mut compressed, crc32("abb霍".as_bytes())).unwrap();
+
+        assert_eq!(decompress(&compressed).unwrap(), "abb霍");
+    }
+
+    #[test]
+    fn test_checksum() {
+        let compressed = compress_bytes(b"abbcccdddd");
+        let bits = compressed.len() - 4 - 8 - 3;
+
+        // Swapping the codes of b and c still decodes, to the wrong data.
+        let mut corrupted = compressed.clone();
+        corrupted[bits] ^= 0b0011_1100;
+        assert!(matches!(decompress_bytes(&corrupted).unwrap_err(), HuffmanError::ChecksumMismatch));
+
+        let mut corrupted = compressed.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(decompress_bytes(&corrupted).unwrap_err(), HuffmanError::ChecksumMismatch));
+
+        assert_eq!(decompress_u16(&compress_u16(&[1, 2, 2, 50_000])).unwrap(), [1, 2, 2, 50_000]);
+        assert_eq!(decompress_bytes(&compress_adaptive(b"abbcccdddd")).unwrap(), b"abbcccdddd");
+    }
+
+    #[test]
+    fn test_decompress_truncated() {
+        let compressed = compress("abbcccdddd");
+
+        for length in [0, 3, 10, compressed.len() - 1] {
+            let error = decompress(&compressed[..length]).unwrap_err();
+            assert!(matches!(error, HuffmanError::Truncated));
+        }
+    }
+
+    #[test]
+    fn test_decompress_invalid_header() {
+        let mut compressed = compress(&"ab".repeat(20));
+        compressed[21..25].copy_from_slice(&0xD800u32.to_le_bytes());
+
+        let error = decompress(&compressed).unwrap_err();
+        assert!(matches!(error, HuffmanError::BadHeader(_)));
+    }
+}
@@ -0,0 +1,159 @@
+//! Turning a panic, which is a bug, into a short message and a crash report in
+//! a file, rather than Rust's own dump on the terminal.
+
+use std::backtrace::Backtrace;
+use std::collections::hash_map::RandomState;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::cache;
+use crate::messages::text;
+
+/// The exit code after a crash, as for any Rust program that panics.
+pub const EXIT_CODE: u8 = 101;
+
+/// What a crash report says about the run, besides the panic.
+#[derive(Debug, Clone)]
+pub struct Run {
+    /// The name of the command.
+    pub command: &'static str,
+    /// Everything the command was given, which is hashed rather than written
+    /// to the report so that it does not give away the names of files.
+    pub config: String,
+    /// Whether to tell of the crash as JSON, for `--json`.
+    pub json: bool,
+}
+
+/// Runs `function`, or, should it panic, writes a report to a file made by
+/// [`create_report`] in the temporary directory and tells the user where it
+/// is, returning `None`.
+pub fn guard<T>(run: Run, function: impl FnOnce() -> T) -> Option<T> {
+    let previous = panic::take_hook();
+    // Panics on several threads add to the same report.
+    let file: Mutex<Option<(PathBuf, File)>> = Mutex::new(None);
+    panic::set_hook(Box::new(move |info| {
+        let report = report(&run, info, &Backtrace::force_capture());
+        let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+        if file.is_none() {
+            *file = create_report(&std::env::temp_dir()).ok();
+        }
+        let written = match &mut *file {
+            Some((path, file)) => file.write_all(report.as_bytes()).ok().map(|()| &*path),
+            None => None,
+        };
+
+        let message = match written {
+            Some(path) => text!(Crashed, panic_message(info), path.display()),
+            None => format!("{}\n\n{}", text!(CrashedWithoutReport, panic_message(info)), report),
+        };
+        if run.json {
+            eprintln!("{}", crate::json_error("E_CRASH", &message, None, None));
+        } else {
+            eprintln!("{}", text!(Error, message));
+        }
+    }));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(function)).ok();
+    panic::set_hook(previous);
+
+    result
+}
+
+/// Makes a new file in `dir` for the report of a crash of this process, which
+/// only the user may read. The temporary directory is shared, so the name is
+/// random and the file must not already be there, lest another user have put
+/// a link to somewhere else in its place.
+fn create_report(dir: &Path) -> io::Result<(PathBuf, File)> {
+    let mut attempts = 0;
+    loop {
+        let suffix = RandomState::new().build_hasher().finish();
+        let path = dir.join(format!("huffman-rs-crash-{}-{:016x}.txt", std::process::id(), suffix));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        match options.open(&path) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists && attempts < 8 => attempts += 1,
+            result => return result.map(|file| (path, file)),
+        }
+    }
+}
+
+/// The report of the panic described by `info`.
+fn report(run: &Run, info: &PanicHookInfo, backtrace: &Backtrace) -> String {
+    let location = info.location().map_or("an unknown place".to_string(), |location| location.to_string());
+    let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+
+    format!(
+        "huffman-rs {} crashed\ncommand: {}\nconfig: {:016x}\nthread: {}\npanicked at {}: {}\n\nbacktrace:\n{}\n\n",
+        env!("CARGO_PKG_VERSION"),
+        run.command,
+        cache::fingerprint(&[], &run.config),
+        thread,
+        location,
+        panic_message(info),
+        backtrace,
+    )
+}
+
+/// What the panic said, if it said it with a string, as nearly all do.
+fn panic_message<'a>(info: &'a PanicHookInfo) -> &'a str {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("a panic without a message")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard() {
+        let run = Run { command: "compress", config: "Compress { input: \"a\" }".to_string(), json: false };
+        assert_eq!(guard(run.clone(), || 7), Some(7));
+
+        let reports = || -> Vec<PathBuf> {
+            let prefix = format!("huffman-rs-crash-{}-", std::process::id());
+            let entries = std::fs::read_dir(std::env::temp_dir()).unwrap();
+            let paths = entries.map(|entry| entry.unwrap().path());
+            paths.filter(|path| path.file_name().unwrap().to_string_lossy().starts_with(&prefix)).collect()
+        };
+        assert!(reports().is_empty());
+        assert_eq!(guard(run, || -> u8 { panic!("the tables are {}", "wrong") }), None);
+
+        let [path] = &reports()[..] else { panic!("{:?}", reports()) };
+        let report = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let hash = format!("config: {:016x}\n", cache::fingerprint(&[], "Compress { input: \"a\" }"));
+        assert!(report.starts_with(&format!("huffman-rs {} crashed\ncommand: compress\n", env!("CARGO_PKG_VERSION"))));
+        assert!(report.contains(&hash), "{}", report);
+        assert!(report.contains("src/crash.rs"), "{}", report);
+        assert!(report.contains(": the tables are wrong\n\nbacktrace:\n"), "{}", report);
+        assert!(!report.contains("input"), "{}", report);
+    }
+
+    #[test]
+    fn test_create_report() {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-crash-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (first, _) = create_report(&dir).unwrap();
+        let (second, _) = create_report(&dir).unwrap();
+        assert_ne!(first, second);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::bits::BitReader;
+use crate::code::CodeTable;
+use crate::error::{HuffmanError, Result};
+use crate::symbol::Symbol;
+use crate::tree::{HuffmanNode, Tree};
+
+/// Decodes `length` symbols from the bits in `bytes` by walking `tree`.
+///
+/// Fails with [`HuffmanError::Truncated`] if `bytes` runs out first; padding
+/// bits after the last symbol are ignored.
+pub fn decode<S: Symbol>(bytes: &[u8], tree: &Tree<S>, length: usize) -> Result<Vec<S>> {
+    let mut reader = BitReader::new(bytes);
+    let mut output = Vec::new();
+
+    for _ in 0..length {
+        let mut node = tree.root();
+
+        loop {
+            match node {
+                HuffmanNode::Leaf { symbol, .. } => {
+                    // A lone leaf is still coded with one bit per occurrence.
+                    if core::ptr::eq(node, tree.root()) {
+                        next_bit(&mut reader)?;
+                    }
+                    output.push(*symbol);
+                    break;
+                }
+                HuffmanNode::Internal { left, right } => {
+                    node = if next_bit(&mut reader)? { right } else { left };
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Decodes `length` symbols from bits produced with canonical `codes`.
+///
+/// Only the code lengths are looked at, so `codes` may as well be the table
+/// the encoder used before [`CodeTable::canonicalize`]; the bits must have
+/// been encoded with the canonical one.
+///
+/// Fails with [`HuffmanError::Truncated`] if `bytes` runs out first and with
+/// [`HuffmanError::CorruptStream`] on a bit sequence that is not a code.
+pub fn decode_canonical<S: Symbol>(bytes: &[u8], codes: &CodeTable<S>, length: usize) -> Result<Vec<S>> {
+    let decoder = CanonicalDecoder::new(codes);
+    let mut reader = BitReader::new(bytes);
+
+    (0..length).map(|_| decoder.decode_symbol(&mut reader)).collect()
+}
+
+/// The number of bits [`CanonicalDecoder`] looks up at once, at most.
+const TABLE_BITS: usize = 10;
+
+/// Decodes canonical codes one symbol at a time.
+///
+/// Codes of up to [`TABLE_BITS`] bits are decoded with a single lookup of the
+/// next bits; longer ones, and those cut short by the end of the input, a bit
+/// at a time.
+#[derive(Debug)]
+pub(crate) struct CanonicalDecoder<S> {
+    /// Symbols in the order of their codes.
+    symbols: Vec<S>,
+    /// The number of codes of each length.
+    counts: Vec<usize>,
+    /// The bits looked up at once, as many as the longest code up to
+    /// [`TABLE_BITS`].
+    table_bits: u8,
+    /// The code length and symbol of the code that each value of the next
+    /// `table_bits` bits starts with, if it is that short.
+    table: Vec<Option<(u8, S)>>,
+}
+
+impl<S: Symbol> CanonicalDecoder<S> {
+    pub(crate) fn new(codes: &CodeTable<S>) -> CanonicalDecoder<S> {
+        let mut symbols: Vec<(usize, S)> = codes.iter().map(|(symbol, code)| (code.len(), symbol)).collect();
+        symbols.sort_unstable();
+
+        let max_length = symbols.last().map_or(0, |&(code_length, _)| code_length);
+        let mut counts = vec![0; max_length + 1];
+        for &(code_length, _) in &symbols {
+            counts[code_length] += 1;
+        }
+
+        let table_bits = max_length.clamp(1, TABLE_BITS);
+        let mut table = vec![None; 1 << table_bits];
+        let mut code = 0;
+        let mut previous_length = 0;
+        for &(code_length, symbol) in symbols.iter().take_while(|&&(code_length, _)| code_length <= table_bits) {
+            code <<= code_length - previous_length;
+            previous_length = code_length;
+
+            // Every value of the next bits that starts with the code.
+            let shift = table_bits - code_length;
+            table[code << shift..(code + 1) << shift].fill(Some((code_length as u8, symbol)));
+            code += 1;
+        }
+
+        CanonicalDecoder {
+            symbols: symbols.into_iter().map(|(_, symbol)| symbol).collect(),
+            counts,
+            table_bits: table_bits as u8,
+            table,
+        }
+    }
+
+    pub(crate) fn decode_symbol(&self, reader: &mut BitReader) -> Result<S> {
+        let (bits, available) = reader.peek_bits(self.table_bits);
+        if let Some((code_length, symbol)) = self.table[bits as usize] {
+            if code_length as usize <= available {
+                reader.skip(code_length as usize);
+                return Ok(symbol);
+            }
+        }
+
+        self.decode_bitwise(reader)
+    }
+
+    fn decode_bitwise(&self, reader: &mut BitReader) -> Result<S> {
+        // Canonical codes of one length are consecutive, so it is enough to
+        // track how far the bits read so far are past the first code of the
+        // current length, and how many symbols have shorter codes.
+        let mut offset = 0;
+        let mut index = 0;
+
+        for &count in &self.counts[1..] {
+            offset = offset * 2 + next_bit(reader)? as usize;
+            if offset < count {
+                return Ok(self.symbols[index + offset]);
+            }
+
+            offset -= count;
+            index += count;
+            if offset >= self.symbols.len() - index {
+                // Past the last code of any length.
+                break;
+            }
+        }
+
+        Err(HuffmanError::CorruptStream("Invalid code in bitstream".to_string()))
+    }
+}
+
+fn next_bit(reader: &mut BitReader) -> Result<bool> {
+    reader.read_bit().ok_or(HuffmanError::Truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode, get_frequencies};
+    use crate::Map;
+
+    #[test]
+    fn test_decode() {
+        let input = "abbcccdddd";
+        let tree = Tree::from_frequencies(&get_frequencies(input)).unwrap();
+        let encoded = encode(input.chars(), &CodeTable::from_tree(&tree)).unwrap();
+
+        assert_eq!(String::from_iter(decode(&encoded, &tree, input.len()).unwrap()), input);
+    }
+
+    #[test]
+    fn test_decode_single_symbol() {
+        let tree = Tree::from_frequencies(&get_frequencies("a")).unwrap();
+
+        assert_eq!(decode(&[0], &tree, 5).unwrap(), ['a'; 5]);
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let input = "abbcccdddd";
+        let tree = Tree::from_frequencies(&get_frequencies(input)).unwrap();
+        let encoded = encode(input.chars(), &CodeTable::from_tree(&tree)).unwrap();
+
+        let error = decode(&encoded[..1], &tree, input.len()).unwrap_err();
+        assert!(matches!(error, HuffmanError::Truncated));
+    }
+
+    #[test]
+    fn test_decode_canonical() {
+        let input = "test\nmyreallycooltest";
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&get_frequencies(input)).unwrap()).canonicalize();
+        let encoded = encode(input.chars(), &codes).unwrap();
+
+        assert_eq!(String::from_iter(decode_canonical(&encoded, &codes, input.chars().count()).unwrap()), input);
+    }
+
+    #[test]
+    fn test_decode_canonical_invalid_code() {
+        // Only "0" and "10" are codes, so "11" is not.
+        let codes = CodeTable::from_code_lengths(&Map::from([('a', 1), ('b', 2)])).unwrap();
+
+        assert_eq!(decode_canonical(&[0b0101_0000], &codes, 4).unwrap(), ['a', 'b', 'b', 'a']);
+        let error = decode_canonical(&[0b1100_0000], &codes, 1).unwrap_err();
+        assert!(matches!(error, HuffmanError::CorruptStream(_)));
+    }
+
+    #[test]
+    fn test_decode_canonical_bytes() {
+        let input: Vec<u8> = (0..=255).chain([0, 0, 0, 1]).collect();
+        let tree = Tree::from_frequencies(&crate::get_byte_frequencies(&input)).unwrap();
+        let codes = CodeTable::from_tree(&tree).canonicalize();
+        let encoded = encode(input.iter().copied(), &codes).unwrap();
+
+        assert_eq!(decode_canonical(&encoded, &codes, input.len()).unwrap(), input);
+    }
+
+    #[test]
+    fn test_decode_canonical_long_codes() {
+        // Fibonacci frequencies make codes of every length up to 19, longer
+        // than those looked up at once.
+        let mut frequencies = Map::new();
+        let (mut a, mut b) = (1, 1);
+        for symbol in 'a'..='t' {
+            frequencies.insert(symbol, a);
+            (a, b) = (b, a + b);
+        }
+        let input: String =
+            frequencies.iter().flat_map(|(&symbol, &count)| std::iter::repeat_n(symbol, count)).collect();
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&frequencies).unwrap()).canonicalize();
+        assert_eq!(codes.iter().map(|(_, code)| code.len()).max(), Some(19));
+        let encoded = encode(input.chars(), &codes).unwrap();
+
+        assert_eq!(String::from_iter(decode_canonical(&encoded, &codes, input.len()).unwrap()), input);
+        let error = decode_canonical(&encoded[..encoded.len() - 1], &codes, input.len()).unwrap_err();
+        assert!(matches!(error, HuffmanError::Truncated));
+    }
+}
+
@@ -0,0 +1,433 @@
+use std::io::Read;
+
+use crate::adaptive::AdaptiveModel;
+use crate::compress::decode_block;
+use crate::crc::Crc32;
+use crate::error::{HuffmanError, Result};
+use crate::format::{self, AdaptiveBlockHeader, BlockHeader, Coding, Header, SymbolKind};
+use crate::SharedTable;
+
+/// Decompresses the stream read from `R`, yielding the original bytes
+/// (characters are yielded as UTF-8 and `u16` symbols as little-endian pairs
+/// of bytes).
+///
+/// One block is decoded at a time, so memory use is bounded by the block size
+/// the stream was written with. Files before format version 4 are a single
+/// block and are read whole. Streams written by
+/// [`AdaptiveEncoder`](crate::AdaptiveEncoder) are read the same way.
+///
+/// The checksum at the end of the stream is checked once it is reached, before
+/// the last blocks are yielded, so the read fails with
+/// [`HuffmanError::ChecksumMismatch`] if the data is not what was compressed.
+/// Once a read has failed, every later read fails with the same error rather
+/// than ending the stream early.
+///
+/// [`HuffmanDecoder::with_threads`] decodes several blocks at once instead.
+///
+/// ```
+/// use std::io::Read;
+///
+/// let compressed = huffman::compress("abbcccdddd");
+/// let mut decoder = huffman::HuffmanDecoder::new(compressed.as_slice());
+///
+/// let mut output = String::new();
+/// decoder.read_to_string(&mut output).unwrap();
+/// assert_eq!(output, "abbcccdddd");
+/// ```
+#[derive(Debug)]
+pub struct HuffmanDecoder<R: Read> {
+    inner: R,
+    header: Option<Header>,
+    /// The coding tree of an adaptive stream, carried from block to block.
+    model: AdaptiveModel,
+    buffer: Vec<u8>,
+    position: usize,
+    finished: bool,
+    threads: usize,
+    /// The CRC-32 of the bytes decoded so far.
+    crc: Crc32,
+    /// The table blocks may refer to instead of storing their own.
+    table: Option<SharedTable>,
+    /// The header of the block after those in the buffer, read ahead to
+    /// know whether they are the last.
+    next: Option<BlockHeader>,
+    next_adaptive: Option<AdaptiveBlockHeader>,
+    /// The error decoding failed with, which every later read returns once
+    /// the buffer is used up.
+    failed: Option<HuffmanError>,
+}
+
+impl<R: Read> HuffmanDecoder<R> {
+    pub fn new(inner: R) -> HuffmanDecoder<R> {
+        HuffmanDecoder::with_threads(inner, 1)
+    }
+
+    /// Creates a decoder that reads up to `threads` blocks ahead and decodes
+    /// them on a thread each, which holds as many blocks in memory. Nothing of
+    /// such a batch is yielded if any block of it fails to decode. Streams of
+    /// a single block or adaptively coded are still decoded on one thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is 0.
+    pub fn with_threads(inner: R, threads: usize) -> HuffmanDecoder<R> {
+        assert!(threads > 0, "Number of threads must not be 0");
+        HuffmanDecoder {
+            inner,
+            header: None,
+            model: AdaptiveModel::new(),
+            buffer: Vec::new(),
+            position: 0,
+            finished: false,
+            threads,
+            crc: Crc32::new(),
+            table: None,
+            next: None,
+            next_adaptive: None,
+            failed: None,
+        }
+    }
+
+    /// Decodes blocks that refer to `table` rather than storing a table, as
+    /// [`SharedTable::compress`] writes them. Without it, or if they refer to
+    /// another table, they fail with [`HuffmanError::MissingTable`].
+    pub fn with_table(mut self, table: SharedTable) -> HuffmanDecoder<R> {
+        self.table = Some(table);
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Reading from the inner reader directly will corrupt the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decodes the next blocks into the buffer, and marks the end of the
+    /// stream once it is reached. The header after them is read ahead, so
+    /// that the checksum is compared before the last of them are yielded; if
+    /// that fails, the error is kept for once they have been.
+    fn fill_buffer(&mut self) -> Result<()> {
+        let header = match self.header {
+            Some(header) => header,
+            None => *self.header.insert(Header::read(&mut self.inner)?),
+        };
+
+        if header.coding == Coding::Adaptive {
+            let block = match self.next_adaptive.take() {
+                Some(block) => block,
+                None => match AdaptiveBlockHeader::read(&mut self.inner)? {
+                    Some(block) => block,
+                    None => {
+                        self.finished = true;
+                        return self.check(&header);
+                    }
+                },
+            };
+
+            let bits = self.read_bits(Some(block.encoded_size))?;
+            let buffer = self.model.decode_block(&bits, block.length)?;
+            self.crc.update(&buffer);
+            match AdaptiveBlockHeader::read(&mut self.inner) {
+                Ok(Some(next)) => self.next_adaptive = Some(next),
+                Ok(None) => {
+                    self.finished = true;
+                    self.check(&header)?;
+                }
+                Err(error) => self.failed = Some(error),
+            }
+            self.buffer = buffer;
+            self.position = 0;
+            return Ok(());
+        }
+
+        let mut blocks = Vec::new();
+        while !self.finished {
+            let next = match self.next.take() {
+                Some(block) => Ok(Some(block)),
+                None => BlockHeader::read(&header, &mut self.inner),
+            };
+            match next {
+                Err(error) if !blocks.is_empty() => {
+                    self.failed = Some(error);
+                    break;
+                }
+                Err(error) => return Err(error),
+                Ok(Some(block)) if blocks.len() == self.threads => {
+                    self.next = Some(block);
+                    break;
+                }
+                Ok(Some(block)) => {
+                    let block = match &self.table {
+                        Some(table) => table.expand(block)?,
+                        None => block,
+                    };
+                    let bits = self.read_bits(block.encoded_size)?;
+                    blocks.push((block, bits));
+                    self.finished = header.is_single_block();
+                }
+                Ok(None) => self.finished = true,
+            }
+        }
+
+        let buffer = match blocks.as_slice() {
+            [] => Vec::new(),
+            [(block, bits)] => decode_to_bytes(header.kind, block, bits)?,
+            blocks => std::thread::scope(|scope| {
+                let workers: Vec<_> = blocks.iter()
+                    .map(|(block, bits)| scope.spawn(move || decode_to_bytes(header.kind, block, bits)))
+                    .collect();
+
+                workers.into_iter()
+                    .map(|worker| worker.join().expect("Decoding a block panicked"))
+                    .collect::<Result<Vec<Vec<u8>>>>()
+            })?
+            .concat(),
+        };
+        self.crc.update(&buffer);
+        // Nothing of the last blocks is yielded unless the checksum matches.
+        if self.finished {
+            self.check(&header)?;
+        }
+        self.buffer = buffer;
+        self.position = 0;
+
+        Ok(())
+    }
+
+    /// Compares the checksum at the end of the stream, if it has one, with
+    /// that of the bytes decoded.
+    fn check(&mut self, header: &Header) -> Result<()> {
+        if header.has_checksum() && format::read_checksum(&mut self.inner)? != self.crc.finish() {
+            return Err(HuffmanError::ChecksumMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Reads the `size` bytes of encoded bits of a block, or everything left
+    /// if the size is not stored.
+    fn read_bits(&mut self, size: Option<u64>) -> Result<Vec<u8>> {
+        let mut bits = Vec::new();
+        match size {
+            Some(size) => {
+                (&mut self.inner).take(size).read_to_end(&mut bits)?;
+                if bits.len() as u64 != size {
+                    return Err(HuffmanError::Truncated);
+                }
+            }
+            None => {
+                self.inner.read_to_end(&mut bits)?;
+            }
+        }
+
+        Ok(bits)
+    }
+}
+
+/// Decodes a block as the bytes [`HuffmanDecoder`] yields for it.
+fn decode_to_bytes(kind: SymbolKind, block: &BlockHeader, bits: &[u8]) -> Result<Vec<u8>> {
+    Ok(match kind {
+        SymbolKind::Chars => String::from_iter(decode_block::<char>(block, bits)?).into_bytes(),
+        SymbolKind::Bytes => decode_block::<u8>(block, bits)?,
+        SymbolKind::U16 => decode_block::<u16>(block, bits)?.into_iter().flat_map(u16::to_le_bytes).collect(),
+    })
+}
+
+impl<R: Read> Read for HuffmanDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if let Some(error) = &self.failed {
+                return Err(error.duplicate().into());
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            if let Err(error) = self.fill_buffer() {
+                self.failed = Some(error.duplicate());
+                return Err(error.into());
+            }
+        }
+
+        let length = buf.len().min(self.buffer.len() - self.position);
+        buf[..length].copy_from_slice(&self.buffer[self.position..self.position + length]);
+        self.position += length;
+
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, compress_bytes, HuffmanEncoder};
+    use std::io::Write;
+
+    #[test]
+    fn test_decoder() {
+        let input = "Les Misérables 霍夫曼";
+        let mut output = String::new();
+        HuffmanDecoder::new(compress(input).as_slice()).read_to_string(&mut output).unwrap();
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_decoder_small_reads() {
+        let input: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 256) as u8).collect();
+        let mut encoder = HuffmanEncoder::with_block_size(Vec::new(), 1024);
+        encoder.write_all(&input).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decoder = HuffmanDecoder::new(compressed.as_slice());
+        let mut output = Vec::new();
+        let mut chunk = [0; 3];
+        loop {
+            let length = decoder.read(&mut chunk).unwrap();
+            if length == 0 {
+                break;
+            }
+            output.extend_from_slice(&chunk[..length]);
+        }
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_decoder_is_lazy() {
+        let mut encoder = HuffmanEncoder::with_block_size(Vec::new(), 4);
+        encoder.write_all(b"abcdefgh").unwrap();
+        let mut compressed = encoder.finish().unwrap();
+
+        // Corrupt the second block; the first is still readable.
+        let length = compressed.len();
+        compressed.truncate(length - 16);
+
+        let mut decoder = HuffmanDecoder::new(compressed.as_slice());
+        let mut first = [0; 4];
+        decoder.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"abcd");
+
+        let error = decoder.read(&mut first).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_decoder_failure_sticks() {
+        let input: Vec<u8> = (0..500u32).map(|i| (i * i % 251) as u8).collect();
+        let compressed = compress_bytes(&input);
+
+        let mut encoder = crate::AdaptiveEncoder::new(Vec::new());
+        encoder.write_all(&input[..250]).unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(&input[250..]).unwrap();
+        let adaptive = encoder.finish().unwrap();
+
+        let flip = |data: &[u8]| {
+            let mut flipped = data.to_vec();
+            *flipped.last_mut().unwrap() ^= 1;
+            flipped
+        };
+        let (flipped, flipped_adaptive) = (flip(&compressed), flip(&adaptive));
+        let cut = &compressed[..compressed.len() - 4];
+        let cut_adaptive = &adaptive[..adaptive.len() - 4];
+
+        let streams = [(&flipped[..], 1), (cut, 1), (&flipped, 4), (cut, 4), (&flipped_adaptive, 1), (cut_adaptive, 1)];
+        for (data, threads) in streams {
+            let mut decoder = HuffmanDecoder::with_threads(data, threads);
+            let mut output = Vec::new();
+            let first = HuffmanError::from(decoder.read_to_end(&mut output).unwrap_err());
+            // The last block is not yielded, now or on reading again.
+            assert!(output.len() < input.len() && input.starts_with(&output));
+            for _ in 0..2 {
+                let again = HuffmanError::from(decoder.read(&mut [0; 64]).unwrap_err());
+                assert_eq!(again.to_string(), first.to_string());
+            }
+            assert!(matches!(first, HuffmanError::ChecksumMismatch | HuffmanError::Truncated), "{:?}", first);
+        }
+    }
+
+    #[test]
+    fn test_decoder_threads() {
+        let input: Vec<u8> = (0..5000u32).map(|i| (i * i % 251) as u8).collect();
+        let mut encoder = HuffmanEncoder::with_block_size(Vec::new(), 300);
+        encoder.write_all(&input).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        for threads in [1, 2, 5, 17, 64] {
+            let mut output = Vec::new();
+            HuffmanDecoder::with_threads(compressed.as_slice(), threads).read_to_end(&mut output).unwrap();
+            assert_eq!(output, input);
+        }
+
+        let mut output = String::new();
+        HuffmanDecoder::with_threads(compress("Les Misérables").as_slice(), 4).read_to_string(&mut output).unwrap();
+        assert_eq!(output, "Les Misérables");
+
+        // An error in any block of a batch fails the read.
+        let mut corrupt = compressed.clone();
+        corrupt.truncate(compressed.len() - 12);
+        let error = HuffmanDecoder::with_threads(corrupt.as_slice(), 4).read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_decoder_adaptive_blocks() {
+        // Flushing ends a block, and the model carries on across blocks.
+        let mut encoder = crate::AdaptiveEncoder::new(Vec::new());
+        encoder.write_all(b"abracadabra").unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(b", abracadabra").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut output = String::new();
+        HuffmanDecoder::new(compressed.as_slice()).read_to_string(&mut output).unwrap();
+        assert_eq!(output, "abracadabra, abracadabra");
+
+        let error = HuffmanDecoder::new(&compressed[..compressed.len() - 9]).read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_decoder_empty() {
+        let mut output = Vec::new();
+        HuffmanDecoder::new(compress_bytes(b"").as_slice()).read_to_end(&mut output).unwrap();
+
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_decoder_faulty_reader() {
+        use crate::faulty::{Faults, FaultyReader};
+
+        let input: Vec<u8> = (0..5000u32).map(|i| (i * i % 251) as u8).collect();
+        let mut encoder = HuffmanEncoder::with_block_size(Vec::new(), 300);
+        encoder.write_all(&input).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        for threads in [1, 4] {
+            let faults = Faults { short: Some(5), interrupt: true, ..Faults::default() };
+            let mut output = Vec::new();
+            HuffmanDecoder::with_threads(FaultyReader::new(compressed.as_slice(), faults), threads)
+                .read_to_end(&mut output)
+                .unwrap();
+            assert_eq!(output, input);
+
+            // A failure anywhere, even in the checksum, fails the read rather
+            // than ending it early.
+            for fail_after in [0, 3, 100, compressed.len() / 2, compressed.len() - 1] {
+                let faults = Faults { fail_after: Some(fail_after), ..Faults::default() };
+                let reader = FaultyReader::new(compressed.as_slice(), faults);
+                let mut decoder = HuffmanDecoder::with_threads(reader, threads);
+                let error = decoder.read_to_end(&mut Vec::new()).unwrap_err();
+                assert_eq!(error.kind(), std::io::ErrorKind::Other);
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::bits::BitWriter;
+use crate::code::CodeTable;
+use crate::error::{HuffmanError, Result};
+use crate::symbol::Symbol;
+
+/// Replaces every symbol of `input` with its code and packs the result into
+/// bytes. The last byte is padded with zero bits.
+///
+/// Fails with [`HuffmanError::InvalidInput`] if `input` contains a symbol that
+/// has no code in `codes`.
+pub fn encode<S: Symbol>(input: impl IntoIterator<Item = S>, codes: &CodeTable<S>) -> Result<Vec<u8>> {
+    let mut writer = BitWriter::new();
+
+    for symbol in input {
+        let code = codes.get(symbol).ok_or_else(|| HuffmanError::InvalidInput(format!("No code for {:?}", symbol)))?;
+        for bit in code.chars() {
+            writer.write_bit(bit == '1');
+        }
+    }
+
+    Ok(writer.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_frequencies, Tree};
+
+    #[test]
+    fn test_encode() {
+        let input = "abbcccdddd";
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&get_frequencies(input)).unwrap());
+
+        let bits: String = input.chars().map(|c| codes.get(c).unwrap()).collect();
+        assert_eq!(bits.len(), 19);
+
+        let encoded = encode(input.chars(), &codes).unwrap();
+        assert_eq!(encoded.len(), 3);
+
+        let decoded_bits: String = encoded.iter().map(|byte| format!("{:08b}", byte)).collect();
+        assert_eq!(&decoded_bits[..19], bits);
+        assert_eq!(&decoded_bits[19..], "00000");
+    }
+
+    #[test]
+    fn test_encode_single_symbol() {
+        let input = "aaaa";
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&get_frequencies(input)).unwrap());
+
+        assert_eq!(encode(input.chars(), &codes).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_encode_missing_code() {
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&get_frequencies("ab")).unwrap());
+
+        assert!(matches!(encode("abc".chars(), &codes), Err(HuffmanError::InvalidInput(_))));
+    }
+}
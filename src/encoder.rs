@@ -0,0 +1,212 @@
+use std::io::Write;
+
+use crate::compress::{write_block, DEFAULT_BLOCK_SIZE};
+use crate::crc::Crc32;
+use crate::error::{HuffmanError, Result};
+use crate::format::{self, BlockHeader, Header, SymbolKind};
+
+/// Compresses the bytes written to it into `W`, producing the same format as
+/// [`compress_bytes`](crate::compress_bytes) without needing the whole input
+/// in memory.
+///
+/// Input is buffered and coded in blocks. Call [`HuffmanEncoder::finish`] once
+/// done to write the last block and end the stream; dropping the encoder
+/// discards whatever is still buffered.
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut encoder = huffman::HuffmanEncoder::new(Vec::new());
+/// encoder.write_all(b"abbcccdddd").unwrap();
+/// let compressed = encoder.finish().unwrap();
+///
+/// assert_eq!(huffman::decompress_bytes(&compressed).unwrap(), b"abbcccdddd");
+/// ```
+#[derive(Debug)]
+pub struct HuffmanEncoder<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+    block_size: usize,
+    started: bool,
+    crc: Crc32,
+    /// Whether writing to the inner writer failed, which may leave part of a
+    /// block there.
+    failed: bool,
+}
+
+impl<W: Write> HuffmanEncoder<W> {
+    /// Creates an encoder with blocks of [`DEFAULT_BLOCK_SIZE`] bytes.
+    pub fn new(inner: W) -> HuffmanEncoder<W> {
+        HuffmanEncoder::with_block_size(inner, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Creates an encoder that codes every `block_size` bytes with their own
+    /// table. Larger blocks hold more input in memory and spend less on tables.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is 0.
+    pub fn with_block_size(inner: W, block_size: usize) -> HuffmanEncoder<W> {
+        assert!(block_size > 0, "Block size must not be 0");
+        HuffmanEncoder { inner, buffer: Vec::new(), block_size, started: false, crc: Crc32::new(), failed: false }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Writing to the inner writer directly will corrupt the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Writes the buffered input, the end of the stream and its checksum, and
+    /// returns the inner writer.
+    ///
+    /// Once writing to the inner writer has failed, this and every other write
+    /// fails too, so a stream cut short is never ended as if it were whole.
+    pub fn finish(mut self) -> Result<W> {
+        self.write_buffered()?;
+        BlockHeader::write_end(&mut self.inner)?;
+        format::write_checksum(&mut self.inner, self.crc.finish())?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+
+    fn write_buffered(&mut self) -> Result<()> {
+        if self.failed {
+            return Err(after_failure());
+        }
+
+        // Until this succeeds, as a block cannot be taken back once partly
+        // written.
+        self.failed = true;
+        if !self.started {
+            Header::new(SymbolKind::Bytes).write(&mut self.inner)?;
+            self.started = true;
+        }
+
+        if !self.buffer.is_empty() {
+            write_block(SymbolKind::Bytes, &self.buffer, &mut self.inner)?;
+            self.buffer.clear();
+        }
+        self.failed = false;
+
+        Ok(())
+    }
+}
+
+/// The error of writing to an encoder whose inner writer failed before.
+pub(crate) fn after_failure() -> HuffmanError {
+    HuffmanError::Io(std::io::Error::other("An earlier write failed, so the stream cannot be continued"))
+}
+
+impl<W: Write> Write for HuffmanEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // Write out a full block before taking more input, so that nothing is
+        // consumed if that fails.
+        if self.buffer.len() == self.block_size {
+            self.write_buffered()?;
+        }
+
+        let length = buf.len().min(self.block_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..length]);
+        self.crc.update(&buf[..length]);
+
+        Ok(length)
+    }
+
+    /// Codes the buffered input as a block of its own, however short, so that
+    /// everything written so far can be decoded. Flushing often costs ratio.
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.write_buffered()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faulty::{Faults, FaultyWriter};
+    use crate::{compress_bytes, decompress_bytes};
+
+    #[test]
+    fn test_encoder_matches_compress_bytes() {
+        let input = b"test\nmyreallycooltest";
+
+        let mut encoder = HuffmanEncoder::new(Vec::new());
+        for chunk in input.chunks(3) {
+            encoder.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(encoder.finish().unwrap(), compress_bytes(input));
+    }
+
+    #[test]
+    fn test_encoder_blocks() {
+        let input: Vec<u8> = (0..10_000u32).map(|i| (i * i % 251) as u8).collect();
+
+        let mut encoder = HuffmanEncoder::with_block_size(Vec::new(), 1000);
+        for chunk in input.chunks(777) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(decompress_bytes(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_encoder_flush() {
+        let mut encoder = HuffmanEncoder::new(Vec::new());
+        encoder.write_all(b"abb").unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(b"ccc").unwrap();
+        encoder.flush().unwrap();
+        encoder.flush().unwrap();
+
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(decompress_bytes(&compressed).unwrap(), b"abbccc");
+    }
+
+    #[test]
+    fn test_encoder_empty() {
+        let compressed = HuffmanEncoder::new(Vec::new()).finish().unwrap();
+
+        assert_eq!(compressed, compress_bytes(b""));
+        assert_eq!(decompress_bytes(&compressed).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_encoder_faulty_writer() {
+        let input: Vec<u8> = (0..10_000u32).map(|i| (i * i % 251) as u8).collect();
+
+        // Short and interrupted writes are retried.
+        let faults = Faults { short: Some(7), interrupt: true, ..Faults::default() };
+        let mut encoder = HuffmanEncoder::with_block_size(FaultyWriter::new(Vec::new(), faults), 1000);
+        encoder.write_all(&input).unwrap();
+        let compressed = encoder.finish().unwrap().inner;
+        assert_eq!(decompress_bytes(&compressed).unwrap(), input);
+
+        // Everything but the end of the stream and its checksum.
+        let blocks = compressed.len() - 12;
+        for fail_after in [0, 5, 500, blocks - 1] {
+            let faults = Faults { fail_after: Some(fail_after), ..Faults::default() };
+            let mut encoder = HuffmanEncoder::with_block_size(FaultyWriter::new(Vec::new(), faults), 1000);
+            assert!(encoder.write_all(&input).and_then(|()| encoder.flush()).is_err());
+
+            // Failing once fails for good, so no end is written after a
+            // partial block.
+            let error = encoder.flush().and_then(|()| encoder.write_all(b"more")).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::Other);
+            let error = encoder.finish().unwrap_err();
+            assert!(matches!(&error, HuffmanError::Io(error) if error.kind() == std::io::ErrorKind::Other));
+        }
+
+        let faults = Faults { fail_after: Some(blocks + 8), ..Faults::default() };
+        let mut encoder = HuffmanEncoder::with_block_size(FaultyWriter::new(Vec::new(), faults), 1000);
+        encoder.write_all(&input).unwrap();
+        encoder.flush().unwrap();
+        assert!(encoder.finish().is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+/// A [`Result`](core::result::Result) with a [`HuffmanError`].
+pub type Result<T> = core::result::Result<T, HuffmanError>;
+
+/// What data was read as, where an error depends on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// A compressed file, laid out as [`format`](crate::format) describes.
+    Compressed,
+    /// An [`archive`](crate::archive) of compressed files.
+    Archive,
+}
+
+/// Everything that can go wrong compressing or decompressing.
+#[derive(Debug)]
+pub enum HuffmanError {
+    /// Reading or writing failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// There were no symbols to build a tree from.
+    EmptyInput,
+    /// The input ended in the middle of a header or of the encoded bits.
+    Truncated,
+    /// The data does not start with the magic bytes of what it was read as.
+    BadMagic(Container),
+    /// The data is of a version this build does not read, probably a newer
+    /// one.
+    UnsupportedVersion { container: Container, version: u8 },
+    /// A file or block header is malformed.
+    BadHeader(String),
+    /// The encoded bits do not decode with the codes of their block.
+    CorruptStream(String),
+    /// The data decoded, but its checksum differs from the one stored.
+    ChecksumMismatch,
+    /// Text was expected but the data is not UTF-8.
+    NotUtf8,
+    /// The compressed data holds other symbols than were asked for.
+    WrongSymbolKind,
+    /// The blocks refer to a [`SharedTable`](crate::SharedTable) that was not
+    /// given to decode them.
+    MissingTable,
+    /// A [`SharedTable`](crate::SharedTable) read from JSON is malformed.
+    InvalidTable(String),
+    /// Input to code as `u16`s is an odd number of bytes.
+    OddLength,
+    /// The arguments cannot be coded or written, such as a symbol with no code.
+    InvalidInput(String),
+    /// Another error, with what was being done when it happened and what to
+    /// do about it, as added by [`HuffmanError::context`] and
+    /// [`HuffmanError::with_hint`].
+    Context { error: Box<HuffmanError>, context: Option<String>, hint: Option<String> },
+}
+
+impl HuffmanError {
+    /// This error, saying it happened in `context`, such as `"entry a.txt"`,
+    /// which goes in front of its message.
+    pub fn context(self, context: impl Into<String>) -> HuffmanError {
+        HuffmanError::Context { error: Box::new(self), context: Some(context.into()), hint: None }
+    }
+
+    /// This error, with `hint` on what to do about it in place of the one
+    /// [`HuffmanError::hint`] would give.
+    pub fn with_hint(self, hint: impl Into<String>) -> HuffmanError {
+        match self {
+            HuffmanError::Context { error, context, .. } => HuffmanError::Context { error, context, hint: Some(hint.into()) },
+            error => HuffmanError::Context { error: Box::new(error), context: None, hint: Some(hint.into()) },
+        }
+    }
+
+    /// The error under any [`HuffmanError::Context`], to tell what went wrong.
+    ///
+    /// ```
+    /// use huffman::{Container, HuffmanError};
+    ///
+    /// let error = huffman::decompress_bytes(b"not compressed").unwrap_err().context("a.txt");
+    /// assert_eq!(error.to_string(), "a.txt: Invalid header: Not a compressed file");
+    /// assert!(matches!(error.root(), HuffmanError::BadMagic(Container::Compressed)));
+    /// ```
+    pub fn root(&self) -> &HuffmanError {
+        match self {
+            HuffmanError::Context { error, .. } => error.root(),
+            error => error,
+        }
+    }
+
+    /// What to do about this error: the hint given with
+    /// [`HuffmanError::with_hint`], or else one for what went wrong, if there
+    /// is more to say than the message does.
+    pub fn hint(&self) -> Option<&str> {
+        if let HuffmanError::Context { error, hint, .. } = self {
+            return hint.as_deref().or_else(|| error.hint());
+        }
+
+        Some(match self {
+            HuffmanError::BadMagic(Container::Compressed) => {
+                "the data does not start with the HUFF magic; it may not be compressed, or be an archive"
+            }
+            HuffmanError::BadMagic(Container::Archive) => {
+                "the data does not start with the HUFA magic; it may be a single compressed file"
+            }
+            HuffmanError::UnsupportedVersion { .. } => "it was probably written by a newer version of this library",
+            HuffmanError::Truncated => "the data was cut short, perhaps by an interrupted copy or download",
+            HuffmanError::CorruptStream(_) | HuffmanError::ChecksumMismatch => {
+                "the data is damaged; compress the original again if it is still there"
+            }
+            HuffmanError::MissingTable => "decode it with the SharedTable it was compressed with",
+            HuffmanError::OddLength => "u16 symbols take two bytes each; code the input as bytes instead",
+            HuffmanError::InvalidTable(_) => "shared tables are read from what SharedTable::to_json writes",
+            _ => return None,
+        })
+    }
+
+    /// A copy of this error, to return again. An I/O error keeps its kind
+    /// and message, but not what is under it.
+    #[cfg(feature = "std")]
+    pub(crate) fn duplicate(&self) -> HuffmanError {
+        match self {
+            HuffmanError::Io(error) => HuffmanError::Io(io::Error::new(error.kind(), error.to_string())),
+            HuffmanError::EmptyInput => HuffmanError::EmptyInput,
+            HuffmanError::Truncated => HuffmanError::Truncated,
+            HuffmanError::BadMagic(container) => HuffmanError::BadMagic(*container),
+            HuffmanError::UnsupportedVersion { container, version } => {
+                HuffmanError::UnsupportedVersion { container: *container, version: *version }
+            }
+            HuffmanError::BadHeader(message) => HuffmanError::BadHeader(message.clone()),
+            HuffmanError::CorruptStream(message) => HuffmanError::CorruptStream(message.clone()),
+            HuffmanError::ChecksumMismatch => HuffmanError::ChecksumMismatch,
+            HuffmanError::NotUtf8 => HuffmanError::NotUtf8,
+            HuffmanError::WrongSymbolKind => HuffmanError::WrongSymbolKind,
+            HuffmanError::MissingTable => HuffmanError::MissingTable,
+            HuffmanError::InvalidTable(message) => HuffmanError::InvalidTable(message.clone()),
+            HuffmanError::OddLength => HuffmanError::OddLength,
+            HuffmanError::InvalidInput(message) => HuffmanError::InvalidInput(message.clone()),
+            HuffmanError::Context { error, context, hint } => {
+                HuffmanError::Context { error: Box::new(error.duplicate()), context: context.clone(), hint: hint.clone() }
+            }
+        }
+    }
+}
+
+impl fmt::Display for HuffmanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            HuffmanError::Io(error) => write!(f, "{}", error),
+            HuffmanError::EmptyInput => write!(f, "Input is empty"),
+            HuffmanError::Truncated => write!(f, "Compressed data ended early"),
+            HuffmanError::BadMagic(Container::Compressed) => write!(f, "Invalid header: Not a compressed file"),
+            HuffmanError::BadMagic(Container::Archive) => write!(f, "Invalid header: Not an archive"),
+            HuffmanError::UnsupportedVersion { container, version } => {
+                let name = if *container == Container::Archive { "archive" } else { "format" };
+                write!(f, "Invalid header: Unsupported {} version {}", name, version)
+            }
+            HuffmanError::BadHeader(message) => write!(f, "Invalid header: {}", message),
+            HuffmanError::CorruptStream(message) => write!(f, "Corrupt data: {}", message),
+            HuffmanError::ChecksumMismatch => write!(f, "Corrupted archive: the data does not match its checksum"),
+            HuffmanError::NotUtf8 => write!(f, "Data is not UTF-8"),
+            HuffmanError::WrongSymbolKind => write!(f, "Compressed data holds other symbols than expected"),
+            HuffmanError::MissingTable => write!(f, "Compressed with a shared table that was not given"),
+            HuffmanError::InvalidTable(message) => write!(f, "Invalid table: {}", message),
+            HuffmanError::OddLength => write!(f, "Input is not a whole number of u16 symbols"),
+            HuffmanError::InvalidInput(message) => write!(f, "{}", message),
+            HuffmanError::Context { error, context: Some(context), .. } => write!(f, "{}: {}", context, error),
+            HuffmanError::Context { error, context: None, .. } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl core::error::Error for HuffmanError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            HuffmanError::Io(error) => Some(error),
+            // The message already says what the error under it does.
+            HuffmanError::Context { error, .. } => error.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Unwraps errors made by the conversion the other way, so they survive a trip
+/// through a [`Read`](io::Read) or [`Write`](io::Write) implementation.
+#[cfg(feature = "std")]
+impl From<io::Error> for HuffmanError {
+    fn from(error: io::Error) -> HuffmanError {
+        error.downcast::<HuffmanError>().unwrap_or_else(HuffmanError::Io)
+    }
+}
+
+/// For [`Read`](io::Read) and [`Write`](io::Write) implementations, which
+/// must return [`io::Error`]s. I/O errors are passed through unchanged.
+#[cfg(feature = "std")]
+impl From<HuffmanError> for io::Error {
+    fn from(error: HuffmanError) -> io::Error {
+        if let HuffmanError::Io(error) = error {
+            return error;
+        }
+
+        // With context, the kind is still that of what went wrong.
+        let kind = match error.root() {
+            HuffmanError::Io(error) => error.kind(),
+            HuffmanError::Truncated => io::ErrorKind::UnexpectedEof,
+            HuffmanError::InvalidTable(_) | HuffmanError::OddLength | HuffmanError::InvalidInput(_) => {
+                io::ErrorKind::InvalidInput
+            }
+            _ => io::ErrorKind::InvalidData,
+        };
+
+        io::Error::new(kind, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_io_round_trip() {
+        let error = HuffmanError::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::PermissionDenied);
+
+        let error = io::Error::from(HuffmanError::Truncated);
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(matches!(HuffmanError::from(error), HuffmanError::Truncated));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(HuffmanError::BadHeader("Unknown symbol kind 7".to_string()).to_string(),
+            "Invalid header: Unknown symbol kind 7");
+        assert_eq!(HuffmanError::BadMagic(Container::Archive).to_string(), "Invalid header: Not an archive");
+        let version = HuffmanError::UnsupportedVersion { container: Container::Compressed, version: 11 };
+        assert_eq!(version.to_string(), "Invalid header: Unsupported format version 11");
+    }
+
+    #[test]
+    fn test_context() {
+        let error = HuffmanError::Truncated.context("entry a.txt");
+        assert_eq!(error.to_string(), "entry a.txt: Compressed data ended early");
+        assert!(matches!(error.root(), HuffmanError::Truncated));
+        assert_eq!(error.hint(), HuffmanError::Truncated.hint());
+
+        let error = error.context("b.huf");
+        assert_eq!(error.to_string(), "b.huf: entry a.txt: Compressed data ended early");
+        assert!(matches!(error.root(), HuffmanError::Truncated));
+    }
+
+    #[test]
+    fn test_hint() {
+        assert!(HuffmanError::BadMagic(Container::Compressed).hint().unwrap().contains("HUFF"));
+        assert!(HuffmanError::EmptyInput.hint().is_none());
+
+        let error = HuffmanError::EmptyInput.with_hint("give it some symbols");
+        assert_eq!(error.to_string(), "Input is empty");
+        assert_eq!(error.hint(), Some("give it some symbols"));
+
+        let error = HuffmanError::Truncated.context("a.txt").with_hint("download it again");
+        assert_eq!(error.to_string(), "a.txt: Compressed data ended early");
+        assert_eq!(error.hint(), Some("download it again"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_io_kind_under_context() {
+        let error = HuffmanError::Truncated.context("a.txt");
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::UnexpectedEof);
+
+        let error = HuffmanError::from(io::Error::new(io::ErrorKind::NotFound, "gone")).context("a.txt");
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::NotFound);
+    }
+}
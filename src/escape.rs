@@ -0,0 +1,201 @@
+//! Escape coding for alphabets too large to give every symbol a table entry.
+//!
+//! Only the most frequent symbols get codes. All others share an escape code,
+//! followed by the symbol's `u32` value in a fixed number of bits.
+
+use std::collections::HashMap;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::code::CodeTable;
+use crate::decode::CanonicalDecoder;
+use crate::error::{HuffmanError, Result};
+use crate::symbol::Symbol;
+use crate::tree::Tree;
+
+/// The most occurrences a symbol can have and still be escaped. Symbols any
+/// more frequent than this are cheaper to store in the table in practice.
+const MAX_ESCAPED_FREQUENCY: usize = 16;
+
+/// A symbol from the table, or the escape that stands for all the others.
+///
+/// The escape sorts after every symbol, and so takes the last canonical code
+/// of its length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Escaped<S> {
+    Symbol(S),
+    Escape,
+}
+
+impl<S: Symbol> Symbol for Escaped<S> {
+    fn to_u32(self) -> u32 {
+        match self {
+            Escaped::Symbol(symbol) => symbol.to_u32(),
+            Escaped::Escape => u32::MAX,
+        }
+    }
+
+    fn from_u32(value: u32) -> Option<Escaped<S>> {
+        match value {
+            u32::MAX => Some(Escaped::Escape),
+            value => S::from_u32(value).map(Escaped::Symbol),
+        }
+    }
+}
+
+/// Codes for a block, possibly with an escape.
+#[derive(Debug, Clone)]
+pub(crate) struct EscapeTable<S> {
+    pub(crate) codes: CodeTable<Escaped<S>>,
+    /// The number of bits of each escaped symbol's value.
+    pub(crate) literal_width: u8,
+}
+
+impl<S: Symbol> EscapeTable<S> {
+    /// The tables worth considering for `frequencies`: one coding every
+    /// symbol, and one escaping the symbols that occur fewer than `n` times for
+    /// each `n` up to [`MAX_ESCAPED_FREQUENCY`] that escapes more. A full search
+    /// would build a tree per symbol.
+    ///
+    /// `frequencies` must not be empty.
+    pub(crate) fn candidates(frequencies: &HashMap<S, usize>) -> Vec<EscapeTable<S>> {
+        let mut symbols: Vec<(usize, S)> = frequencies.iter().map(|(&symbol, &frequency)| (frequency, symbol)).collect();
+        symbols.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let mut counts: Vec<usize> = (1..=MAX_ESCAPED_FREQUENCY + 1)
+            .map(|threshold| symbols.iter().take_while(|&&(frequency, _)| frequency >= threshold).count())
+            .collect();
+        counts.dedup();
+
+        counts.into_iter().map(|count| EscapeTable::with_tabled(&symbols, count)).collect()
+    }
+
+    /// Builds the table giving codes to the first `count` of `symbols`, which
+    /// are sorted by descending frequency.
+    fn with_tabled(symbols: &[(usize, S)], count: usize) -> EscapeTable<S> {
+        let mut frequencies: HashMap<Escaped<S>, usize> =
+            symbols[..count].iter().map(|&(frequency, symbol)| (Escaped::Symbol(symbol), frequency)).collect();
+
+        let escaped = &symbols[count..];
+        let mut literal_width = 0;
+        if !escaped.is_empty() {
+            frequencies.insert(Escaped::Escape, escaped.iter().map(|&(frequency, _)| frequency).sum());
+
+            let max_value = escaped.iter().map(|&(_, symbol)| symbol.to_u32()).max().unwrap_or(0);
+            literal_width = (u32::BITS - max_value.leading_zeros()).max(1) as u8;
+        }
+
+        let tree = Tree::from_frequencies(&frequencies).expect("A table codes at least the escape");
+        let codes = CodeTable::from_tree(&tree).canonicalize();
+        EscapeTable { codes, literal_width }
+    }
+
+    /// The number of bits [`EscapeTable::encode`] takes for symbols with
+    /// `frequencies`.
+    pub(crate) fn encoded_bits(&self, frequencies: &HashMap<S, usize>) -> u64 {
+        let escape_length = self.codes.get(Escaped::Escape).map_or(0, str::len);
+
+        frequencies.iter()
+            .map(|(&symbol, &frequency)| {
+                let length = match self.codes.get(Escaped::Symbol(symbol)) {
+                    Some(code) => code.len(),
+                    None => escape_length + self.literal_width as usize,
+                };
+                (frequency * length) as u64
+            })
+            .sum()
+    }
+
+    /// Whether [`EscapeTable::encode`] can code `symbol`, by its own code or as
+    /// an escaped literal that fits the width.
+    pub(crate) fn can_encode(&self, symbol: S) -> bool {
+        self.codes.get(Escaped::Symbol(symbol)).is_some()
+            || (self.codes.get(Escaped::Escape).is_some() && (symbol.to_u32() as u64) < 1 << self.literal_width)
+    }
+
+    /// Encodes `input`, escaping symbols without a code of their own.
+    pub(crate) fn encode(&self, input: &[S]) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+
+        for &symbol in input {
+            let (code, literal) = match self.codes.get(Escaped::Symbol(symbol)) {
+                Some(code) => (code, None),
+                None => (self.codes.get(Escaped::Escape).expect("No code or escape for symbol"), Some(symbol.to_u32())),
+            };
+
+            for bit in code.chars() {
+                writer.write_bit(bit == '1');
+            }
+            if let Some(value) = literal {
+                writer.write_bits(value, self.literal_width);
+            }
+        }
+
+        writer.finish()
+    }
+
+    /// Decodes `length` symbols encoded by [`EscapeTable::encode`].
+    pub(crate) fn decode(&self, bytes: &[u8], length: usize) -> Result<Vec<S>> {
+        let decoder = CanonicalDecoder::new(&self.codes);
+        let mut reader = BitReader::new(bytes);
+
+        (0..length)
+            .map(|_| match decoder.decode_symbol(&mut reader)? {
+                Escaped::Symbol(symbol) => Ok(symbol),
+                Escaped::Escape => {
+                    let value = reader.read_bits(self.literal_width).ok_or(HuffmanError::Truncated)?;
+                    S::from_u32(value).ok_or_else(|| HuffmanError::CorruptStream("Invalid escaped symbol".to_string()))
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frequency::count_symbols;
+
+    #[test]
+    fn test_candidates() {
+        let input: Vec<char> = "aaaabbbbccccdddd".chars().chain('\u{1F600}'..'\u{1F640}').collect();
+        let frequencies = count_symbols(input.iter().copied());
+        let candidates = EscapeTable::candidates(&frequencies);
+
+        // Everything, the letters and the escape, then just the escape.
+        let lengths: Vec<usize> = candidates.iter().map(|table| table.codes.len()).collect();
+        assert_eq!(lengths, [68, 5, 1]);
+        assert_eq!(candidates[0].codes.get(Escaped::Escape), None);
+        assert_eq!(candidates[1].codes.get(Escaped::Symbol('\u{1F600}')), None);
+        assert_eq!(candidates[1].literal_width, 17);
+
+        for table in candidates {
+            assert!(input.iter().all(|&symbol| table.can_encode(symbol)));
+            let encoded = table.encode(&input);
+            assert_eq!(table.encoded_bits(&frequencies).div_ceil(8), encoded.len() as u64);
+            assert_eq!(table.decode(&encoded, input.len()).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_escape_everything() {
+        let input: Vec<u16> = vec![1, 2, 3, 0xFFFF];
+        let candidates = EscapeTable::candidates(&count_symbols(input.iter().copied()));
+        let table = candidates.last().unwrap();
+
+        assert_eq!(table.codes.len(), 1);
+        assert_eq!(table.literal_width, 16);
+        assert_eq!(table.decode(&table.encode(&input), input.len()).unwrap(), input);
+    }
+
+    #[test]
+    fn test_invalid_escaped_symbol() {
+        let codes = CodeTable::from_code_lengths(&HashMap::from([(Escaped::Escape, 1)])).unwrap();
+        let table: EscapeTable<char> = EscapeTable { codes, literal_width: 16 };
+
+        assert!(table.can_encode('\u{FFFF}') && !table.can_encode('\u{10000}'));
+
+        // The escape, then the surrogate 0xD800.
+        let error = table.decode(&[0b0110_1100, 0b0000_0000, 0], 1).unwrap_err();
+        assert!(matches!(error, HuffmanError::CorruptStream(_)));
+    }
+}
@@ -0,0 +1,124 @@
+//! Readers and writers that misbehave on purpose, for testing how errors are
+//! handled.
+
+use std::io::{self, Read, Write};
+
+/// The ways [`FaultyWriter`] and [`FaultyReader`] misbehave.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Faults {
+    /// The most bytes taken or given per call, if limited.
+    pub(crate) short: Option<usize>,
+    /// Whether every other call fails with [`io::ErrorKind::Interrupted`],
+    /// which callers are expected to retry.
+    pub(crate) interrupt: bool,
+    /// The number of bytes after which every call fails, if any.
+    pub(crate) fail_after: Option<usize>,
+}
+
+impl Faults {
+    /// How many of `wanted` bytes to move after `done`, or the error to fail
+    /// with instead.
+    fn allow(&self, done: usize, wanted: usize, interrupted: &mut bool) -> io::Result<usize> {
+        if self.interrupt {
+            *interrupted = !*interrupted;
+            if *interrupted {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+        }
+
+        let mut allowed = wanted.min(self.short.unwrap_or(usize::MAX));
+        if let Some(limit) = self.fail_after {
+            if done >= limit && wanted > 0 {
+                return Err(io::Error::other("Injected failure"));
+            }
+            allowed = allowed.min(limit - done);
+        }
+
+        Ok(allowed)
+    }
+}
+
+/// Writes to `W` with the given [`Faults`].
+#[derive(Debug)]
+pub(crate) struct FaultyWriter<W: Write> {
+    pub(crate) inner: W,
+    faults: Faults,
+    written: usize,
+    interrupted: bool,
+}
+
+impl<W: Write> FaultyWriter<W> {
+    pub(crate) fn new(inner: W, faults: Faults) -> FaultyWriter<W> {
+        FaultyWriter { inner, faults, written: 0, interrupted: false }
+    }
+}
+
+impl<W: Write> Write for FaultyWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let allowed = self.faults.allow(self.written, buf.len(), &mut self.interrupted)?;
+        let length = self.inner.write(&buf[..allowed])?;
+        self.written += length;
+
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads from `R` with the given [`Faults`].
+#[derive(Debug)]
+pub(crate) struct FaultyReader<R: Read> {
+    inner: R,
+    faults: Faults,
+    read: usize,
+    interrupted: bool,
+}
+
+impl<R: Read> FaultyReader<R> {
+    pub(crate) fn new(inner: R, faults: Faults) -> FaultyReader<R> {
+        FaultyReader { inner, faults, read: 0, interrupted: false }
+    }
+}
+
+impl<R: Read> Read for FaultyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let allowed = self.faults.allow(self.read, buf.len(), &mut self.interrupted)?;
+        let length = self.inner.read(&mut buf[..allowed])?;
+        self.read += length;
+
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faulty_writer() {
+        let faults = Faults { short: Some(3), interrupt: true, ..Faults::default() };
+        let mut writer = FaultyWriter::new(Vec::new(), faults);
+        assert_eq!(writer.write(b"abcdef").unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert_eq!(writer.write(b"abcdef").unwrap(), 3);
+        writer.write_all(b"ghijk").unwrap();
+        assert_eq!(writer.inner, b"abcghijk");
+
+        let mut writer = FaultyWriter::new(Vec::new(), Faults { fail_after: Some(4), ..Faults::default() });
+        assert!(writer.write_all(b"abcdef").is_err());
+        assert_eq!(writer.inner, b"abcd");
+    }
+
+    #[test]
+    fn test_faulty_reader() {
+        let faults = Faults { short: Some(2), interrupt: true, fail_after: Some(5) };
+        let mut reader = FaultyReader::new(b"abcdefgh".as_slice(), faults);
+        let mut bytes = [0; 5];
+        reader.read_exact(&mut bytes).unwrap();
+        assert_eq!(&bytes, b"abcde");
+
+        assert!(reader.read(&mut bytes).is_err());
+        assert!(reader.read(&mut bytes).is_err());
+    }
+}
@@ -0,0 +1,1171 @@
+//! The compressed file format.
+//!
+//! Every file starts with the [`MAGIC`] bytes and a format version. All
+//! integers are little-endian.
+//!
+//! Since version 4, the version is followed by the [`SymbolKind`] as a `u8`
+//! (0 for characters, 1 for bytes, 2 for `u16`s), since version 7 by the
+//! [`Coding`] as a `u8` (0 for static, 1 for adaptive), since version 8 by a
+//! `u8` 0, or a `u8` 1 and the `u64`s of a [`Source`] (its size, time,
+//! checksum and, since version 11, settings), and then by a sequence of
+//! blocks. With static coding, each block is coded with its own table and
+//! contains:
+//!
+//! - the number of symbols as a `u64`, which is never 0,
+//! - the [`TableEncoding`] as a `u8` (0 for code lengths, 1 for topology, 2
+//!   for a shared table),
+//! - the number of distinct symbols in the table as a `u32`,
+//! - for code lengths, per symbol in ascending order, its value and its code
+//!   length as a `u8`, and for topology, the value of each symbol in the order
+//!   of their codes. Characters are stored as their `u32` scalar value, bytes
+//!   and `u16`s as themselves,
+//! - the code length of the escape as a `u8`, or 0 if the block has none,
+//!   and if it has one, the width of escaped symbols in bits as a `u8`,
+//! - for topology, the shape of the code tree in preorder, with a 1 bit for
+//!   each internal node and a 0 bit for each leaf, padded with zeros to a
+//!   whole byte. The leaves are the symbols in order, except that the escape
+//!   is the last leaf at the depth of its code length. A tree that is a single
+//!   leaf gives it a code of length 1,
+//! - the size of the encoded bits in bytes as a `u64`,
+//! - the encoded bits, padded with zeros to a whole byte.
+//!
+//! Since version 10, a block coded with a [`SharedTable`](crate::SharedTable)
+//! stores the table's `u32` id in place of the table and escape, from the
+//! number of distinct symbols to the escape's width; the table itself is given
+//! to the decoder.
+//!
+//! A `u64` 0 in place of the number of symbols ends the stream, followed since
+//! version 9 by the CRC-32 (IEEE) of the decompressed bytes as a `u32`, as
+//! [`decompress_bytes`](crate::decompress_bytes) returns them.
+//!
+//! With adaptive coding, which is only used for bytes, each block contains
+//! just the number of symbols as a `u64`, the size of the encoded bits in
+//! bytes as a `u64` and the bits (see [`AdaptiveEncoder`](crate::AdaptiveEncoder)),
+//! and a `u64` 0 and the CRC-32 again end the stream. The coding tree
+//! carries on from one block to the next.
+//!
+//! The bits are encoded with canonical codes (see
+//! [`CodeTable::from_code_lengths`](crate::CodeTable::from_code_lengths)),
+//! where the escape counts as a symbol above all others. Symbols not in the
+//! table are encoded as the escape followed by their value in the escape's
+//! width, most significant bit first. This keeps tables small for large
+//! alphabets, such as text with many rare characters.
+//!
+//! Version 10 has the same layout without the settings of the source, version
+//! 9 also lacks shared tables, version 8 also lacks the CRC-32, and version 7
+//! also lacks the source. Version 6 also lacks the coding, and is always
+//! static. Version 5 also lacks the table encoding and always stores code
+//! lengths, and version 4 has no escape either. Older versions hold exactly
+//! one block, whose bits run to the end of the file, so the block has no size:
+//!
+//! - version 3 has the same symbol kinds, except `u16`, and block layout
+//!   otherwise,
+//! - version 2 omits the symbol kind and only stores characters,
+//! - version 1 stores characters with a `u64` frequency in place of each code
+//!   length, and the bits are encoded with the codes of the tree built from
+//!   them.
+//!
+//! Older versions stay readable when new ones are added.
+
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::{Container, HuffmanError, Result};
+
+pub const MAGIC: [u8; 4] = *b"HUFF";
+
+/// The latest format version.
+pub const VERSION: u8 = 11;
+
+/// The oldest format version that [`Header::read`] still reads.
+pub const OLDEST_VERSION: u8 = 1;
+
+/// The start of every file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub kind: SymbolKind,
+    /// How the blocks are coded, always [`Coding::Static`] before version 7.
+    pub coding: Coding,
+    /// What was compressed, if recorded, which is possible since version 8.
+    pub source: Option<Source>,
+}
+
+/// The file a stream was compressed from, to tell later whether it changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Source {
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// When the file was last modified, in nanoseconds since the Unix epoch.
+    pub modified: u64,
+    /// The [`Source::checksum`] of the contents.
+    pub checksum: u64,
+    /// A fingerprint of the settings it was compressed with, however the
+    /// writer of the header chooses to make one, recorded since version 11
+    /// and 0 before.
+    pub settings: u64,
+}
+
+/// How the blocks of a file are coded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coding {
+    /// With a code table per block, described by a [`BlockHeader`].
+    Static,
+    /// With a code tree updated after every symbol, so no table is stored;
+    /// each block is described by an [`AdaptiveBlockHeader`].
+    Adaptive,
+}
+
+/// What the encoded symbols are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Chars,
+    Bytes,
+    U16,
+}
+
+/// The start of each block of encoded symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// The number of symbols in the block.
+    pub length: u64,
+    pub table: Table,
+    /// How the table is stored, always [`TableEncoding::CodeLengths`] before
+    /// version 6.
+    pub encoding: TableEncoding,
+    /// The code for symbols not in the table, if any, stored since version 5.
+    pub escape: Option<Escape>,
+    /// The size of the encoded bits in bytes, or `None` if they run to the end
+    /// of the input, as in files before version 4.
+    pub encoded_size: Option<u64>,
+}
+
+/// The start of each block of an adaptively coded file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveBlockHeader {
+    /// The number of symbols in the block.
+    pub length: u64,
+    /// The size of the encoded bits in bytes.
+    pub encoded_size: u64,
+}
+
+/// How the code lengths of a block are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableEncoding {
+    /// The code length of each symbol.
+    CodeLengths,
+    /// The shape of the code tree, which takes about two bits per symbol
+    /// rather than a byte.
+    Topology,
+    /// Nothing but the id of a [`Table::Shared`], since version 10.
+    Shared,
+}
+
+/// The code that precedes each symbol missing from a block's table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Escape {
+    /// The length of the escape's canonical code.
+    pub code_length: u8,
+    /// The number of bits that follow the escape, between 1 and 32.
+    pub literal_width: u8,
+}
+
+/// What the decoder needs to reconstruct the codes, keyed by the `u32` value
+/// of each symbol (see [`Symbol`](crate::Symbol)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Table {
+    /// The frequency of each symbol, stored by version 1.
+    Frequencies(HashMap<u32, usize>),
+    /// The canonical code length of each symbol, stored since version 2.
+    CodeLengths(HashMap<u32, u8>),
+    /// The [`SharedTable::id`](crate::SharedTable::id) of the table the block
+    /// was coded with, which the decoder must be given, stored since version
+    /// 10. The table's escape is not in the header.
+    Shared(u32),
+}
+
+impl Header {
+    /// A statically coded header of the latest version.
+    pub fn new(kind: SymbolKind) -> Header {
+        Header { version: VERSION, kind, coding: Coding::Static, source: None }
+    }
+
+    /// Writes the magic bytes, version, symbol kind, coding and source.
+    ///
+    /// Fails with [`HuffmanError::InvalidInput`] unless this is the latest
+    /// version.
+    pub fn write<W: Write>(&self, output: &mut W) -> Result<()> {
+        if self.version != VERSION {
+            return Err(HuffmanError::InvalidInput("Only the latest version can be written".to_string()));
+        }
+        if self.coding == Coding::Adaptive && self.kind != SymbolKind::Bytes {
+            return Err(HuffmanError::InvalidInput("Only bytes can be coded adaptively".to_string()));
+        }
+
+        output.write_all(&MAGIC)?;
+        output.write_all(&[self.version, kind_to_u8(self.kind), coding_to_u8(self.coding)])?;
+        match self.source {
+            Some(source) => {
+                output.write_all(&[1])?;
+                for value in [source.size, source.modified, source.checksum, source.settings] {
+                    output.write_all(&value.to_le_bytes())?;
+                }
+            }
+            None => output.write_all(&[0])?,
+        }
+
+        Ok(())
+    }
+
+    /// Reads the magic bytes, version and, if the version stores them, the
+    /// symbol kind, coding and source.
+    ///
+    /// Fails with [`HuffmanError::BadMagic`] if this is not compressed data,
+    /// with [`HuffmanError::UnsupportedVersion`] if it is of an unknown
+    /// version, with [`HuffmanError::BadHeader`] if the header is malformed and
+    /// with [`HuffmanError::Truncated`] if it is cut short.
+    pub fn read<R: Read>(input: &mut R) -> Result<Header> {
+        if take::<4>(input)? != MAGIC {
+            return Err(HuffmanError::BadMagic(Container::Compressed));
+        }
+
+        let version = take::<1>(input)?[0];
+        let kind = match version {
+            OLDEST_VERSION..=2 => SymbolKind::Chars,
+            3..=VERSION => match take::<1>(input)?[0] {
+                0 => SymbolKind::Chars,
+                1 => SymbolKind::Bytes,
+                2 if version >= 4 => SymbolKind::U16,
+                kind => return Err(invalid_data(&format!("Unknown symbol kind {}", kind))),
+            },
+            version => return Err(HuffmanError::UnsupportedVersion { container: Container::Compressed, version }),
+        };
+
+        let coding = if version >= 7 {
+            match take::<1>(input)?[0] {
+                0 => Coding::Static,
+                1 if kind == SymbolKind::Bytes => Coding::Adaptive,
+                1 => return Err(invalid_data("Only bytes can be coded adaptively")),
+                coding => return Err(invalid_data(&format!("Unknown coding {}", coding))),
+            }
+        } else {
+            Coding::Static
+        };
+
+        let source = if version >= 8 {
+            match take::<1>(input)?[0] {
+                0 => None,
+                1 => Some(Source {
+                    size: u64::from_le_bytes(take(input)?),
+                    modified: u64::from_le_bytes(take(input)?),
+                    checksum: u64::from_le_bytes(take(input)?),
+                    settings: if version >= 11 { u64::from_le_bytes(take(input)?) } else { 0 },
+                }),
+                flag => return Err(invalid_data(&format!("Unknown source flag {}", flag))),
+            }
+        } else {
+            None
+        };
+
+        Ok(Header { version, kind, coding, source })
+    }
+
+    /// The number of bytes [`Header::write`] writes for this header.
+    pub fn size(&self) -> u64 {
+        let source_size = match self.source {
+            Some(_) if self.version >= 11 => 4 * 8,
+            Some(_) => 3 * 8,
+            None => 0,
+        };
+
+        MAGIC.len() as u64 + 3 + 1 + source_size
+    }
+
+    /// The number of bytes after the last block: the end marker, unless the
+    /// file holds one block, and the checksum, if it has one.
+    pub fn trailer_size(&self) -> u64 {
+        let end_size = if self.is_single_block() { 0 } else { 8 };
+        let checksum_size = if self.has_checksum() { 4 } else { 0 };
+
+        end_size + checksum_size
+    }
+
+    /// Whether the file holds one block without an end marker.
+    pub fn is_single_block(&self) -> bool {
+        self.version < 4
+    }
+
+    /// Whether the end of the stream is followed by a CRC-32, as since
+    /// version 9.
+    pub fn has_checksum(&self) -> bool {
+        self.version >= 9
+    }
+}
+
+impl Source {
+    /// The 64-bit FNV-1a hash of `contents`.
+    pub fn checksum(contents: &[u8]) -> u64 {
+        contents.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+    }
+}
+
+/// Writes the CRC-32 that follows the end of the stream.
+pub fn write_checksum<W: Write>(output: &mut W, checksum: u32) -> Result<()> {
+    output.write_all(&checksum.to_le_bytes())?;
+
+    Ok(())
+}
+
+/// Reads the CRC-32 that follows the end of the stream, failing with
+/// [`HuffmanError::Truncated`] if it is missing.
+pub fn read_checksum<R: Read>(input: &mut R) -> Result<u32> {
+    Ok(u32::from_le_bytes(take(input)?))
+}
+
+/// `compressed` with its header replaced by `header`, such as to record its
+/// [`Source`].
+///
+/// Fails like [`Header::read`], and with [`HuffmanError::InvalidInput`] unless
+/// both headers are of the latest version with the same symbol kind and
+/// coding.
+pub fn with_header(compressed: &[u8], header: &Header) -> Result<Vec<u8>> {
+    let mut rest = compressed;
+    let old = Header::read(&mut rest)?;
+    if old.version != VERSION || (old.kind, old.coding) != (header.kind, header.coding) {
+        return Err(HuffmanError::InvalidInput("Headers do not match".to_string()));
+    }
+
+    let mut output = Vec::with_capacity(compressed.len() + 25);
+    header.write(&mut output)?;
+    output.extend_from_slice(rest);
+
+    Ok(output)
+}
+
+impl BlockHeader {
+    /// Writes the block header, up to the encoded bits, in the latest version.
+    ///
+    /// Fails with [`HuffmanError::InvalidInput`] if the header has no encoded
+    /// size, no symbols, an escape width outside 1 to 32, code lengths that
+    /// do not fill a tree when stored as topology, a shared table not stored
+    /// as one or with an escape of its own, or stores frequencies, which the
+    /// latest version cannot.
+    pub fn write<W: Write>(&self, kind: SymbolKind, output: &mut W) -> Result<()> {
+        let Some(encoded_size) = self.encoded_size else {
+            return Err(HuffmanError::InvalidInput("Only blocks with an encoded size can be written".to_string()));
+        };
+        if self.length == 0 {
+            return Err(HuffmanError::InvalidInput("Blocks cannot be empty".to_string()));
+        }
+        if self.escape.is_some_and(|escape| escape.code_length == 0 || !(1..=32).contains(&escape.literal_width)) {
+            return Err(HuffmanError::InvalidInput("Invalid escape".to_string()));
+        }
+
+        output.write_all(&self.length.to_le_bytes())?;
+        output.write_all(&[encoding_to_u8(self.encoding)])?;
+        match (&self.table, self.encoding) {
+            (Table::CodeLengths(lengths), TableEncoding::CodeLengths) => {
+                write_symbols(output, kind, lengths, |length| [*length])?;
+                write_escape(output, self.escape)?;
+            }
+            (Table::CodeLengths(lengths), TableEncoding::Topology) => {
+                let (symbols, shape) = to_topology(lengths, self.escape)?;
+                output.write_all(&(symbols.len() as u32).to_le_bytes())?;
+                for symbol in symbols {
+                    write_symbol(output, kind, symbol)?;
+                }
+                write_escape(output, self.escape)?;
+                output.write_all(&shape)?;
+            }
+            (Table::Shared(id), TableEncoding::Shared) if self.escape.is_none() => output.write_all(&id.to_le_bytes())?,
+            _ => return Err(HuffmanError::InvalidInput("Only code lengths or a shared table can be written".to_string())),
+        }
+        output.write_all(&encoded_size.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// The number of bytes [`BlockHeader::write`] writes for this header, if
+    /// it can be written.
+    pub fn size(&self, kind: SymbolKind) -> u64 {
+        let count = match &self.table {
+            Table::Frequencies(frequencies) => frequencies.len(),
+            Table::CodeLengths(lengths) => lengths.len(),
+            Table::Shared(_) => return 8 + 1 + 4 + 8,
+        } as u64;
+        let symbol_size = match kind {
+            SymbolKind::Chars => 4,
+            SymbolKind::Bytes => 1,
+            SymbolKind::U16 => 2,
+        };
+
+        let table_size = match self.encoding {
+            TableEncoding::CodeLengths | TableEncoding::Shared => count * (symbol_size + 1),
+            TableEncoding::Topology => {
+                let leaves = count + self.escape.is_some() as u64;
+                count * symbol_size + (2 * leaves).saturating_sub(1).div_ceil(8)
+            }
+        };
+        let escape_size = if self.escape.is_some() { 2 } else { 1 };
+
+        8 + 1 + 4 + table_size + escape_size + 8
+    }
+
+    /// Writes the marker that ends the stream in place of a block.
+    pub fn write_end<W: Write>(output: &mut W) -> Result<()> {
+        output.write_all(&0u64.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// Reads a block header of the format described by `header`, or `None` at
+    /// the end of the stream.
+    ///
+    /// Fails like [`Header::read`].
+    pub fn read<R: Read>(header: &Header, input: &mut R) -> Result<Option<BlockHeader>> {
+        let length = u64::from_le_bytes(take(input)?);
+        if length == 0 && !header.is_single_block() {
+            return Ok(None);
+        }
+
+        let encoding = if header.version >= 6 {
+            match take::<1>(input)?[0] {
+                0 => TableEncoding::CodeLengths,
+                1 => TableEncoding::Topology,
+                2 if header.version >= 10 => TableEncoding::Shared,
+                encoding => return Err(invalid_data(&format!("Unknown table encoding {}", encoding))),
+            }
+        } else {
+            TableEncoding::CodeLengths
+        };
+        if encoding == TableEncoding::Shared {
+            let id = u32::from_le_bytes(take(input)?);
+            let encoded_size = Some(u64::from_le_bytes(take(input)?));
+            return Ok(Some(BlockHeader { length, table: Table::Shared(id), encoding, escape: None, encoded_size }));
+        }
+
+        let count = u32::from_le_bytes(take(input)?);
+        let (table, escape) = if header.version == 1 {
+            (read_frequencies(input, count, length)?, None)
+        } else {
+            let (lengths, escape) = match encoding {
+                TableEncoding::Topology => read_topology(input, header.kind, count)?,
+                _ => {
+                    let lengths = read_code_lengths(input, header.kind, count)?;
+                    (lengths, if header.version >= 5 { read_escape(input)? } else { None })
+                }
+            };
+
+            if (length == 0) != (lengths.is_empty() && escape.is_none()) {
+                return Err(invalid_data("Code lengths do not match the length"));
+            }
+            (Table::CodeLengths(lengths), escape)
+        };
+
+        let encoded_size = if header.is_single_block() {
+            None
+        } else {
+            Some(u64::from_le_bytes(take(input)?))
+        };
+
+        Ok(Some(BlockHeader { length, table, encoding, escape, encoded_size }))
+    }
+}
+
+impl AdaptiveBlockHeader {
+    /// Writes the block header, up to the encoded bits.
+    ///
+    /// Fails with [`HuffmanError::InvalidInput`] if the block has no symbols.
+    pub fn write<W: Write>(&self, output: &mut W) -> Result<()> {
+        if self.length == 0 {
+            return Err(HuffmanError::InvalidInput("Blocks cannot be empty".to_string()));
+        }
+
+        output.write_all(&self.length.to_le_bytes())?;
+        output.write_all(&self.encoded_size.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// Writes the marker that ends the stream in place of a block.
+    pub fn write_end<W: Write>(output: &mut W) -> Result<()> {
+        BlockHeader::write_end(output)
+    }
+
+    /// Reads a block header, or `None` at the end of the stream.
+    ///
+    /// Fails with [`HuffmanError::Truncated`] if it is cut short.
+    pub fn read<R: Read>(input: &mut R) -> Result<Option<AdaptiveBlockHeader>> {
+        let length = u64::from_le_bytes(take(input)?);
+        if length == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(AdaptiveBlockHeader { length, encoded_size: u64::from_le_bytes(take(input)?) }))
+    }
+}
+
+fn read_frequencies<R: Read>(input: &mut R, count: u32, length: u64) -> Result<Table> {
+    let mut frequencies = HashMap::new();
+    let mut total: u64 = 0;
+    for _ in 0..count {
+        let symbol = read_symbol(input, SymbolKind::Chars)?;
+        let frequency = u64::from_le_bytes(take(input)?);
+
+        total = total.checked_add(frequency).ok_or_else(|| invalid_data("Frequency too large"))?;
+        let frequency = usize::try_from(frequency).map_err(|_| invalid_data("Frequency too large"))?;
+
+        if frequency == 0 || frequencies.insert(symbol, frequency).is_some() {
+            return Err(invalid_data("Invalid frequency table"));
+        }
+    }
+
+    if total != length {
+        return Err(invalid_data("Frequencies do not match the length"));
+    }
+
+    Ok(Table::Frequencies(frequencies))
+}
+
+fn read_code_lengths<R: Read>(input: &mut R, kind: SymbolKind, count: u32) -> Result<HashMap<u32, u8>> {
+    let mut lengths = HashMap::new();
+    for _ in 0..count {
+        let symbol = read_symbol(input, kind)?;
+        let code_length = take::<1>(input)?[0];
+
+        if code_length == 0 || lengths.insert(symbol, code_length).is_some() {
+            return Err(invalid_data("Invalid code length table"));
+        }
+    }
+
+    Ok(lengths)
+}
+
+fn read_escape<R: Read>(input: &mut R) -> Result<Option<Escape>> {
+    let code_length = take::<1>(input)?[0];
+    if code_length == 0 {
+        return Ok(None);
+    }
+
+    let literal_width = take::<1>(input)?[0];
+    if !(1..=32).contains(&literal_width) {
+        return Err(invalid_data("Invalid escape width"));
+    }
+
+    Ok(Some(Escape { code_length, literal_width }))
+}
+
+fn read_topology<R: Read>(
+    input: &mut R,
+    kind: SymbolKind,
+    count: u32,
+) -> Result<(HashMap<u32, u8>, Option<Escape>)> {
+    let symbols = (0..count).map(|_| read_symbol(input, kind)).collect::<Result<Vec<u32>>>()?;
+    let escape = read_escape(input)?;
+
+    let leaves = symbols.len() + escape.is_some() as usize;
+    if leaves == 0 {
+        return Ok((HashMap::new(), None));
+    }
+
+    let mut shape = vec![0; (2 * leaves - 1).div_ceil(8)];
+    fill(input, &mut shape)?;
+
+    let mut depths = Vec::with_capacity(leaves);
+    read_shape(&mut BitReader::new(&shape), 0, leaves, &mut depths)?;
+    if depths.len() != leaves {
+        return Err(invalid_data("Tree shape does not match the table"));
+    }
+    if depths == [0] {
+        depths[0] = 1;
+    }
+
+    if let Some(escape) = escape {
+        let position = depths.iter().rposition(|&depth| depth == escape.code_length)
+            .ok_or_else(|| invalid_data("Tree shape has no leaf for the escape"))?;
+        depths.remove(position);
+    }
+
+    let mut lengths = HashMap::new();
+    for (symbol, depth) in symbols.into_iter().zip(depths) {
+        if lengths.insert(symbol, depth).is_some() {
+            return Err(invalid_data("Invalid code length table"));
+        }
+    }
+
+    Ok((lengths, escape))
+}
+
+/// Reads the subtree at `depth` of a tree of `leaves` leaves, pushing the
+/// depth of each leaf.
+fn read_shape(reader: &mut BitReader, depth: u8, leaves: usize, depths: &mut Vec<u8>) -> Result<()> {
+    match reader.read_bit() {
+        None => Err(invalid_data("Tree shape does not match the table")),
+        Some(false) if depths.len() == leaves => Err(invalid_data("Tree shape does not match the table")),
+        Some(false) => {
+            depths.push(depth);
+            Ok(())
+        }
+        Some(true) => {
+            let depth = depth.checked_add(1).ok_or_else(|| invalid_data("Tree shape too deep"))?;
+            read_shape(reader, depth, leaves, depths)?;
+            read_shape(reader, depth, leaves, depths)
+        }
+    }
+}
+
+/// The symbols of a table in the order of their codes, and the shape of the
+/// code tree.
+fn to_topology(lengths: &HashMap<u32, u8>, escape: Option<Escape>) -> Result<(Vec<u32>, Vec<u8>)> {
+    // The escape comes after the symbols with codes of the same length.
+    let mut leaves: Vec<(u8, bool, u32)> = lengths.iter()
+        .map(|(&symbol, &length)| (length, false, symbol))
+        .chain(escape.map(|escape| (escape.code_length, true, 0)))
+        .collect();
+    leaves.sort_unstable();
+
+    let mut writer = BitWriter::new();
+    let mut depths = leaves.iter().map(|&(length, _, _)| length).peekable();
+    if leaves.len() == 1 && leaves[0].0 == 1 {
+        writer.write_bit(false);
+        depths.next();
+    } else {
+        write_shape(&mut writer, 0, &mut depths)?;
+    }
+    if depths.next().is_some() {
+        return Err(HuffmanError::InvalidInput("Code lengths do not fill a tree".to_string()));
+    }
+
+    let symbols = leaves.into_iter().filter(|&(_, is_escape, _)| !is_escape).map(|(_, _, symbol)| symbol).collect();
+    Ok((symbols, writer.finish()))
+}
+
+/// Writes the subtree at `depth` holding the next leaves of `depths`, which
+/// are sorted.
+fn write_shape(
+    writer: &mut BitWriter,
+    depth: u8,
+    depths: &mut std::iter::Peekable<impl Iterator<Item = u8>>,
+) -> Result<()> {
+    match depths.peek() {
+        Some(&leaf_depth) if leaf_depth == depth => {
+            depths.next();
+            writer.write_bit(false);
+            Ok(())
+        }
+        Some(&leaf_depth) if leaf_depth > depth => {
+            writer.write_bit(true);
+            write_shape(writer, depth + 1, depths)?;
+            write_shape(writer, depth + 1, depths)
+        }
+        _ => Err(HuffmanError::InvalidInput("Code lengths do not fill a tree".to_string())),
+    }
+}
+
+fn read_symbol<R: Read>(input: &mut R, kind: SymbolKind) -> Result<u32> {
+    match kind {
+        SymbolKind::Chars => {
+            let value = u32::from_le_bytes(take(input)?);
+            char::from_u32(value).ok_or_else(|| invalid_data("Invalid character in header"))?;
+            Ok(value)
+        }
+        SymbolKind::Bytes => Ok(take::<1>(input)?[0] as u32),
+        SymbolKind::U16 => Ok(u16::from_le_bytes(take(input)?) as u32),
+    }
+}
+
+fn write_symbols<W: Write, T, const N: usize>(
+    output: &mut W,
+    kind: SymbolKind,
+    symbols: &HashMap<u32, T>,
+    to_bytes: impl Fn(&T) -> [u8; N],
+) -> Result<()> {
+    let mut symbols: Vec<(&u32, &T)> = symbols.iter().collect();
+    symbols.sort_unstable_by_key(|&(&symbol, _)| symbol);
+
+    output.write_all(&(symbols.len() as u32).to_le_bytes())?;
+    for (&symbol, value) in symbols {
+        write_symbol(output, kind, symbol)?;
+        output.write_all(&to_bytes(value))?;
+    }
+
+    Ok(())
+}
+
+fn write_symbol<W: Write>(output: &mut W, kind: SymbolKind, symbol: u32) -> std::io::Result<()> {
+    match kind {
+        SymbolKind::Chars => output.write_all(&symbol.to_le_bytes()),
+        SymbolKind::Bytes => output.write_all(&[symbol as u8]),
+        SymbolKind::U16 => output.write_all(&(symbol as u16).to_le_bytes()),
+    }
+}
+
+fn write_escape<W: Write>(output: &mut W, escape: Option<Escape>) -> std::io::Result<()> {
+    match escape {
+        Some(escape) => output.write_all(&[escape.code_length, escape.literal_width]),
+        None => output.write_all(&[0]),
+    }
+}
+
+pub(crate) fn kind_to_u8(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Chars => 0,
+        SymbolKind::Bytes => 1,
+        SymbolKind::U16 => 2,
+    }
+}
+
+fn coding_to_u8(coding: Coding) -> u8 {
+    match coding {
+        Coding::Static => 0,
+        Coding::Adaptive => 1,
+    }
+}
+
+fn encoding_to_u8(encoding: TableEncoding) -> u8 {
+    match encoding {
+        TableEncoding::CodeLengths => 0,
+        TableEncoding::Topology => 1,
+        TableEncoding::Shared => 2,
+    }
+}
+
+pub(crate) fn take<const N: usize>(input: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    fill(input, &mut bytes)?;
+
+    Ok(bytes)
+}
+
+pub(crate) fn fill(input: &mut impl Read, bytes: &mut [u8]) -> Result<()> {
+    input.read_exact(bytes).map_err(|error| match error.kind() {
+        ErrorKind::UnexpectedEof => HuffmanError::Truncated,
+        _ => HuffmanError::Io(error),
+    })
+}
+
+fn invalid_data(message: &str) -> HuffmanError {
+    HuffmanError::BadHeader(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_lengths_block(lengths: HashMap<u32, u8>, escape: Option<Escape>) -> BlockHeader {
+        BlockHeader {
+            length: 6,
+            table: Table::CodeLengths(lengths),
+            encoding: TableEncoding::CodeLengths,
+            escape,
+            encoded_size: Some(2),
+        }
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        for kind in [SymbolKind::Chars, SymbolKind::Bytes, SymbolKind::U16] {
+            let mut bytes = Vec::new();
+            Header::new(kind).write(&mut bytes).unwrap();
+
+            assert_eq!(Header::read(&mut bytes.as_slice()).unwrap(), Header::new(kind));
+            assert_eq!(bytes.len() as u64, Header::new(kind).size());
+            assert_eq!(Header::new(kind).trailer_size(), 8 + 4);
+        }
+    }
+
+    #[test]
+    fn test_block_header_round_trip() {
+        let escape = Escape { code_length: 2, literal_width: 17 };
+        let blocks = [
+            (SymbolKind::Chars, code_lengths_block(HashMap::from([('a' as u32, 2), ('b' as u32, 2), ('霍' as u32, 1)]), None)),
+            (SymbolKind::Chars, code_lengths_block(HashMap::from([('a' as u32, 2), ('霍' as u32, 1)]), Some(escape))),
+            (SymbolKind::Chars, code_lengths_block(HashMap::from([('a' as u32, 1)]), None)),
+            (SymbolKind::Bytes, code_lengths_block(HashMap::from([(0x00, 2), (0x80, 2), (0xFF, 1)]), None)),
+            (SymbolKind::U16, code_lengths_block(HashMap::from([(0x0000, 2), (0x1234, 2), (0xFFFF, 1)]), None)),
+        ];
+
+        for (kind, mut block) in blocks {
+            for encoding in [TableEncoding::CodeLengths, TableEncoding::Topology] {
+                block.encoding = encoding;
+
+                let mut bytes = Vec::new();
+                block.write(kind, &mut bytes).unwrap();
+                assert_eq!(bytes.len() as u64, block.size(kind));
+                BlockHeader::write_end(&mut bytes).unwrap();
+
+                let mut rest = bytes.as_slice();
+                let header = Header::new(kind);
+                assert_eq!(BlockHeader::read(&header, &mut rest).unwrap(), Some(block.clone()));
+                assert_eq!(BlockHeader::read(&header, &mut rest).unwrap(), None);
+                assert!(rest.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut bytes = Vec::new();
+        Header::new(SymbolKind::Bytes).write(&mut bytes).unwrap();
+        let block = BlockHeader {
+            length: 2,
+            table: Table::CodeLengths(HashMap::from([(0xFF, 1)])),
+            encoding: TableEncoding::CodeLengths,
+            escape: Some(Escape { code_length: 1, literal_width: 8 }),
+            encoded_size: Some(1),
+        };
+        block.write(SymbolKind::Bytes, &mut bytes).unwrap();
+
+        let mut expected = b"HUFF\x0b\x01\x00\x00".to_vec();
+        expected.extend_from_slice(&2u64.to_le_bytes());
+        expected.push(0);
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.extend_from_slice(&[0xFF, 1]);
+        expected.extend_from_slice(&[1, 8]);
+        expected.extend_from_slice(&1u64.to_le_bytes());
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_shared_layout() {
+        let block = BlockHeader {
+            length: 3,
+            table: Table::Shared(0xDEAD_BEEF),
+            encoding: TableEncoding::Shared,
+            escape: None,
+            encoded_size: Some(1),
+        };
+        let mut bytes = Vec::new();
+        block.write(SymbolKind::U16, &mut bytes).unwrap();
+        assert_eq!(bytes.len() as u64, block.size(SymbolKind::U16));
+
+        let mut expected = 3u64.to_le_bytes().to_vec();
+        expected.push(2);
+        expected.extend_from_slice(&0xDEAD_BEEFu32.to_le_bytes());
+        expected.extend_from_slice(&1u64.to_le_bytes());
+        assert_eq!(bytes, expected);
+        assert_eq!(BlockHeader::read(&Header::new(SymbolKind::U16), &mut bytes.as_slice()).unwrap(), Some(block.clone()));
+
+        // A shared table has its own escape, and is only stored as a reference.
+        let escaped = BlockHeader { escape: Some(Escape { code_length: 1, literal_width: 16 }), ..block.clone() };
+        let stored = BlockHeader { encoding: TableEncoding::CodeLengths, ..block };
+        for block in [escaped, stored] {
+            assert!(matches!(block.write(SymbolKind::U16, &mut Vec::new()).unwrap_err(), HuffmanError::InvalidInput(_)));
+        }
+    }
+
+    #[test]
+    fn test_topology_layout() {
+        let block = BlockHeader {
+            length: 4,
+            table: Table::CodeLengths(HashMap::from([(0x00, 2), (0x80, 3), (0xFF, 1)])),
+            encoding: TableEncoding::Topology,
+            escape: Some(Escape { code_length: 3, literal_width: 8 }),
+            encoded_size: Some(1),
+        };
+        let mut bytes = Vec::new();
+        block.write(SymbolKind::Bytes, &mut bytes).unwrap();
+
+        let mut expected = 4u64.to_le_bytes().to_vec();
+        expected.push(1);
+        expected.extend_from_slice(&3u32.to_le_bytes());
+        expected.extend_from_slice(&[0xFF, 0x00, 0x80]);
+        expected.extend_from_slice(&[3, 8]);
+        // 0xFF, 0x00, 0x80 and the escape at depths 1, 2, 3 and 3.
+        expected.push(0b1010_1000);
+        expected.extend_from_slice(&1u64.to_le_bytes());
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_read_invalid_topology() {
+        let header = Header::new(SymbolKind::Bytes);
+        let block = |shape: u8| {
+            let mut bytes = 2u64.to_le_bytes().to_vec();
+            bytes.push(1);
+            bytes.extend_from_slice(&2u32.to_le_bytes());
+            bytes.extend_from_slice(&[0x00, 0x01, 0, shape]);
+            bytes.extend_from_slice(&1u64.to_le_bytes());
+            bytes
+        };
+
+        let read = BlockHeader::read(&header, &mut block(0b1000_0000).as_slice()).unwrap().unwrap();
+        assert_eq!(read.table, Table::CodeLengths(HashMap::from([(0x00, 1), (0x01, 1)])));
+
+        // Shapes with one leaf and with more than two.
+        for shape in [0b0000_0000, 0b1100_0000, 0b1110_0000] {
+            let error = BlockHeader::read(&header, &mut block(shape).as_slice()).unwrap_err();
+            assert!(matches!(error, HuffmanError::BadHeader(_)));
+        }
+    }
+
+    #[test]
+    fn test_write_unsupported() {
+        let header = Header { version: 1, kind: SymbolKind::Chars, coding: Coding::Static, source: None };
+        assert!(matches!(header.write(&mut Vec::new()).unwrap_err(), HuffmanError::InvalidInput(_)));
+
+        let mut block = BlockHeader {
+            length: 2,
+            table: Table::Frequencies(HashMap::from([(0x61, 2)])),
+            encoding: TableEncoding::CodeLengths,
+            escape: None,
+            encoded_size: Some(1),
+        };
+        assert!(matches!(block.write(SymbolKind::Chars, &mut Vec::new()).unwrap_err(), HuffmanError::InvalidInput(_)));
+
+        block.table = Table::CodeLengths(HashMap::new());
+        block.escape = Some(Escape { code_length: 1, literal_width: 33 });
+        assert!(matches!(block.write(SymbolKind::Chars, &mut Vec::new()).unwrap_err(), HuffmanError::InvalidInput(_)));
+
+        // Code lengths that leave a gap have no tree to store.
+        block.table = Table::CodeLengths(HashMap::from([(0x61, 1), (0x62, 2)]));
+        block.escape = None;
+        block.encoding = TableEncoding::Topology;
+        assert!(matches!(block.write(SymbolKind::Chars, &mut Vec::new()).unwrap_err(), HuffmanError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_read_v3() {
+        let mut bytes = b"HUFF\x03\x01".to_vec();
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&[0xFF, 1]);
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        assert_eq!(header, Header { version: 3, kind: SymbolKind::Bytes, coding: Coding::Static, source: None });
+        assert!(header.is_single_block());
+
+        let block = BlockHeader::read(&header, &mut rest).unwrap().unwrap();
+        assert_eq!(block, BlockHeader {
+            length: 2,
+            table: Table::CodeLengths(HashMap::from([(0xFF, 1)])),
+            encoding: TableEncoding::CodeLengths,
+            escape: None,
+            encoded_size: None,
+        });
+    }
+
+    #[test]
+    fn test_read_v4() {
+        let mut bytes = b"HUFF\x04\x01".to_vec();
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&[0xFF, 1]);
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        let block = BlockHeader::read(&header, &mut rest).unwrap().unwrap();
+        assert_eq!(block, BlockHeader {
+            length: 2,
+            table: Table::CodeLengths(HashMap::from([(0xFF, 1)])),
+            encoding: TableEncoding::CodeLengths,
+            escape: None,
+            encoded_size: Some(1),
+        });
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_adaptive_round_trip() {
+        let header = Header { coding: Coding::Adaptive, ..Header::new(SymbolKind::Bytes) };
+        let block = AdaptiveBlockHeader { length: 10, encoded_size: 4 };
+
+        let mut bytes = Vec::new();
+        header.write(&mut bytes).unwrap();
+        block.write(&mut bytes).unwrap();
+        AdaptiveBlockHeader::write_end(&mut bytes).unwrap();
+        assert_eq!(&bytes[..8], b"HUFF\x0b\x01\x01\x00");
+
+        let mut rest = bytes.as_slice();
+        assert_eq!(Header::read(&mut rest).unwrap(), header);
+        assert_eq!(AdaptiveBlockHeader::read(&mut rest).unwrap(), Some(block));
+        assert_eq!(AdaptiveBlockHeader::read(&mut rest).unwrap(), None);
+        assert!(rest.is_empty());
+
+        // Only bytes can be coded adaptively.
+        let error = Header::read(&mut b"HUFF\x0b\x00\x01\x00".as_slice()).unwrap_err();
+        assert!(matches!(error, HuffmanError::BadHeader(_)));
+        let header = Header { kind: SymbolKind::Chars, ..header };
+        assert!(matches!(header.write(&mut Vec::new()).unwrap_err(), HuffmanError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_source() {
+        let checksum = Source::checksum(b"abbcccdddd");
+        let source = Source { size: 10, modified: 1_700_000_000_000_000_000, checksum, settings: 7 };
+        let header = Header { source: Some(source), ..Header::new(SymbolKind::Chars) };
+
+        let mut bytes = Vec::new();
+        header.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + 32);
+        assert_eq!(header.size(), 8 + 32);
+        assert_eq!(Header::read(&mut bytes.as_slice()).unwrap(), header);
+
+        // Without the settings before version 11.
+        bytes[MAGIC.len()] = 10;
+        bytes.truncate(8 + 24);
+        let old = Header::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(old.source, Some(Source { settings: 0, ..source }));
+        assert_eq!(old.size(), 8 + 24);
+
+        let compressed = crate::compress("abbcccdddd");
+        let recorded = with_header(&compressed, &header).unwrap();
+        assert_eq!(Header::read(&mut recorded.as_slice()).unwrap().source, Some(source));
+        assert_eq!(crate::decompress(&recorded).unwrap(), "abbcccdddd");
+
+        let error = with_header(&compressed, &Header::new(SymbolKind::Bytes)).unwrap_err();
+        assert!(matches!(error, HuffmanError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_read_v9() {
+        let mut bytes = b"HUFF\x09\x01\x00\x00".to_vec();
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.push(2);
+
+        // Shared tables came later.
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        assert!(header.has_checksum());
+        assert!(matches!(BlockHeader::read(&header, &mut rest).unwrap_err(), HuffmanError::BadHeader(_)));
+    }
+
+    #[test]
+    fn test_read_v8() {
+        let mut bytes = b"HUFF\x08\x01\x00\x00".to_vec();
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        assert!(!header.has_checksum());
+        assert_eq!(BlockHeader::read(&header, &mut rest).unwrap(), None);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_read_v7() {
+        let mut bytes = b"HUFF\x07\x01\x00".to_vec();
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        assert_eq!(header, Header { version: 7, kind: SymbolKind::Bytes, coding: Coding::Static, source: None });
+        assert_eq!(BlockHeader::read(&header, &mut rest).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_v6() {
+        let mut bytes = b"HUFF\x06\x01".to_vec();
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        assert_eq!(header, Header { version: 6, kind: SymbolKind::Bytes, coding: Coding::Static, source: None });
+        assert_eq!(BlockHeader::read(&header, &mut rest).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_v5() {
+        let mut bytes = b"HUFF\x05\x01".to_vec();
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&[0xFF, 1, 1, 8]);
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        let block = BlockHeader::read(&header, &mut rest).unwrap().unwrap();
+        assert_eq!(block.encoding, TableEncoding::CodeLengths);
+        assert_eq!(block.escape, Some(Escape { code_length: 1, literal_width: 8 }));
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_read_escape_only() {
+        let block = BlockHeader {
+            length: 3,
+            table: Table::CodeLengths(HashMap::new()),
+            encoding: TableEncoding::CodeLengths,
+            escape: Some(Escape { code_length: 1, literal_width: 21 }),
+            encoded_size: Some(9),
+        };
+        let mut bytes = Vec::new();
+        block.write(SymbolKind::Chars, &mut bytes).unwrap();
+
+        let header = Header::new(SymbolKind::Chars);
+        assert_eq!(BlockHeader::read(&header, &mut bytes.as_slice()).unwrap(), Some(block));
+
+        // Symbols with neither a table nor an escape.
+        let mut bytes = 1u64.to_le_bytes().to_vec();
+        bytes.push(0);
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.push(0);
+        let error = BlockHeader::read(&header, &mut bytes.as_slice()).unwrap_err();
+        assert!(matches!(error, HuffmanError::BadHeader(_)));
+    }
+
+    #[test]
+    fn test_read_v2() {
+        let mut bytes = b"HUFF\x02".to_vec();
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&('a' as u32).to_le_bytes());
+        bytes.push(1);
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        assert_eq!(header, Header { version: 2, kind: SymbolKind::Chars, coding: Coding::Static, source: None });
+
+        let block = BlockHeader::read(&header, &mut rest).unwrap().unwrap();
+        assert_eq!(block.table, Table::CodeLengths(HashMap::from([('a' as u32, 1)])));
+    }
+
+    #[test]
+    fn test_read_v1() {
+        let mut bytes = b"HUFF\x01".to_vec();
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&('a' as u32).to_le_bytes());
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        let block = BlockHeader::read(&header, &mut rest).unwrap().unwrap();
+        assert_eq!(block.table, Table::Frequencies(HashMap::from([('a' as u32, 2)])));
+
+        bytes[5] = 3;
+        let mut rest = bytes.as_slice();
+        let header = Header::read(&mut rest).unwrap();
+        let error = BlockHeader::read(&header, &mut rest).unwrap_err();
+        assert!(matches!(error, HuffmanError::BadHeader(_)));
+    }
+
+    #[test]
+    fn test_read_bad_magic() {
+        let error = Header::read(&mut b"HUFX\x01".as_slice()).unwrap_err();
+        assert!(matches!(error, HuffmanError::BadMagic(Container::Compressed)));
+    }
+
+    #[test]
+    fn test_read_unknown_version() {
+        let error = Header::read(&mut b"HUFF\xFF".as_slice()).unwrap_err();
+        assert!(matches!(error, HuffmanError::UnsupportedVersion { container: Container::Compressed, version: 0xFF }));
+    }
+
+    #[test]
+    fn test_read_unknown_symbol_kind() {
+        for mut bytes in [b"HUFF\x04\x03".as_slice(), b"HUFF\x03\x02", b"HUFF\x07\x01\x02", b"HUFF\x08\x01\x00\x02"] {
+            let error = Header::read(&mut bytes).unwrap_err();
+            assert!(matches!(error, HuffmanError::BadHeader(_)));
+        }
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        let error = Header::read(&mut b"HUF".as_slice()).unwrap_err();
+        assert!(matches!(error, HuffmanError::Truncated));
+    }
+}
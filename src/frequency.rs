@@ -0,0 +1,153 @@
+#[cfg(feature = "std")]
+use std::io::{BufRead, ErrorKind};
+
+#[cfg(feature = "std")]
+use crate::error::{HuffmanError, Result};
+use crate::symbol::Symbol;
+use crate::Map;
+
+/// Counts how often each character occurs in everything `reader` yields.
+///
+/// Fails with [`HuffmanError::NotUtf8`] if it is not text.
+#[cfg(feature = "std")]
+pub fn get_frequencies_from_reader<R: BufRead>(reader: &mut R) -> Result<Map<char, usize>> {
+    let mut frequencies: Map<char, usize> = Map::new();
+    let mut line = String::new();
+
+    loop {
+        let length = reader.read_line(&mut line).map_err(|error| match error.kind() {
+            ErrorKind::InvalidData => HuffmanError::NotUtf8,
+            _ => HuffmanError::Io(error),
+        })?;
+        if length == 0 {
+            break;
+        }
+
+        let frequencies_temp = get_frequencies(&line);
+
+        for (key, value) in frequencies_temp {
+            *frequencies.entry(key).or_insert(0) += value;
+        }
+
+        line.clear();
+    }
+
+    Ok(frequencies)
+}
+
+/// Counts how often each character occurs in `line`.
+pub fn get_frequencies(line: &str) -> Map<char, usize> {
+    count_symbols(line.chars())
+}
+
+/// Counts how often each byte occurs in everything `reader` yields.
+#[cfg(feature = "std")]
+pub fn get_byte_frequencies_from_reader<R: BufRead>(reader: &mut R) -> Result<Map<u8, usize>> {
+    let mut frequencies: Map<u8, usize> = Map::new();
+
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        for &byte in buffer {
+            *frequencies.entry(byte).or_insert(0) += 1;
+        }
+
+        let length = buffer.len();
+        reader.consume(length);
+    }
+
+    Ok(frequencies)
+}
+
+/// Counts how often each byte occurs in `bytes`.
+pub fn get_byte_frequencies(bytes: &[u8]) -> Map<u8, usize> {
+    count_symbols(bytes.iter().copied())
+}
+
+pub(crate) fn count_symbols<S: Symbol>(symbols: impl IntoIterator<Item = S>) -> Map<S, usize> {
+    let mut frequencies: Map<S, usize> = Map::new();
+    for symbol in symbols {
+        *frequencies.entry(symbol).or_insert(0) += 1;
+    }
+
+    frequencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_frequencies() {
+        let frequencies = get_frequencies("abbcccdddd");
+
+        assert_eq!(frequencies[&'a'], 1);
+        assert_eq!(frequencies[&'b'], 2);
+        assert_eq!(frequencies[&'c'], 3);
+        assert_eq!(frequencies[&'d'], 4);
+    }
+
+    #[test]
+    fn test_get_frequencies_cjk() {
+        let frequencies = get_frequencies("霍夫曼编码编码");
+
+        assert_eq!(frequencies[&'霍'], 1);
+        assert_eq!(frequencies[&'编'], 2);
+        assert_eq!(frequencies[&'码'], 2);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_frequencies_from_reader() {
+        let mut cursor = std::io::Cursor::new(b"test\nmyreallycooltest");
+        let frequencies = get_frequencies_from_reader(&mut cursor).unwrap();
+
+        assert_eq!(frequencies[&'t'], 4);
+        assert_eq!(frequencies[&'e'], 3);
+        assert_eq!(frequencies[&'s'], 2);
+        assert_eq!(frequencies[&'m'], 1);
+        assert_eq!(frequencies[&'y'], 2);
+        assert_eq!(frequencies[&'r'], 1);
+        assert_eq!(frequencies[&'a'], 1);
+        assert_eq!(frequencies[&'l'], 3);
+        assert_eq!(frequencies[&'c'], 1);
+        assert_eq!(frequencies[&'o'], 2);
+        assert_eq!(frequencies[&'\n'], 1);
+        assert_eq!(frequencies.get(&'d'), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_frequencies_from_reader_not_text() {
+        let mut cursor = std::io::Cursor::new([b'a', 0xFF, b'\n']);
+
+        assert!(matches!(get_frequencies_from_reader(&mut cursor), Err(HuffmanError::NotUtf8)));
+    }
+
+    #[test]
+    fn test_get_byte_frequencies() {
+        let frequencies = get_byte_frequencies(&[0x00, 0xFF, 0xFF, 0xC3, 0x28]);
+
+        assert_eq!(frequencies[&0x00], 1);
+        assert_eq!(frequencies[&0xFF], 2);
+        assert_eq!(frequencies[&0xC3], 1);
+        assert_eq!(frequencies[&0x28], 1);
+        assert_eq!(frequencies.get(&0x01), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_byte_frequencies_from_reader() {
+        // Invalid UTF-8, spread over several reads.
+        let bytes: Vec<u8> = [0xFF, 0xFE, b'\n'].repeat(1000);
+        let mut reader = std::io::BufReader::with_capacity(7, bytes.as_slice());
+        let frequencies = get_byte_frequencies_from_reader(&mut reader).unwrap();
+
+        assert_eq!(frequencies, get_byte_frequencies(&bytes));
+        assert_eq!(frequencies[&0xFF], 1000);
+        assert_eq!(frequencies[&b'\n'], 1000);
+    }
+}
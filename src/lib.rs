@@ -0,0 +1,135 @@
+//! Huffman coding.
+//!
+//! Count symbol frequencies, build a [`Tree`] from them and derive a
+//! [`CodeTable`] mapping each symbol to its prefix-free code, then [`encode`]
+//! the input with it. Symbols are characters, bytes for arbitrary binary data or
+//! `u16`s for pre-tokenized input (see [`Symbol`]):
+//!
+//! ```
+//! let frequencies = huffman::get_frequencies("abbcccdddd");
+//! let tree = huffman::Tree::from_frequencies(&frequencies)?;
+//! let codes = huffman::CodeTable::from_tree(&tree);
+//!
+//! assert_eq!(codes.get('d'), Some("0"));
+//! assert_eq!(huffman::encode("abbcccdddd".chars(), &codes)?.len(), 3);
+//! # Ok::<(), huffman::HuffmanError>(())
+//! ```
+//!
+//! [`compress`] and [`compress_bytes`] do all of this with canonical codes and
+//! store their lengths in front of the encoded bits (see [`format`](mod@format)),
+//! so that [`decompress`] can restore the input:
+//!
+//! ```
+//! let compressed = huffman::compress("abbcccdddd");
+//! assert_eq!(huffman::decompress(&compressed).unwrap(), "abbcccdddd");
+//! ```
+//!
+//! [`HuffmanEncoder`] and [`HuffmanDecoder`] do the same incrementally, over
+//! [`Write`](std::io::Write) and [`Read`](std::io::Read). Input that can only
+//! be read once, such as a pipe, can be compressed in a single pass with
+//! [`AdaptiveEncoder`] or [`compress_adaptive`]. A [`SharedTable`] codes many
+//! small files alike without storing a table in each. The [`archive`] module
+//! stores several compressed files in one, and [`stats`] measures how well
+//! input compresses.
+//!
+//! Everything that can fail returns a [`HuffmanError`].
+//!
+//! Trees, tables and buffers are allocated with `Vec`, `Box` and the maps,
+//! so all allocation goes through the global allocator, and a program that
+//! must not contend for the system's can give its own with
+//! `#[global_allocator]`, such as a per-thread arena. Nothing is kept between
+//! calls: everything a call allocates is freed by the time its result is.
+//!
+//! Without the default `std` feature the crate is `no_std` and needs only
+//! `alloc`, for embedded targets. What is left is the coder itself: trees, code
+//! tables, [`encode`] and [`decode`], with frequencies in a [`Map`]; the
+//! formats, streams and everything else that reads or writes need `std`.
+//!
+//! The crate has no `unsafe` code. Should a faster path ever need it, it goes
+//! behind a feature with a safe fallback, and the bit handling stays covered
+//! by running the tests under Miri with `cargo +nightly miri test --lib`,
+//! which skips the few that take too long there.
+//!
+//! Decoding malformed data fails with an error rather than panicking. The
+//! parsers and decoders are fuzzed with `cargo +nightly fuzz run decode` (or
+//! `header`, or `round_trip`) from `fuzz/`, and the same targets run over
+//! mutated streams with the tests.
+
+#![forbid(unsafe_code)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod adaptive;
+mod bits;
+mod code;
+#[cfg(feature = "std")]
+mod compress;
+#[cfg(feature = "std")]
+mod crc;
+mod decode;
+#[cfg(feature = "std")]
+mod decoder;
+mod encode;
+#[cfg(feature = "std")]
+mod encoder;
+mod error;
+#[cfg(feature = "std")]
+mod escape;
+#[cfg(all(feature = "std", test))]
+mod faulty;
+mod frequency;
+#[cfg(feature = "std")]
+mod optimize;
+#[cfg(feature = "std")]
+mod progress;
+#[cfg(feature = "std")]
+mod shared;
+mod symbol;
+#[cfg(feature = "std")]
+mod tables;
+mod tree;
+
+#[cfg(feature = "std")]
+pub mod archive;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "std")]
+pub mod stats;
+
+/// The map of frequencies and code lengths: a hash map with `std`, and
+/// without it a [`BTreeMap`](alloc::collections::BTreeMap), as `alloc` has no
+/// hash map.
+#[cfg(feature = "std")]
+pub type Map<K, V> = std::collections::HashMap<K, V>;
+#[cfg(not(feature = "std"))]
+pub type Map<K, V> = alloc::collections::BTreeMap<K, V>;
+
+#[cfg(feature = "std")]
+pub use adaptive::AdaptiveEncoder;
+pub use code::CodeTable;
+#[cfg(feature = "std")]
+pub use compress::{
+    compress, compress_adaptive, compress_bytes, compress_bytes_optimal, compress_bytes_parallel, compress_optimal,
+    compress_parallel, compress_u16, compress_u16_optimal, compress_u16_parallel, decompress, decompress_bytes,
+    decompress_u16, DEFAULT_BLOCK_SIZE,
+};
+pub use decode::{decode, decode_canonical};
+#[cfg(feature = "std")]
+pub use decoder::HuffmanDecoder;
+pub use encode::encode;
+#[cfg(feature = "std")]
+pub use encoder::HuffmanEncoder;
+pub use error::{Container, HuffmanError, Result};
+pub use frequency::{get_byte_frequencies, get_frequencies};
+#[cfg(feature = "std")]
+pub use frequency::{get_byte_frequencies_from_reader, get_frequencies_from_reader};
+#[cfg(feature = "std")]
+pub use progress::Progress;
+#[cfg(feature = "std")]
+pub use shared::SharedTable;
+pub use symbol::Symbol;
+#[cfg(feature = "std")]
+pub use tables::Tables;
+pub use tree::{HuffmanNode, Tree};
@@ -1,190 +1,1216 @@
-use std::collections::{BinaryHeap, HashMap};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::cmp::Ordering;
+#![deny(unsafe_code)]
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
+mod cache;
+mod cli;
+mod crash;
+mod messages;
+mod meter;
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+mod mmap;
+mod remove;
 
-    if args.len() < 2 {
-        panic!("No filename provided");
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::Deref;
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+use std::time::UNIX_EPOCH;
+
+use cache::Cache;
+use messages::text;
+use meter::Meter;
+use cli::{is_stdio, Command, Symbols};
+use huffman::archive::{self, Entry};
+use huffman::format::{self, BlockHeader, Header, Source, SymbolKind, Table, TableEncoding};
+use huffman::stats::{self, Report};
+use huffman::{Container, HuffmanError, SharedTable, Tables};
+
+fn main() -> ExitCode {
+    let cli::Parsed { command, json } = cli::parse(std::env::args().skip(1));
+    let command = match command {
+        Ok(command) => command,
+        Err(message) if json => {
+            eprintln!("{}", json_error("E_USAGE", &message, None, None));
+            return ExitCode::from(2);
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", text!(Error, message), text!(Usage));
+            return ExitCode::from(2);
+        }
+    };
+
+    let name = command.name();
+    let run = crash::Run { command: name, config: format!("{:?}", command), json };
+    let Some(result) = crash::guard(run, || execute(command)) else {
+        return ExitCode::from(crash::EXIT_CODE);
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) if json => {
+            let message = failure.error.to_string();
+            let hint = hint(name, &failure);
+            eprintln!("{}", json_error(error_code(&failure.error), &message, Some(&failure.path), hint.as_deref()));
+            ExitCode::FAILURE
+        }
+        Err(failure) => {
+            eprintln!("{}", text!(Error, failure));
+            if let Some(hint) = hint(name, &failure) {
+                eprintln!("{}", text!(Hint, hint));
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Does what `command` says.
+fn execute(command: Command) -> Result<(), Failure> {
+    match command {
+        Command::Compress { input, output, adaptive: true, verify, remove, progress, .. } => {
+            distinct_output(std::slice::from_ref(&input), &output)?;
+            compress_adaptive(&input, &output, progress).and_then(|()| finish(&[input], &output, verify, remove, None))
+        }
+        Command::Compress {
+            input, output, symbols, optimal, threads, cache, if_changed, verify, remove, progress, stats, dump_table,
+            use_table, mmap, ..
+        } => {
+            distinct_output(std::slice::from_ref(&input), &output)?;
+            let blocks = if optimal { Blocks::Optimal } else { Blocks::Parallel(threads.unwrap_or_else(cpus)) };
+            read_table(use_table.as_deref()).and_then(|table| {
+                let dump_table = dump_table.as_deref();
+                let options = Options { cache, if_changed, progress, stats, mmap, dump_table, table: table.as_ref() };
+                match compress(&input, &output, symbols, blocks, options)? {
+                    true => finish(&[input], &output, verify, remove, table.as_ref()),
+                    // --if-changed, which never removes the input, left the output as it was.
+                    false => Ok(()),
+                }
+            })
+        }
+        Command::Archive { inputs, output, symbols, adaptive, optimal, threads, verify, remove } => {
+            let blocks = if optimal { Blocks::Optimal } else { Blocks::Parallel(threads.unwrap_or_else(cpus)) };
+            distinct_output(&inputs, &output)?;
+            archive(&inputs, &output, (!adaptive).then_some((symbols, blocks)))
+                .and_then(|()| finish(&inputs, &output, verify, remove, None))
+        }
+        Command::Extract { input, output, threads } => extract(&input, &output, threads.unwrap_or_else(cpus)),
+        Command::Decompress { input, output, threads, progress, use_table } => read_table(use_table.as_deref())
+            .and_then(|table| decompress(&input, &output, threads.unwrap_or_else(cpus), progress, table)),
+        Command::Stats { input, verbose } => stats(&input, verbose),
+        Command::DryRun { input, symbols } => dry_run(&input, symbols),
+        Command::Copy { source, destination, entries } => copy_entries(&source, &destination, &entries),
+        Command::Help => {
+            println!("{}", text!(Usage));
+            Ok(())
+        }
+        Command::Version { verbose } => {
+            println!("huffman-rs {}", env!("CARGO_PKG_VERSION"));
+            if verbose {
+                println!("{}", capabilities().join("\n"));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// What this build of the tool supports and does by default, for bug reports.
+fn capabilities() -> Vec<String> {
+    let features: Vec<&str> = [("std", cfg!(feature = "std")), ("mmap", cfg!(feature = "mmap"))]
+        .iter()
+        .filter_map(|&(feature, enabled)| enabled.then_some(feature))
+        .collect();
+    let or_none = |list: &[&str]| if list.is_empty() { text!(Nothing) } else { list.join(", ") };
+    let cache = match Cache::open() {
+        Some(cache) => text!(VersionCache, cache.dir().display(), cache.limit()),
+        None => text!(VersionNoCache),
+    };
+
+    vec![
+        text!(VersionFileFormat, format::VERSION, format::OLDEST_VERSION),
+        text!(VersionArchiveFormat, archive::VERSION),
+        text!(VersionFeatures, or_none(&features)),
+        text!(VersionTarget, std::env::consts::ARCH, std::env::consts::OS),
+        text!(VersionSimd, or_none(&cpu_features())),
+        text!(VersionBlockSize, huffman::DEFAULT_BLOCK_SIZE),
+        text!(VersionThreads, cpus()),
+        cache,
+    ]
+}
+
+/// The vector instructions found at run time, which the coder does not use
+/// yet but which tell machines apart in bug reports.
+fn cpu_features() -> Vec<&'static str> {
+    #[cfg(target_arch = "x86_64")]
+    let detected = [
+        ("sse2", std::arch::is_x86_feature_detected!("sse2")),
+        ("sse4.2", std::arch::is_x86_feature_detected!("sse4.2")),
+        ("avx2", std::arch::is_x86_feature_detected!("avx2")),
+        ("bmi2", std::arch::is_x86_feature_detected!("bmi2")),
+        ("avx512f", std::arch::is_x86_feature_detected!("avx512f")),
+    ];
+    #[cfg(target_arch = "aarch64")]
+    let detected = [("neon", std::arch::is_aarch64_feature_detected!("neon"))];
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let detected: [(&str, bool); 0] = [];
+
+    detected.iter().filter_map(|&(feature, found)| found.then_some(feature)).collect()
+}
+
+/// An error and the file it concerns.
+#[derive(Debug)]
+struct Failure {
+    path: PathBuf,
+    error: HuffmanError,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+/// What to do about `failure` of `command`, where there is more to say than
+/// the error does: the hint it was given, if any, or else one for the command.
+fn hint(command: &str, failure: &Failure) -> Option<String> {
+    let mut error = &failure.error;
+    while let HuffmanError::Context { error: inner, hint, .. } = error {
+        if hint.is_some() {
+            return hint.clone();
+        }
+        error = inner;
+    }
+
+    let path = failure.path.display();
+    let decoding = command == "decompress" || command == "extract";
+    match error {
+        HuffmanError::BadMagic(Container::Compressed) if command == "decompress" => Some(match magic(&failure.path) {
+            Some(archive::MAGIC) => text!(HintArchive, path),
+            _ => text!(HintCompress, path),
+        }),
+        HuffmanError::BadMagic(Container::Archive) => Some(match magic(&failure.path) {
+            Some(format::MAGIC) => text!(HintOneFile, path),
+            _ => text!(HintNewArchive, path),
+        }),
+        HuffmanError::UnsupportedVersion { .. } => Some(text!(HintNewer, path, env!("CARGO_PKG_VERSION"))),
+        HuffmanError::Truncated if decoding => Some(text!(HintCutShort, path)),
+        HuffmanError::CorruptStream(_) | HuffmanError::ChecksumMismatch if decoding => Some(text!(HintDamaged, path)),
+        HuffmanError::MissingTable => Some(text!(HintUseTable)),
+        HuffmanError::OddLength => Some(text!(HintOddBytes)),
+        HuffmanError::InvalidTable(_) => Some(text!(HintDumpTable, path)),
+        _ => None,
+    }
+}
+
+/// The identifier of `error` printed with `--json`, which stays the same
+/// whatever the message says so that scripts can tell failures apart.
+fn error_code(error: &HuffmanError) -> &'static str {
+    match error {
+        HuffmanError::Io(error) if error.kind() == ErrorKind::NotFound => "E_NOT_FOUND",
+        HuffmanError::Io(error) if error.kind() == ErrorKind::PermissionDenied => "E_PERMISSION",
+        HuffmanError::Io(_) => "E_IO",
+        HuffmanError::EmptyInput => "E_EMPTY",
+        HuffmanError::Truncated => "E_TRUNCATED",
+        HuffmanError::BadMagic(_) => "E_BAD_MAGIC",
+        HuffmanError::UnsupportedVersion { .. } => "E_VERSION",
+        HuffmanError::BadHeader(_) => "E_BAD_HEADER",
+        HuffmanError::CorruptStream(_) => "E_CORRUPT",
+        HuffmanError::ChecksumMismatch => "E_CHECKSUM",
+        HuffmanError::NotUtf8 => "E_NOT_UTF8",
+        HuffmanError::WrongSymbolKind => "E_SYMBOL_KIND",
+        HuffmanError::MissingTable => "E_MISSING_TABLE",
+        // Kept from before tables and lengths had errors of their own.
+        HuffmanError::InvalidTable(_) | HuffmanError::OddLength | HuffmanError::InvalidInput(_) => "E_INVALID_INPUT",
+        HuffmanError::Context { error, .. } => error_code(error),
+    }
+}
+
+/// A failure as one line of JSON, such as
+/// `{"code":"E_TRUNCATED","message":"Compressed data ended early","path":"a.huf"}`,
+/// with a `hint` if there is one.
+fn json_error(code: &str, message: &str, path: Option<&Path>, hint: Option<&str>) -> String {
+    let mut json = format!("{{\"code\":{},\"message\":{}", json_string(code), json_string(message));
+    if let Some(path) = path {
+        json += &format!(",\"path\":{}", json_string(&path.to_string_lossy()));
+    }
+    if let Some(hint) = hint {
+        json += &format!(",\"hint\":{}", json_string(hint));
+    }
+
+    json + "}"
+}
+
+/// `text` as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            '\n' => json += "\\n",
+            c if c < ' ' => json += &format!("\\u{:04x}", c as u32),
+            c => json.push(c),
+        }
+    }
+
+    json + "\""
+}
+
+/// The first four bytes of the file at `path`, if it has as many.
+fn magic(path: &Path) -> Option<[u8; 4]> {
+    let mut magic = [0; 4];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).ok()?;
+    Some(magic)
+}
+
+/// How `compress` chooses and codes its blocks.
+#[derive(Debug, Clone, Copy)]
+enum Blocks {
+    /// To make the output as small as possible.
+    Optimal,
+    /// Of the default size, coded on this many threads.
+    Parallel(usize),
+}
+
+fn cpus() -> usize {
+    std::thread::available_parallelism().map_or(1, |count| count.get())
+}
+
+/// Attributes errors to `path`.
+fn at<E: Into<HuffmanError>>(path: &Path) -> impl Fn(E) -> Failure + '_ {
+    move |error| Failure { path: path.to_path_buf(), error: error.into() }
+}
+
+/// What `compress` does besides compressing, as the options of the same names
+/// ask.
+#[derive(Debug, Clone, Copy)]
+struct Options<'a> {
+    cache: bool,
+    if_changed: bool,
+    progress: bool,
+    stats: bool,
+    mmap: bool,
+    dump_table: Option<&'a Path>,
+    /// The shared table to code with instead of choosing tables.
+    table: Option<&'a SharedTable>,
+}
+
+/// Reads the shared table at `path`, if one is given.
+fn read_table(path: Option<&Path>) -> Result<Option<SharedTable>, Failure> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let json = fs::read_to_string(path).map_err(at(path))?;
+    SharedTable::from_json(&json).map(Some).map_err(at(path))
+}
+
+/// Compresses `input` to `output`, and returns whether the output was written
+/// rather than skipped or only given a new header.
+fn compress(input: &Path, output: &Path, symbols: Symbols, blocks: Blocks, options: Options) -> Result<bool, Failure> {
+    let Options { cache, if_changed, progress, stats, mmap, dump_table, table } = options;
+    // Only files have a size and time to compare; standard input is never
+    // compressed with --if-changed.
+    let (size, modified) = if if_changed {
+        let metadata = fs::metadata(input).map_err(at(input))?;
+        let modified = metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos() as u64);
+        (metadata.len(), modified)
+    } else {
+        (0, 0)
+    };
+
+    // Skip sources whose size and time are unchanged without reading them,
+    // and those whose contents are unchanged after reading them, if they were
+    // compressed the same way. Only a header of the latest version can be
+    // written, so an output of an older one is compressed again instead.
+    let settings = cache::fingerprint(&[], &format!("{:?} {:?} {:?}", symbols, blocks, table.map(SharedTable::id)));
+    let recorded = if if_changed { recorded_header(output) } else { None };
+    let recorded = recorded.filter(|header| header.version == format::VERSION);
+    let recorded_source = recorded.and_then(|header| header.source).filter(|source| source.settings == settings);
+    if recorded_source.is_some_and(|source| (source.size, source.modified) == (size, modified)) {
+        return Ok(false);
     }
 
-    let file = File::open(&args[1]).unwrap();
-    let mut reader = BufReader::new(file);
-    let frequencies: HashMap<char, u32> = get_frequencies_from_reader(&mut reader).unwrap();
+    let bytes = read_input(input, progress, mmap)?;
+    let source = Source { size: bytes.len() as u64, modified, checksum: Source::checksum(&bytes), settings };
+    if let Some(recorded) = recorded {
+        if recorded_source.is_some_and(|old| (old.size, old.checksum) == (source.size, source.checksum)) {
+            // Record the new time, so the next run needn't read the source.
+            rewrite_header(output, &Header { source: Some(source), ..recorded })?;
+            return Ok(false);
+        }
+    }
+
+    if let Some(path) = dump_table {
+        let table = SharedTable::from_input(symbol_kind(&bytes, symbols), &bytes).map_err(at(input))?;
+        fs::write(path, table.to_json()).map_err(at(path))?;
+    }
+
+    let entropy = stats.then(|| symbol_entropy(&bytes, symbols));
+    let original_size = bytes.len() as u64;
+
+    let cache = if cache { Cache::open() } else { None };
+    let key = cache::fingerprint(&bytes, &format!("{:?} {}", symbols, matches!(blocks, Blocks::Optimal)));
+    let cached = cache.as_ref().and_then(|cache| cache.get(key)).and_then(|tables| tables.compress(&bytes).ok());
+
+    let compressed = match (table, cached) {
+        (Some(table), _) => table.compress(&bytes).map_err(at(input))?,
+        (None, Some(compressed)) => compressed,
+        (None, None) => {
+            let compressed = compress_symbols(&bytes, symbols, blocks).map_err(at(input))?;
+            if let (Some(cache), Ok(tables)) = (&cache, Tables::from_compressed(&compressed)) {
+                // The cache only saves time, so failing to fill it is no failure.
+                let _ = cache.put(key, &tables);
+            }
+            compressed
+        }
+    };
+
+    let compressed = if if_changed {
+        let header = Header { source: Some(source), ..Header::read(&mut compressed.as_slice()).map_err(at(input))? };
+        format::with_header(&compressed, &header).map_err(at(input))?
+    } else {
+        compressed
+    };
 
-    for (key, value) in frequencies {
-        println!("{} {}", key, value);
+    if let Some(entropy) = entropy {
+        print_stats(original_size, entropy, &Report::from_compressed(&compressed).map_err(at(input))?);
     }
+
+    let partial = partial(output);
+    let mut writer = create(&partial)?;
+    if let Err(error) = writer.write_all(&compressed).and_then(|()| writer.flush()) {
+        discard(&partial);
+        return Err(at(output)(error));
+    }
+    Ok(true)
 }
 
-#[derive(Debug, Clone)]
-enum HuffmanNode {
-    Internal { left: Box<HuffmanNode>, right: Box<HuffmanNode> },
-    Leaf { character: char, frequency: usize },
+/// The whole of a file, read into memory or mapped there.
+enum Contents {
+    Read(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(mmap::Mapped),
 }
 
-impl HuffmanNode {
-    fn frequency(&self) -> usize {
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
         match self {
-            HuffmanNode::Internal { left, right } => left.frequency() + right.frequency(),
-            HuffmanNode::Leaf { frequency, .. } => *frequency,
+            Contents::Read(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Contents::Mapped(mapped) => mapped,
         }
     }
 }
 
-impl Ord for HuffmanNode {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let ordering = other.frequency().cmp(&self.frequency());
-        if ordering == Ordering::Equal {
-            let HuffmanNode::Leaf { character: self_char, frequency: _ } = self else { return ordering; };
-            let HuffmanNode::Leaf { character, frequency: _ } = other else { return ordering; };
-            character.cmp(self_char)
-        } else {
-            ordering
+/// Reads all of `input`, or maps it into memory if `mmap` is set, which the
+/// command line only allows if built with the feature.
+fn read_input(input: &Path, progress: bool, mmap: bool) -> Result<Contents, Failure> {
+    #[cfg(feature = "mmap")]
+    if mmap {
+        let file = File::open(input).map_err(at(input))?;
+        // SAFETY: --mmap is documented to need FILE unchanged until the tool
+        // is done with it, so the user who asks for it vouches for that.
+        #[allow(unsafe_code)]
+        let mapped = unsafe { mmap::Mapped::new(&file) };
+        return mapped.map(Contents::Mapped).map_err(at(input));
+    }
+    debug_assert!(!mmap, "--mmap without the mmap feature");
+
+    let mut bytes = Vec::new();
+    open_metered(input, progress)?.read_to_end(&mut bytes).map_err(at(input))?;
+    Ok(Contents::Read(bytes))
+}
+
+/// The kind of symbols `bytes` are compressed as.
+fn symbol_kind(bytes: &[u8], symbols: Symbols) -> SymbolKind {
+    match symbols {
+        Symbols::Auto if std::str::from_utf8(bytes).is_ok() => SymbolKind::Chars,
+        Symbols::Auto => SymbolKind::Bytes,
+        Symbols::U16Le => SymbolKind::U16,
+    }
+}
+
+/// The entropy of `bytes` as the symbols they are compressed as.
+fn symbol_entropy(bytes: &[u8], symbols: Symbols) -> f64 {
+    match symbols {
+        Symbols::Auto => match std::str::from_utf8(bytes) {
+            Ok(text) => stats::entropy(&huffman::get_frequencies(text)),
+            Err(_) => stats::entropy(&huffman::get_byte_frequencies(bytes)),
+        },
+        Symbols::U16Le => {
+            let mut frequencies = HashMap::new();
+            for pair in bytes.chunks_exact(2) {
+                *frequencies.entry([pair[0], pair[1]]).or_insert(0) += 1;
+            }
+            stats::entropy(&frequencies)
         }
     }
 }
 
-impl PartialOrd for HuffmanNode {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// Prints the size compressing `input` would give, from how often its symbols
+/// occur, without coding or writing anything.
+fn dry_run(input: &Path, symbols: Symbols) -> Result<(), Failure> {
+    let mut bytes = Vec::new();
+    open(input)?.read_to_end(&mut bytes).map_err(at(input))?;
+
+    let report = match symbols {
+        Symbols::Auto => match std::str::from_utf8(&bytes) {
+            Ok(text) => Report::predict(text),
+            Err(_) => Report::predict_bytes(&bytes),
+        },
+        Symbols::U16Le => {
+            if !bytes.len().is_multiple_of(2) {
+                return Err(at(input)(HuffmanError::OddLength));
+            }
+            let symbols: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+            Report::predict_u16(&symbols)
+        }
+    };
+
+    let predicted = format!("{:.1}", percent(report.compressed_size, bytes.len() as u64));
+    let overhead = format!("{:.1}", percent(report.header_size(), report.compressed_size));
+    println!("{}", text!(OriginalSize, bytes.len()));
+    println!("{}", text!(PredictedSize, report.compressed_size, predicted));
+    println!("{}", text!(AverageCodeLength, format!("{:.3}", report.average_code_length())));
+    println!("{}", text!(HeaderOverhead, report.header_size(), overhead));
+    Ok(())
+}
+
+/// Prints, on standard error as the output may go to standard output, how
+/// `original_size` bytes of the given entropy compressed.
+fn print_stats(original_size: u64, entropy: f64, report: &Report) {
+    let compressed = format!("{:.1}", percent(report.compressed_size, original_size));
+    let overhead = format!("{:.1}", percent(report.header_size(), report.compressed_size));
+
+    eprintln!("{}", text!(OriginalSize, original_size));
+    eprintln!("{}", text!(CompressedSize, report.compressed_size, compressed));
+    eprintln!("{}", text!(Entropy, format!("{:.3}", entropy)));
+    eprintln!("{}", text!(AverageCodeLength, format!("{:.3}", report.average_code_length())));
+    eprintln!("{}", text!(HeaderOverhead, report.header_size(), overhead));
+}
+
+/// `size` as a percentage of `of`, or 0 of nothing.
+fn percent(size: u64, of: u64) -> f64 {
+    if of == 0 { 0.0 } else { size as f64 * 100.0 / of as f64 }
+}
+
+/// Opens the file at `path` for reading, or standard input for `-`.
+fn open(path: &Path) -> Result<Box<dyn Read>, Failure> {
+    if is_stdio(path) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    Ok(Box::new(BufReader::new(File::open(path).map_err(at(path))?)))
+}
+
+/// Like [`open`], showing how much has been read if `progress` is set.
+fn open_metered(path: &Path, progress: bool) -> Result<Box<dyn Read>, Failure> {
+    let reader = open(path)?;
+    if !progress {
+        return Ok(reader);
+    }
+
+    let total = if is_stdio(path) { None } else { fs::metadata(path).ok().map(|metadata| metadata.len()) };
+    let mut meter = Meter::new(total);
+    Ok(Box::new(huffman::Progress::new(reader, move |done| meter.update(done))))
+}
+
+/// Creates the file at `path` for writing, or writes to standard output for
+/// `-`.
+fn create(path: &Path) -> Result<Box<dyn Write>, Failure> {
+    if is_stdio(path) {
+        return Ok(Box::new(BufWriter::new(io::stdout().lock())));
+    }
+
+    Ok(Box::new(BufWriter::new(File::create(path).map_err(at(path))?)))
+}
+
+/// Where `output` is written before it is checked and renamed into place by
+/// [`finish`], so that a failure leaves no half of it nor harms a file already
+/// there. Standard output is written directly.
+fn partial(output: &Path) -> PathBuf {
+    if is_stdio(output) {
+        return output.to_path_buf();
+    }
+
+    let mut partial = output.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+/// Whether `a` and `b` are the same file, by whatever names; false if either
+/// does not exist.
+fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (fs::metadata(a), fs::metadata(b)) {
+            (Ok(a), Ok(b)) => (a.dev(), a.ino()) == (b.dev(), b.ino()),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
     }
 }
 
-impl PartialEq for HuffmanNode {
-    fn eq(&self, other: &Self) -> bool {
-        self.frequency() == other.frequency()
+/// Fails, before anything is written, if `output` is one of `inputs`, which
+/// writing it would destroy.
+fn distinct_output(inputs: &[PathBuf], output: &Path) -> Result<(), Failure> {
+    if is_stdio(output) {
+        return Ok(());
+    }
+    match inputs.iter().find(|input| !is_stdio(input) && same_file(input, output)) {
+        Some(input) => Err(at(output)(HuffmanError::InvalidInput(text!(OutputIsInput, input.display())))),
+        None => Ok(()),
     }
 }
 
-impl Eq for HuffmanNode {}
+/// Removes what was written of a failed output, so no half is left behind.
+/// What went to standard output cannot be taken back.
+fn discard(path: &Path) {
+    if !is_stdio(path) {
+        let _ = fs::remove_file(path);
+    }
+}
 
-fn build_huffman_tree(freq_map: &HashMap<char, usize>) -> HuffmanNode {
-    // Taken from https://opendsa-server.cs.vt.edu/ODSA/Books/CS3/html/Huffman.html
-    let mut heap = BinaryHeap::new();
+/// Checks the [`partial`] file of `output` against its input if `verify` is
+/// set, decoding with `table` if given, or each entry of an archive against
+/// its own; renames it into place, and then removes `inputs` if `remove` is,
+/// once the output is on disk. A failed check removes only the partial file.
+fn finish(
+    inputs: &[PathBuf],
+    output: &Path,
+    verify: bool,
+    remove: bool,
+    table: Option<&SharedTable>,
+) -> Result<(), Failure> {
+    let partial = partial(output);
+    if partial != output {
+        let checked = match inputs {
+            _ if !verify => Ok(()),
+            _ if magic(&partial) == Some(archive::MAGIC) => compare_archive(inputs, &partial, output),
+            [input] => File::open(&partial).map_err(at(output))
+                .and_then(|file| compare(input, BufReader::new(file), output, table)),
+            _ => Ok(()),
+        };
+        if let Err(failure) = checked.and_then(|()| fs::rename(&partial, output).map_err(at(output))) {
+            discard(&partial);
+            return Err(failure);
+        }
+    }
 
-    for (&character, &frequency) in freq_map.iter() {
-        heap.push(HuffmanNode::Leaf { character, frequency });
+    if remove {
+        remove::remove_sources(&remove::RealDisk, inputs, output)
+    } else {
+        Ok(())
     }
+}
 
-    while heap.len() > 1 {
-        let left = heap.pop().unwrap();
-        let right = heap.pop().unwrap();
+/// Decompresses `compressed`, what will be `output` or an entry of it, and
+/// compares it with `input` as it goes.
+fn compare(input: &Path, compressed: impl Read, output: &Path, table: Option<&SharedTable>) -> Result<(), Failure> {
+    let mut decoder = huffman::HuffmanDecoder::with_threads(compressed, cpus());
+    if let Some(table) = table {
+        decoder = decoder.with_table(table.clone());
+    }
+    let mut source = BufReader::new(File::open(input).map_err(at(input))?);
 
-        let internal = HuffmanNode::Internal {
-            left: Box::new(left),
-            right: Box::new(right),
+    let mut decoded = vec![0; 1 << 16];
+    let mut original = vec![0; 1 << 16];
+    loop {
+        let length = decoder.read(&mut decoded).map_err(at(output))?;
+        let same = match source.read_exact(&mut original[..length.max(1)]) {
+            // Both must end together.
+            Ok(()) => length > 0 && decoded[..length] == original[..length],
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => length == 0,
+            Err(error) => return Err(at(input)(error)),
         };
+        if !same {
+            let message = text!(VerifyMismatch, input.display());
+            return Err(at(output)(HuffmanError::CorruptStream(message)));
+        }
+        if length == 0 {
+            return Ok(());
+        }
+    }
+}
 
-        heap.push(internal);
+/// Decompresses each entry of the archive `compressed`, the file that will be
+/// `output`, and compares it with the one of `inputs` it was compressed from.
+fn compare_archive(inputs: &[PathBuf], compressed: &Path, output: &Path) -> Result<(), Failure> {
+    let file = File::open(compressed).map_err(at(output))?;
+    let entries = archive::read_index(&mut BufReader::new(&file)).map_err(at(output))?;
+    if let Some(input) = inputs.get(entries.len()) {
+        return Err(at(output)(HuffmanError::CorruptStream(text!(VerifyMismatch, input.display()))));
     }
 
-    heap.pop().unwrap()
+    for (input, entry) in inputs.iter().zip(&entries) {
+        (&file).seek(SeekFrom::Start(entry.offset)).map_err(at(output))?;
+        compare(input, BufReader::new((&file).take(entry.compressed_size)), output, None)?;
+    }
+
+    Ok(())
+}
+
+/// The header of the compressed file at `path`, if it is one.
+fn recorded_header(path: &Path) -> Option<Header> {
+    Header::read(&mut BufReader::new(File::open(path).ok()?)).ok()
+}
+
+/// Replaces the header of the compressed file at `path` with `header`, through
+/// its [`partial`] file like any other output, so that a failure leaves the
+/// file as it was.
+fn rewrite_header(path: &Path, header: &Header) -> Result<(), Failure> {
+    let compressed = fs::read(path).map_err(at(path))?;
+    let rewritten = format::with_header(&compressed, header).map_err(at(path))?;
+    let partial = partial(path);
+    if let Err(error) = fs::write(&partial, rewritten) {
+        discard(&partial);
+        return Err(at(path)(error));
+    }
+    finish(&[], path, false, false, None)
 }
 
-fn generate_huffman_codes(node: &HuffmanNode, prefix: String, codes: &mut HashMap<char, String>) {
-    match node {
-        HuffmanNode::Leaf { character, .. } => {
-            codes.insert(*character, prefix);
+fn compress_symbols(bytes: &[u8], symbols: Symbols, blocks: Blocks) -> huffman::Result<Vec<u8>> {
+    match symbols {
+        Symbols::Auto => Ok(compress_auto(bytes, blocks)),
+        Symbols::U16Le => {
+            if !bytes.len().is_multiple_of(2) {
+                return Err(HuffmanError::OddLength);
+            }
+
+            let symbols: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+            Ok(match blocks {
+                Blocks::Optimal => huffman::compress_u16_optimal(&symbols),
+                Blocks::Parallel(threads) => huffman::compress_u16_parallel(&symbols, threads),
+            })
+        }
+    }
+}
+
+/// Compresses `input` as it is read, without reading it twice.
+fn compress_adaptive(input: &Path, output: &Path, progress: bool) -> Result<(), Failure> {
+    let reader = open_metered(input, progress)?;
+    let partial = partial(output);
+    let mut encoder = huffman::AdaptiveEncoder::new(create(&partial)?);
+
+    let result = copy(reader, input, &mut encoder, output)
+        .and_then(|()| encoder.finish().map(drop).map_err(at(output)));
+    if result.is_err() {
+        discard(&partial);
+    }
+    result
+}
+
+/// Compresses each of `inputs` into an entry of the archive `output`, named
+/// after the file; adaptively if no symbols and blocks are given. The archive
+/// is written to the [`partial`] file of `output`, for [`finish`] to rename.
+fn archive(inputs: &[PathBuf], output: &Path, coding: Option<(Symbols, Blocks)>) -> Result<(), Failure> {
+    let mut entries = Vec::new();
+    for input in inputs {
+        let name = input.file_name().and_then(|name| name.to_str())
+            .ok_or_else(|| at(input)(HuffmanError::InvalidInput(text!(NotUtf8Name))))?;
+        if entries.iter().any(|entry: &Entry| entry.name == name) {
+            return Err(at(input)(HuffmanError::InvalidInput(text!(SameName, name))));
         }
-        HuffmanNode::Internal { left, right } => {
-            generate_huffman_codes(left, prefix.clone() + "0", codes);
-            generate_huffman_codes(right, prefix + "1", codes);
+        entries.push(Entry::new(name));
+    }
+
+    let partial = partial(output);
+    let mut writer = BufWriter::new(File::create(&partial).map_err(at(output))?);
+    let mut write = || -> Result<(), Failure> {
+        // The index is written again once the offsets are known.
+        archive::write_index(&mut writer, &entries).map_err(at(output))?;
+        let mut offset = archive::index_size(&entries);
+        for (input, entry) in inputs.iter().zip(&mut entries) {
+            let bytes = fs::read(input).map_err(at(input))?;
+            entry.size = bytes.len() as u64;
+            let compressed = match coding {
+                Some((symbols, blocks)) => compress_symbols(&bytes, symbols, blocks).map_err(at(input))?,
+                None => huffman::compress_adaptive(&bytes),
+            };
+            writer.write_all(&compressed).map_err(at(output))?;
+            (entry.offset, entry.compressed_size) = (offset, compressed.len() as u64);
+            offset += entry.compressed_size;
         }
+
+        writer.seek(SeekFrom::Start(0)).map_err(at(output))?;
+        archive::write_index(&mut writer, &entries).map_err(at(output))?;
+        writer.flush().map_err(at(output))
+    };
+
+    let result = write();
+    if result.is_err() {
+        discard(&partial);
     }
+    result
 }
 
-fn get_frequencies_from_reader<R: BufRead>(reader: &mut R) -> std::io::Result<HashMap<char, u32>> {
-    let mut frequencies: HashMap<char, u32> = HashMap::new();
-    let mut line = String::new();
+/// Decompresses each entry of the archive `input` into a file of its name in
+/// `dir`, through its [`partial`] file so that a failed entry leaves a file
+/// already there as it was.
+fn extract(input: &Path, dir: &Path, threads: usize) -> Result<(), Failure> {
+    let file = File::open(input).map_err(at(input))?;
+    let entries = archive::read_index(&mut BufReader::new(&file)).map_err(at(input))?;
+    fs::create_dir_all(dir).map_err(at(dir))?;
 
-    while reader.read_line(&mut line)? > 0 {
-        let frequencies_temp = get_frequencies(&line);
+    for entry in entries {
+        // Only ever write into `dir`, whatever the archive says.
+        let mut components = Path::new(&entry.name).components();
+        if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            let message = text!(RefusingToExtract, entry.name);
+            return Err(at(input)(HuffmanError::BadHeader(message)));
+        }
 
-        for (key, value) in frequencies_temp {
-            *frequencies.entry(key).or_insert(0) += value;
+        let output = dir.join(&entry.name);
+        distinct_output(&[input.to_path_buf()], &output)?;
+        (&file).seek(SeekFrom::Start(entry.offset)).map_err(at(input))?;
+        let reader = BufReader::new((&file).take(entry.compressed_size));
+        let decoder = huffman::HuffmanDecoder::with_threads(reader, threads);
+        let partial = partial(&output);
+        let writer = BufWriter::new(File::create(&partial).map_err(at(&output))?);
+
+        if let Err(mut failure) = copy(decoder, input, writer, &output) {
+            discard(&partial);
+            if failure.path == input {
+                failure.error = failure.error.context(text!(InEntry, entry.name));
+            }
+            return Err(failure);
+        }
+        finish(&[input.to_path_buf()], &output, false, false, None)?;
+    }
+
+    Ok(())
+}
+
+/// Copies the entries `names` of the archive `source` into the archive
+/// `destination`, made if it does not exist, as they are compressed.
+fn copy_entries(source: &Path, destination: &Path, names: &[String]) -> Result<(), Failure> {
+    let from = File::open(source).map_err(at(source))?;
+    let index = archive::read_index(&mut BufReader::new(&from)).map_err(at(source))?;
+    let into = match File::open(destination) {
+        Ok(file) => Some(file),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => return Err(at(destination)(error)),
+    };
+
+    // Every stream of the new archive, with the file it is in now.
+    let mut streams = Vec::new();
+    if let Some(file) = &into {
+        let entries = archive::read_index(&mut BufReader::new(file)).map_err(at(destination))?;
+        streams.extend(entries.into_iter().map(|entry| (file, destination, entry)));
+    }
+    for name in names {
+        let entry = index.iter().find(|entry| entry.name == *name);
+        let entry = entry.ok_or_else(|| at(source)(HuffmanError::InvalidInput(text!(NoSuchEntry, name))))?;
+        if streams.iter().any(|(_, _, stream)| stream.name == *name) {
+            return Err(at(destination)(HuffmanError::InvalidInput(text!(SameName, name))));
         }
+        streams.push((&from, source, entry.clone()));
+    }
+
+    let mut entries: Vec<Entry> = streams.iter().map(|(_, _, entry)| entry.clone()).collect();
+    let mut offset = archive::index_size(&entries);
+    for entry in &mut entries {
+        entry.offset = offset;
+        offset += entry.compressed_size;
+    }
+
+    // Written beside the destination and renamed over it, as the streams
+    // already there are read while writing.
+    let mut temporary = destination.as_os_str().to_owned();
+    temporary.push(".copying");
+    let temporary = PathBuf::from(temporary);
+    let write = || -> Result<(), Failure> {
+        let mut writer = BufWriter::new(File::create(&temporary).map_err(at(destination))?);
+        archive::write_index(&mut writer, &entries).map_err(at(destination))?;
+        for (mut file, path, entry) in streams {
+            file.seek(SeekFrom::Start(entry.offset)).map_err(at(path))?;
+            let mut stream = file.take(entry.compressed_size);
+            copy(&mut stream, path, &mut writer, destination)?;
+            if stream.limit() != 0 {
+                return Err(at(path)(HuffmanError::Truncated));
+            }
+        }
+        writer.flush().map_err(at(destination))?;
+        drop(writer);
+
+        fs::rename(&temporary, destination).map_err(at(destination))
+    };
 
-        line.clear();
+    let result = write();
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
     }
+    result
+}
+
+/// Codes text by character, anything else byte by byte.
+fn compress_auto(input: &[u8], blocks: Blocks) -> Vec<u8> {
+    match (std::str::from_utf8(input), blocks) {
+        (Ok(text), Blocks::Optimal) => huffman::compress_optimal(text),
+        (Ok(text), Blocks::Parallel(threads)) => huffman::compress_parallel(text, threads),
+        (Err(_), Blocks::Optimal) => huffman::compress_bytes_optimal(input),
+        (Err(_), Blocks::Parallel(threads)) => huffman::compress_bytes_parallel(input, threads),
+    }
+}
+
+fn decompress(
+    input: &Path,
+    output: &Path,
+    threads: usize,
+    progress: bool,
+    table: Option<SharedTable>,
+) -> Result<(), Failure> {
+    // Creating the output would cut short the input while it is read.
+    distinct_output(&[input.to_path_buf()], output)?;
+    let mut decoder = huffman::HuffmanDecoder::with_threads(open_metered(input, progress)?, threads);
+    if let Some(table) = table {
+        decoder = decoder.with_table(table);
+    }
+    let partial = partial(output);
+    let writer = create(&partial)?;
+
+    if let Err(failure) = copy(decoder, input, writer, output) {
+        discard(&partial);
+        return Err(failure);
+    }
+    finish(&[input.to_path_buf()], output, false, false, None)
+}
 
-    Ok(frequencies)
+/// Copies everything from `reader` to `writer`, attributing errors to the
+/// file they happened on.
+fn copy(mut reader: impl Read, input: &Path, mut writer: impl Write, output: &Path) -> Result<(), Failure> {
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        let length = reader.read(&mut buffer).map_err(at(input))?;
+        if length == 0 {
+            return writer.flush().map_err(at(output));
+        }
+        writer.write_all(&buffer[..length]).map_err(at(output))?;
+    }
 }
 
-fn get_frequencies(line: &str) -> HashMap<char, u32> {
-    let mut frequencies: HashMap<char, u32> = HashMap::new();
-    for c in line.chars() {
-        *frequencies.entry(c).or_insert(0) += 1;
+fn stats(input: &Path, verbose: bool) -> Result<(), Failure> {
+    let mut reader = BufReader::new(File::open(input).map_err(at(input))?);
+    // In order of the symbols, so the output is the same every time.
+    match huffman::get_frequencies_from_reader(&mut reader) {
+        Ok(frequencies) => {
+            for (key, value) in sorted(frequencies) {
+                println!("{} {}", key, value);
+            }
+        }
+        Err(HuffmanError::NotUtf8) => {
+            let mut reader = BufReader::new(File::open(input).map_err(at(input))?);
+            let frequencies = huffman::get_byte_frequencies_from_reader(&mut reader).map_err(at(input))?;
+
+            for (key, value) in sorted(frequencies) {
+                println!("{:#04x} {}", key, value);
+            }
+        }
+        Err(error) => return Err(at(input)(error)),
+    }
+
+    if verbose {
+        print_blocks(&compress_auto(&fs::read(input).map_err(at(input))?, Blocks::Parallel(cpus()))).map_err(at(input))?;
     }
 
+    Ok(())
+}
+
+fn sorted<S: Ord>(frequencies: HashMap<S, usize>) -> Vec<(S, usize)> {
+    let mut frequencies: Vec<(S, usize)> = frequencies.into_iter().collect();
+    frequencies.sort_unstable_by(|a, b| a.0.cmp(&b.0));
     frequencies
 }
 
+/// Prints how each block of `compressed` is stored.
+fn print_blocks(compressed: &[u8]) -> huffman::Result<()> {
+    let mut rest = compressed;
+    let header = Header::read(&mut rest)?;
+
+    let mut index = 0;
+    while let Some(block) = BlockHeader::read(&header, &mut rest)? {
+        let count = match &block.table {
+            Table::Frequencies(frequencies) => frequencies.len(),
+            Table::CodeLengths(lengths) => lengths.len(),
+            Table::Shared(_) => 0,
+        };
+        let escape = match block.escape {
+            Some(escape) => text!(BlockEscape, escape.literal_width),
+            None => String::new(),
+        };
+        let encoding = match block.encoding {
+            TableEncoding::CodeLengths => text!(StoredAsCodeLengths),
+            TableEncoding::Topology => text!(StoredAsTopology),
+            TableEncoding::Shared => text!(StoredAsSharedTable),
+        };
+        let encoded_size = block.encoded_size.unwrap_or(rest.len() as u64);
+
+        println!(
+            "{}",
+            text!(Block, index, block.length, count, escape, encoding, block.size(header.kind), encoded_size),
+        );
+
+        rest = &rest[encoded_size as usize..];
+        index += 1;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_get_frequencies() {
-        let frequencies = get_frequencies("abbcccdddd");
+    fn test_failures_remove_output() {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-main-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("a.txt"), dir.join("a.txt.huf"));
+        fs::write(&input, "abbcccdddd".repeat(100)).unwrap();
+
+        // Cut short in the checksum, after all the data decoded.
+        let compressed = huffman::compress(&"abbcccdddd".repeat(100));
+        fs::write(&output, &compressed[..compressed.len() - 2]).unwrap();
+        assert!(decompress(&output, &dir.join("b.txt"), 1, false, None).is_err());
+        assert!(!dir.join("b.txt").exists());
+        // Nor does it harm an output already there.
+        fs::write(dir.join("b.txt"), "good").unwrap();
+        assert!(decompress(&output, &dir.join("b.txt"), 1, false, None).is_err());
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "good");
+        assert!(!partial(&dir.join("b.txt")).exists());
+        // Nor is the input cut short to write it.
+        assert!(decompress(&output, &dir.join(".").join("a.txt.huf"), 1, false, None).is_err());
+        assert_eq!(fs::read(&output).unwrap(), compressed[..compressed.len() - 2]);
 
-        assert_eq!(frequencies[&'a'], 1);
-        assert_eq!(frequencies[&'b'], 2);
-        assert_eq!(frequencies[&'c'], 3);
-        assert_eq!(frequencies[&'d'], 4);
+        // A failed archive leaves the file that was there before.
+        let before = fs::read(&output).unwrap();
+        let missing = dir.join("missing.txt");
+        assert!(archive(&[input.clone(), missing], &output, None).is_err());
+        assert!(!partial(&output).exists());
+        assert_eq!(fs::read(&output).unwrap(), before);
+
+        // A failed --verify leaves the file that was there before.
+        fs::write(&output, "before").unwrap();
+        fs::write(partial(&output), huffman::compress("something else")).unwrap();
+        assert!(finish(std::slice::from_ref(&input), &output, true, true, None).is_err());
+        assert!(!partial(&output).exists() && input.exists());
+        assert_eq!(fs::read_to_string(&output).unwrap(), "before");
+
+        let same = dir.join("..").join(dir.file_name().unwrap()).join("a.txt");
+        assert!(distinct_output(std::slice::from_ref(&input), &same).is_err());
+        assert!(distinct_output(std::slice::from_ref(&input), &output).is_ok());
+        assert!(distinct_output(&[PathBuf::from("-")], Path::new("-")).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_frequencies_from_reader() {
-        let mut cursor = std::io::Cursor::new(b"test\nmyreallycooltest");
-        let frequencies = get_frequencies_from_reader(&mut cursor).unwrap();
-
-        assert_eq!(frequencies[&'t'], 4);
-        assert_eq!(frequencies[&'e'], 3);
-        assert_eq!(frequencies[&'s'], 2);
-        assert_eq!(frequencies[&'m'], 1);
-        assert_eq!(frequencies[&'y'], 2);
-        assert_eq!(frequencies[&'r'], 1);
-        assert_eq!(frequencies[&'a'], 1);
-        assert_eq!(frequencies[&'l'], 3);
-        assert_eq!(frequencies[&'c'], 1);
-        assert_eq!(frequencies[&'o'], 2);
-        assert_eq!(frequencies[&'\n'], 1);
-        assert_eq!(frequencies.get(&'d'), None);
+    fn test_if_changed_older_version() {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-if-changed-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("a.txt"), dir.join("a.txt.huf"));
+        fs::write(&input, "abbcccdddd".repeat(100)).unwrap();
+        let options = Options {
+            cache: false, if_changed: true, progress: false, stats: false, mmap: false, dump_table: None, table: None,
+        };
+        let compress = || {
+            let written = compress(&input, &output, Symbols::Auto, Blocks::Parallel(1), options).unwrap();
+            if written {
+                finish(std::slice::from_ref(&input), &output, false, false, None).unwrap();
+            }
+            written
+        };
+        assert!(compress());
+
+        // An output of the previous version, whose source was touched since.
+        let mut bytes = fs::read(&output).unwrap();
+        bytes[format::MAGIC.len()] = format::VERSION - 1;
+        fs::write(&output, bytes).unwrap();
+        let touched = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        File::options().write(true).open(&input).unwrap().set_modified(touched).unwrap();
+
+        assert!(compress());
+        let header = recorded_header(&output).unwrap();
+        assert_eq!(header.version, format::VERSION);
+        assert!(!compress());
+        assert_eq!(huffman::decompress(&fs::read(&output).unwrap()).unwrap(), "abbcccdddd".repeat(100));
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_binary_heap_from_frequencies() {
-        let mut frequencies: HashMap<char, usize> = HashMap::new();
-        frequencies.insert('C', 32);
-        frequencies.insert('D', 42);
-        frequencies.insert('E', 120);
-        frequencies.insert('K', 7);
-        frequencies.insert('L', 42);
-        frequencies.insert('M', 24);
-        frequencies.insert('U', 37);
-        frequencies.insert('Z', 2);
-
-        let root = build_huffman_tree(&frequencies);
-        let mut huffman_codes: HashMap<char, String> = HashMap::new();
-        generate_huffman_codes(&root, String::new(), &mut huffman_codes);
-
-        let expected = HashMap::from([
-            ('M', "11111"),
-            ('D', "101"),
-            ('U', "100"),
-            ('C', "1110"),
-            ('E', "0"),
-            ('K', "111101"),
-            ('L', "110"),
-            ('Z', "111100")
-        ]);
-
-        for (character, code) in &huffman_codes {
-            assert!(expected.get(character).is_some());
-            assert_eq!(code, expected[character]);
-        }
-    }
-}
\ No newline at end of file
+    fn test_if_changed_options() {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-if-changed-options-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("a.txt"), dir.join("a.txt.huf"));
+        fs::write(&input, "abbcccdddd".repeat(100)).unwrap();
+        let table = SharedTable::from_input(SymbolKind::Chars, "abcd".as_bytes()).unwrap();
+        let compress = |symbols, blocks, table| {
+            let options = Options {
+                cache: false, if_changed: true, progress: false, stats: false, mmap: false, dump_table: None, table,
+            };
+            let written = compress(&input, &output, symbols, blocks, options).unwrap();
+            if written {
+                finish(std::slice::from_ref(&input), &output, false, false, table).unwrap();
+            }
+            written
+        };
+
+        assert!(compress(Symbols::Auto, Blocks::Parallel(1), None));
+        assert!(!compress(Symbols::Auto, Blocks::Parallel(1), None));
+        // Each other way of coding it compresses it again, once.
+        for (symbols, blocks, table) in [
+            (Symbols::U16Le, Blocks::Parallel(1), None),
+            (Symbols::U16Le, Blocks::Optimal, None),
+            (Symbols::Auto, Blocks::Parallel(2), None),
+            (Symbols::Auto, Blocks::Parallel(2), Some(&table)),
+        ] {
+            assert!(compress(symbols, blocks, table), "{:?} {:?}", symbols, blocks);
+            assert!(!compress(symbols, blocks, table), "{:?} {:?}", symbols, blocks);
+        }
+
+        // A source touched but unchanged only gets a new header.
+        let before = fs::read(&output).unwrap();
+        let touched = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        File::options().write(true).open(&input).unwrap().set_modified(touched).unwrap();
+        assert!(!compress(Symbols::Auto, Blocks::Parallel(2), Some(&table)));
+        let after = fs::read(&output).unwrap();
+        assert_ne!(after, before);
+        assert_eq!(after.len(), before.len());
+        assert!(!partial(&output).exists());
+        let recorded = recorded_header(&output).unwrap().source.unwrap();
+        assert_eq!(recorded.modified, touched.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+        assert!(!compress(Symbols::Auto, Blocks::Parallel(2), Some(&table)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_archive_verify() {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-archive-verify-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = ["a.txt", "b.txt"].map(|name| dir.join(name));
+        fs::write(&files[0], "abbcccdddd").unwrap();
+        fs::write(&files[1], "霍夫曼").unwrap();
+        let output = dir.join("ab.huf");
+
+        archive(&files, &output, None).unwrap();
+        finish(&files, &output, true, false, None).unwrap();
+        let before = fs::read(&output).unwrap();
+
+        // An entry that no longer matches its file removes nothing.
+        archive(&files, &output, Some((Symbols::Auto, Blocks::Parallel(1)))).unwrap();
+        fs::write(&files[1], "霍夫曼编码").unwrap();
+        let failure = finish(&files, &output, true, true, None).unwrap_err();
+        assert!(matches!(failure.error, HuffmanError::CorruptStream(_)), "{}", failure);
+        assert!(files.iter().all(|file| file.exists()));
+        assert!(!partial(&output).exists());
+        assert_eq!(fs::read(&output).unwrap(), before);
+
+        archive(&files, &output, None).unwrap();
+        finish(&files, &output, true, true, None).unwrap();
+        assert!(!files.iter().any(|file| file.exists()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_entries() {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-copy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = ["a.txt", "b.txt", "c.txt"].map(|name| dir.join(name));
+        for (file, contents) in files.iter().zip(["abbcccdddd", "霍夫曼", ""]) {
+            fs::write(file, contents).unwrap();
+        }
+        let (from, into) = (dir.join("from.huf"), dir.join("into.huf"));
+        archive(&files[..2], &from, None).and_then(|()| finish(&files[..2], &from, false, false, None)).unwrap();
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        // Made if it does not exist, and added to if it does.
+        copy_entries(&from, &into, &names(&["b.txt"])).unwrap();
+        archive(&files[2..], &from, Some((Symbols::Auto, Blocks::Parallel(1)))).unwrap();
+        finish(&files[2..], &from, false, false, None).unwrap();
+        copy_entries(&from, &into, &names(&["c.txt"])).unwrap();
+
+        let index = archive::read_index(&mut BufReader::new(File::open(&into).unwrap())).unwrap();
+        assert_eq!(index.iter().map(|entry| entry.name.as_str()).collect::<Vec<_>>(), ["b.txt", "c.txt"]);
+        let out = dir.join("out");
+        extract(&into, &out, 1).unwrap();
+        assert_eq!(fs::read_to_string(out.join("b.txt")).unwrap(), "霍夫曼");
+        assert_eq!(fs::read_to_string(out.join("c.txt")).unwrap(), "");
+
+        // What cannot be decoded is said to be in its entry.
+        let mut cut = fs::read(&into).unwrap();
+        cut.truncate(cut.len() - 1);
+        let cut_path = dir.join("cut.huf");
+        fs::write(&cut_path, cut).unwrap();
+        fs::create_dir_all(dir.join("cut")).unwrap();
+        fs::write(dir.join("cut").join("c.txt"), "before").unwrap();
+        let failure = extract(&cut_path, &dir.join("cut"), 1).unwrap_err();
+        assert!(failure.to_string().contains(": entry c.txt: "), "{}", failure);
+        // Leaving what was there before.
+        assert_eq!(fs::read_to_string(dir.join("cut").join("c.txt")).unwrap(), "before");
+        assert!(!partial(&dir.join("cut").join("c.txt")).exists());
+
+        let before = fs::read(&into).unwrap();
+        assert!(copy_entries(&from, &into, &names(&["c.txt"])).is_err());
+        assert!(copy_entries(&from, &into, &names(&["d.txt"])).is_err());
+        assert_eq!(fs::read(&into).unwrap(), before);
+        assert!(!dir.join("into.huf.copying").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hints() {
+        let failure = |error| Failure { path: PathBuf::from("missing.txt"), error };
+        let not_compressed = failure(HuffmanError::BadMagic(Container::Compressed));
+
+        let compress = hint("decompress", &not_compressed).unwrap();
+        assert!(compress.ends_with("did you mean `huffman-rs compress missing.txt`?"), "{}", compress);
+        assert!(hint("decompress", &failure(HuffmanError::MissingTable)).unwrap().contains("--use-table"));
+        // A failed --verify is not the input's fault.
+        assert_eq!(hint("compress", &failure(HuffmanError::CorruptStream("mismatch".to_string()))), None);
+        assert!(hint("decompress", &failure(HuffmanError::ChecksumMismatch)).is_some());
+        assert_eq!(hint("decompress", &failure(HuffmanError::EmptyInput)), None);
+
+        // Seen through context, and given way to by a hint of the error's own.
+        let cut_short = failure(HuffmanError::Truncated.context("entry a.txt"));
+        assert!(hint("extract", &cut_short).unwrap().contains("cut short"));
+        let given = failure(HuffmanError::Truncated.context("entry a.txt").with_hint("copy it again"));
+        assert_eq!(hint("extract", &given).as_deref(), Some("copy it again"));
+    }
+
+    #[test]
+    fn test_json_errors() {
+        assert_eq!(error_code(&HuffmanError::BadMagic(Container::Archive)), "E_BAD_MAGIC");
+        assert_eq!(error_code(&HuffmanError::UnsupportedVersion { container: Container::Compressed, version: 11 }), "E_VERSION");
+        assert_eq!(error_code(&HuffmanError::BadHeader("Invalid symbol in header".to_string())), "E_BAD_HEADER");
+        assert_eq!(error_code(&HuffmanError::Io(io::Error::from(ErrorKind::NotFound))), "E_NOT_FOUND");
+        assert_eq!(error_code(&HuffmanError::Truncated.context("entry a.txt")), "E_TRUNCATED");
+
+        let json = json_error("E_TRUNCATED", "ended \"early\"\n", Some(Path::new("a\\b.huf")), None);
+        assert_eq!(json, r#"{"code":"E_TRUNCATED","message":"ended \"early\"\n","path":"a\\b.huf"}"#);
+        assert_eq!(json_error("E_USAGE", "a\u{1}", None, Some("b")), r#"{"code":"E_USAGE","message":"a\u0001","hint":"b"}"#);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = capabilities();
+        let start = format!("file format:    writes version {}, reads 1 to {0}", format::VERSION);
+        assert_eq!(capabilities[0], start);
+        let features = if cfg!(feature = "mmap") { "features:       std, mmap" } else { "features:       std" };
+        assert!(capabilities.iter().any(|line| line == features), "{:?}", capabilities);
+        assert!(capabilities.iter().any(|line| line.starts_with("SIMD:           none used; the CPU has ")));
+    }
+}
@@ -0,0 +1,507 @@
+//! The text the tool shows people, in their language where it is translated.
+//!
+//! Every [`Message`] has an English text, given by [`english`]. A translation
+//! is a [`Translation`] listed in [`TRANSLATIONS`]: the code of its language,
+//! such as `de` or `pt_BR`, and the texts it translates, which may leave some
+//! out to show them in English. The language is that of the first of
+//! `LC_ALL`, `LC_MESSAGES` and `LANG` to be set. Each `{}` in a text is
+//! replaced by the next argument, and each `{N}` by the Nth from 0, so that a
+//! translation can put them in another order.
+//!
+//! Errors from the library are shown in English whatever the language.
+
+use std::fmt::{Display, Write};
+use std::sync::OnceLock;
+
+/// `message` in the language of the environment, with the given arguments.
+macro_rules! text {
+    ($message:ident $(, $arg:expr)* $(,)?) => {
+        $crate::messages::lookup($crate::messages::Message::$message, &[$(&$arg),*])
+    };
+}
+pub(crate) use text;
+
+/// Everything the tool says, apart from what the library does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    Usage,
+    Error,
+    Hint,
+    NoCommand,
+    UnknownCommand,
+    UnknownOption,
+    UnexpectedArgument,
+    NeedsFile,
+    NeedsFileName,
+    ThreadsNeedsNumber,
+    ThreadsNeedsPositive,
+    SymbolsNeedsKind,
+    UnknownSymbolKind,
+    OnlyCompress,
+    OnlyCompressOfOneFile,
+    OnlyCompressWithTables,
+    OnlyCompressOrDecompress,
+    OnlyFixedBlocks,
+    OnlyStatsOrCompress,
+    ArchiveNeedsOutput,
+    AdaptiveNoSymbols,
+    AdaptiveNoOptimal,
+    IfChangedNoRemove,
+    ArchivesNotPiped,
+    FilesNotPiped,
+    StatsNotPiped,
+    StatsNoOutput,
+    CopyNoOutput,
+    CopyNeedsArguments,
+    UnknownArchiveCommand,
+    NoSuchEntry,
+    UseTableNoOptions,
+    DryRunNoOptions,
+    MmapNotPiped,
+    MmapNoProgress,
+    MmapNotBuilt,
+    HintArchive,
+    HintCompress,
+    HintOneFile,
+    HintNewArchive,
+    HintNewer,
+    HintCutShort,
+    HintDamaged,
+    HintUseTable,
+    HintOddBytes,
+    HintDumpTable,
+    NotUtf8Name,
+    SameName,
+    OutputIsInput,
+    RemovingOutput,
+    RefusingToExtract,
+    InEntry,
+    VerifyMismatch,
+    Crashed,
+    VersionFileFormat,
+    VersionArchiveFormat,
+    VersionFeatures,
+    VersionTarget,
+    VersionSimd,
+    VersionBlockSize,
+    VersionThreads,
+    VersionCache,
+    VersionNoCache,
+    Nothing,
+    CrashedWithoutReport,
+    OriginalSize,
+    CompressedSize,
+    PredictedSize,
+    Entropy,
+    AverageCodeLength,
+    HeaderOverhead,
+    Block,
+    BlockEscape,
+    StoredAsCodeLengths,
+    StoredAsTopology,
+    StoredAsSharedTable,
+    ProgressOfTotal,
+    ProgressLeft,
+    ProgressRate,
+}
+
+/// The English text of `message`, which every message has.
+pub fn english(message: Message) -> &'static str {
+    match message {
+        Message::Usage => USAGE,
+        Message::Error => "error: {}",
+        Message::Hint => "hint: {}",
+        Message::NoCommand => "No command given",
+        Message::UnknownCommand => "Unknown command '{}'",
+        Message::UnknownOption => "Unknown option '{}'",
+        Message::UnexpectedArgument => "Unexpected argument '{}'",
+        Message::NeedsFile => "{} needs a file",
+        Message::NeedsFileName => "{} needs a file name",
+        Message::ThreadsNeedsNumber => "--threads needs a number",
+        Message::ThreadsNeedsPositive => "--threads needs a positive number",
+        Message::SymbolsNeedsKind => "--symbols needs a kind",
+        Message::UnknownSymbolKind => "Unknown symbol kind '{}'",
+        Message::OnlyCompress => "{} only applies to compress",
+        Message::OnlyCompressOfOneFile => "{} only applies to compress of one file",
+        Message::OnlyCompressWithTables => "{} only applies to compress of one file with tables",
+        Message::OnlyCompressOrDecompress => "{} only applies to compress of one file and to decompress",
+        Message::OnlyFixedBlocks => "{} only applies to compress with fixed blocks, decompress and extract",
+        Message::OnlyStatsOrCompress => "{} only applies to stats and compress",
+        Message::ArchiveNeedsOutput => "Compressing several files needs an --output archive",
+        Message::AdaptiveNoSymbols => "--adaptive always codes bytes and takes no --symbols",
+        Message::AdaptiveNoOptimal => "--adaptive has no blocks to choose and takes no --optimal",
+        Message::IfChangedNoRemove => "--if-changed needs the input to compare with next time and takes no --rm",
+        Message::ArchivesNotPiped => "Archives are files, and cannot be read or written through -",
+        Message::FilesNotPiped => "--if-changed, --verify and --rm need files, not -",
+        Message::StatsNotPiped => "stats reads its file twice and cannot read -",
+        Message::CopyNoOutput => "archive copy writes into its second archive and takes no --output",
+        Message::CopyNeedsArguments => "archive copy needs the archive to copy from, the one to copy into and entry names",
+        Message::UnknownArchiveCommand => "Unknown archive command '{}'",
+        Message::NoSuchEntry => "No entry named {}",
+        Message::StatsNoOutput => "stats prints to the terminal and takes no output",
+        Message::DryRunNoOptions => "--dry-run writes nothing, and only takes --symbols",
+        Message::UseTableNoOptions => "--use-table decides how to code, and takes no other coding options or --stats",
+        Message::MmapNotPiped => "--mmap maps a file, and cannot map -",
+        Message::MmapNoProgress => "--mmap reads nothing to show the --progress of",
+        Message::MmapNotBuilt => "--mmap needs huffman-rs built with the mmap feature",
+        Message::HintArchive => "{} is an archive; did you mean `huffman-rs extract {0}`?",
+        Message::HintCompress => "{} does not start with HUFF; did you mean `huffman-rs compress {0}`?",
+        Message::HintOneFile => "{} holds one file; did you mean `huffman-rs decompress {0}`?",
+        Message::HintNewArchive => "{} does not start with HUFA; did you mean `huffman-rs compress --archive {0}`?",
+        Message::HintNewer => "{} may be from a newer huffman-rs than {}",
+        Message::HintCutShort => "{} was cut short, perhaps by an interrupted copy; compress the original again",
+        Message::HintDamaged => "{} was damaged after it was compressed; compress the original again",
+        Message::HintUseTable => "decompress it with --use-table and the table it was compressed with",
+        Message::HintOddBytes => "--symbols u16le needs an even number of bytes; leave it out to compress bytes",
+        Message::HintDumpTable => "write the table with `huffman-rs compress --dump-table {} FILE`",
+        Message::NotUtf8Name => "Not a UTF-8 file name",
+        Message::SameName => "Another file is also named {}",
+        Message::RemovingOutput => "Refusing to remove the output",
+        Message::OutputIsInput => "Refusing to overwrite the input {}; give another output with -o",
+        Message::RefusingToExtract => "Refusing to extract {}",
+        Message::InEntry => "entry {}",
+        Message::VerifyMismatch => "Does not decompress to {}",
+        Message::VersionFileFormat => "file format:    writes version {}, reads {} to {0}",
+        Message::VersionArchiveFormat => "archive format: version {}",
+        Message::VersionFeatures => "features:       {}",
+        Message::VersionTarget => "target:         {} {}",
+        Message::VersionSimd => "SIMD:           none used; the CPU has {}",
+        Message::VersionBlockSize => "block size:     {} symbols",
+        Message::VersionThreads => "threads:        {}, one per CPU",
+        Message::VersionCache => "cache:          {}, of at most {} bytes",
+        Message::VersionNoCache => "cache:          none, as neither $XDG_CONFIG_HOME nor $HOME is set",
+        Message::Nothing => "none",
+        Message::Crashed => "huffman-rs crashed, which is a bug: {}; please report it with {}",
+        Message::CrashedWithoutReport => "huffman-rs crashed, which is a bug: {}; please report it with this",
+        Message::OriginalSize => "original size:       {} bytes",
+        Message::CompressedSize => "compressed size:     {} bytes ({}% of the original)",
+        Message::PredictedSize => "predicted size:      {} bytes ({}% of the original)",
+        Message::Entropy => "entropy:             {} bits per symbol",
+        Message::AverageCodeLength => "average code length: {} bits per symbol",
+        Message::HeaderOverhead => "header overhead:     {} bytes ({}% of the output)",
+        Message::Block => "block {}: {} symbols, {} in the table{}, stored as {}; {} header bytes, {} encoded bytes",
+        Message::BlockEscape => ", the rest escaped as {} bit literals",
+        Message::StoredAsCodeLengths => "code lengths",
+        Message::StoredAsTopology => "topology",
+        Message::StoredAsSharedTable => "a shared table",
+        Message::ProgressOfTotal => " / {} ({}%)",
+        Message::ProgressLeft => ", {}/s, {}s left",
+        Message::ProgressRate => ", {}/s",
+    }
+}
+
+const USAGE: &str = "\
+Usage: huffman-rs <COMMAND> [OPTIONS] <FILE>...
+
+A FILE of - reads standard input and, by default, writes standard output.
+
+Commands:
+  compress <FILE>...  Compress FILE, to FILE.huf by default, or several files
+                      into one archive
+  decompress <FILE>   Decompress FILE, to FILE without .huf by default
+  extract <FILE>      Restore the files of the archive FILE, into the current
+                      directory by default
+  stats <FILE>        Print the frequency of each symbol in FILE, and with
+                      --verbose, how each block of it would be stored
+  archive copy <FROM> <TO> <NAME>...
+                      Copy the entries NAME of the archive FROM into the
+                      archive TO, made if need be, without decompressing them
+  version             Print the version, and with --verbose, what this build
+                      supports
+
+Options:
+  -o, --output <FILE>     Write to FILE instead of the default, - for standard
+                          output, or for extract, into the directory FILE
+      --archive           Compress into an archive even if given one file
+      --symbols <KIND>    Symbols to compress: auto (characters if the input
+                          is UTF-8, else bytes) or u16le [default: auto]
+      --adaptive          Compress bytes in a single pass with adaptive
+                          coding, which needs no frequency table
+      --optimal           Choose block boundaries to make the output as small
+                          as possible, which is much slower
+      --threads <N>       Compress or decompress blocks on N threads [default:
+                          the number of CPUs]
+      --cache             Reuse the tables of the last compression of the same
+                          contents, and remember them for next time
+      --if-changed        Skip FILE if the output was compressed from it as it
+                          is now, and record it in the output for next time
+      --verify            Decompress the output once written and check that it
+                          matches FILE, or each entry of an archive its file
+      --rm                Remove FILE once the output is safely on disk
+      --progress          Show how much of FILE has been read, how fast and
+                          how long is left
+      --mmap              Map FILE into memory rather than reading it, if
+                          built with the mmap feature; nothing may change or
+                          truncate FILE until compress is done
+      --dry-run           Only count the symbols of FILE, and print the size
+                          and ratio compressing it would give, writing nothing
+      --stats             Print the sizes, the entropy of FILE and how close
+                          the codes came to it once compressed
+      --json              Print failures as JSON, with a code for scripts to
+                          tell them apart
+      --dump-table <FILE> Also write how often each symbol of the input occurs
+                          to FILE, as a JSON table for --use-table
+      --use-table <FILE>  Compress with the shared table in FILE, which the
+                          output refers to rather than storing a table, or
+                          decompress what was
+  -v, --verbose           Print more detail; for compress, like --stats
+  -h, --help              Print this help
+  -V, --version           Print the version";
+
+/// The texts of one language other than English.
+#[derive(Debug)]
+pub struct Translation {
+    /// A language such as `de`, or one as spoken in a country, such as
+    /// `pt_BR`.
+    pub language: &'static str,
+    pub texts: &'static [(Message, &'static str)],
+}
+
+/// The languages the tool speaks besides English.
+pub const TRANSLATIONS: &[Translation] = &[];
+
+/// The texts of the language chosen, falling back to English.
+#[derive(Debug, Clone, Copy)]
+pub struct Catalog {
+    translation: Option<&'static Translation>,
+}
+
+impl Catalog {
+    /// The catalog for `locale`, such as `pt_BR.UTF-8`, out of
+    /// `translations`: one for the language as spoken in its country if
+    /// there is one, else for the language.
+    pub fn new(locale: &str, translations: &'static [Translation]) -> Catalog {
+        let name = locale.split(['.', '@']).next().unwrap_or_default();
+        let language = name.split('_').next().unwrap_or_default();
+        let translation = [name, language]
+            .iter()
+            .find_map(|code| translations.iter().find(|translation| translation.language == *code));
+
+        Catalog { translation }
+    }
+
+    /// The catalog for the locale of the environment.
+    pub fn from_env() -> Catalog {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default();
+
+        Catalog::new(&locale, TRANSLATIONS)
+    }
+
+    /// `message` with `args` in place of its `{}`s.
+    pub fn text(&self, message: Message, args: &[&dyn Display]) -> String {
+        let translated = self
+            .translation
+            .and_then(|translation| translation.texts.iter().find(|&&(key, _)| key == message));
+        fill(translated.map_or_else(|| english(message), |&(_, text)| text), args)
+    }
+}
+
+/// `message` in the language of the environment, with `args` in place of its
+/// `{}`s; see [`text!`] for the shorthand.
+pub fn lookup(message: Message, args: &[&dyn Display]) -> String {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG.get_or_init(Catalog::from_env).text(message, args)
+}
+
+/// `template` with each `{}` replaced by the next of `args`, and each `{N}` by
+/// the Nth. Placeholders without an argument are left out.
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut filled = String::new();
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+
+        let index = match &rest[1..end] {
+            "" => {
+                next += 1;
+                Some(next - 1)
+            }
+            digits => digits.parse().ok(),
+        };
+        match index {
+            Some(index) => {
+                if let Some(arg) = args.get(index) {
+                    let _ = write!(filled, "{}", arg);
+                }
+            }
+            // Not a placeholder.
+            None => filled.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    filled.push_str(rest);
+
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_TRANSLATIONS: &[Translation] = &[
+        Translation { language: "xx", texts: &[(Message::NeedsFile, "{} braucht eine Datei")] },
+        Translation { language: "xx_YY", texts: &[(Message::SameName, "{0} und {0}")] },
+    ];
+
+    /// Every message but the usage in brackets and with its letters
+    /// accented, and some with their arguments in another order, to show
+    /// what has been left untranslated without knowing another language.
+    const PSEUDO: Translation = Translation {
+        language: "en_XA",
+        texts: &[
+            (Message::Error, "[éŕŕöŕ: {0}]"),
+            (Message::Hint, "[hîñt: {0}]"),
+            (Message::NoCommand, "[Ñö çömmåñd gîvéñ]"),
+            (Message::UnknownCommand, "[Ûñkñöwñ çömmåñd '{0}']"),
+            (Message::UnknownOption, "[Ûñkñöwñ öptîöñ '{0}']"),
+            (Message::UnexpectedArgument, "[Ûñéxpéçtéd åŕgûméñt '{0}']"),
+            (Message::NeedsFile, "[{0} ñéédš å fîlé]"),
+            (Message::NeedsFileName, "[{0} ñéédš å fîlé ñåmé]"),
+            (Message::ThreadsNeedsNumber, "[--threads ñéédš å ñûmbéŕ]"),
+            (Message::ThreadsNeedsPositive, "[--threads ñéédš å pöšîtîvé ñûmbéŕ]"),
+            (Message::SymbolsNeedsKind, "[--symbols ñéédš å kîñd]"),
+            (Message::UnknownSymbolKind, "[Ûñkñöwñ šýmböl kîñd '{0}']"),
+            (Message::OnlyCompress, "[{0} öñlý åpplîéš tö çömpŕéšš]"),
+            (Message::OnlyCompressOfOneFile, "[{0} öñlý åpplîéš tö çömpŕéšš öf öñé fîlé]"),
+            (Message::OnlyCompressWithTables, "[{0} öñlý åpplîéš tö çömpŕéšš öf öñé fîlé wîth tåbléš]"),
+            (Message::OnlyCompressOrDecompress, "[{0} öñlý åpplîéš tö çömpŕéšš öf öñé fîlé åñd tö déçömpŕéšš]"),
+            (Message::OnlyFixedBlocks, "[{0} öñlý åpplîéš tö çömpŕéšš wîth fîxéd blöçkš, déçömpŕéšš åñd éxtŕåçt]"),
+            (Message::OnlyStatsOrCompress, "[{0} öñlý åpplîéš tö štåtš åñd çömpŕéšš]"),
+            (Message::ArchiveNeedsOutput, "[Çömpŕéššîñg šévéŕål fîléš ñéédš åñ --output åŕçhîvé]"),
+            (Message::AdaptiveNoSymbols, "[--adaptive ålwåýš çödéš býtéš åñd tåkéš ñö --symbols]"),
+            (Message::AdaptiveNoOptimal, "[--adaptive håš ñö blöçkš tö çhööšé åñd tåkéš ñö --optimal]"),
+            (Message::IfChangedNoRemove, "[--if-changed ñéédš thé îñpût tö çömpåŕé wîth ñéxt tîmé åñd tåkéš ñö --rm]"),
+            (Message::ArchivesNotPiped, "[Åŕçhîvéš åŕé fîléš, åñd çåññöt bé ŕéåd öŕ wŕîttéñ thŕöûgh -]"),
+            (Message::FilesNotPiped, "[--if-changed, --verify åñd --rm ñééd fîléš, ñöt -]"),
+            (Message::StatsNotPiped, "[štåtš ŕéådš îtš fîlé twîçé åñd çåññöt ŕéåd -]"),
+            (Message::CopyNoOutput, "[åŕçhîvé çöpý wŕîtéš îñtö îtš šéçöñd åŕçhîvé åñd tåkéš ñö --output]"),
+            (Message::CopyNeedsArguments, "[åŕçhîvé çöpý ñéédš thé åŕçhîvé tö çöpý fŕöm, thé öñé tö çöpý îñtö åñd éñtŕý ñåméš]"),
+            (Message::UnknownArchiveCommand, "[Ûñkñöwñ åŕçhîvé çömmåñd '{0}']"),
+            (Message::NoSuchEntry, "[Ñö éñtŕý ñåméd {0}]"),
+            (Message::StatsNoOutput, "[štåtš pŕîñtš tö thé téŕmîñål åñd tåkéš ñö öûtpût]"),
+            (Message::DryRunNoOptions, "[--dry-run wŕîtéš ñöthîñg, åñd öñlý tåkéš --symbols]"),
+            (Message::UseTableNoOptions, "[--use-table déçîdéš höw tö çödé, åñd tåkéš ñö öthéŕ çödîñg öptîöñš öŕ --stats]"),
+            (Message::MmapNotPiped, "[--mmap måpš å fîlé, åñd çåññöt måp -]"),
+            (Message::MmapNoProgress, "[--mmap ŕéådš ñöthîñg tö šhöw thé --progress öf]"),
+            (Message::MmapNotBuilt, "[--mmap ñéédš huffman-rs bûîlt wîth thé mmåp féåtûŕé]"),
+            (Message::HintArchive, "[{0} îš åñ åŕçhîvé; dîd ýöû méåñ `huffman-rs extract {0}`?]"),
+            (Message::HintCompress, "[{0} döéš ñöt štåŕt wîth HUFF; dîd ýöû méåñ `huffman-rs compress {0}`?]"),
+            (Message::HintOneFile, "[{0} höldš öñé fîlé; dîd ýöû méåñ `huffman-rs decompress {0}`?]"),
+            (Message::HintNewArchive, "[{0} döéš ñöt štåŕt wîth HUFA; dîd ýöû méåñ `huffman-rs compress --archive {0}`?]"),
+            (Message::HintNewer, "[{1} îš öldéŕ thåñ thé huffman-rs thåt wŕöté {0}]"),
+            (Message::HintCutShort, "[{0} wåš çût šhöŕt, péŕhåpš bý åñ îñtéŕŕûptéd çöpý; çömpŕéšš thé öŕîgîñål ågåîñ]"),
+            (Message::HintDamaged, "[{0} wåš dåmågéd åftéŕ ît wåš çömpŕéššéd; çömpŕéšš thé öŕîgîñål ågåîñ]"),
+            (Message::HintUseTable, "[déçömpŕéšš ît wîth --use-table åñd thé tåblé ît wåš çömpŕéššéd wîth]"),
+            (Message::HintOddBytes, "[--symbols û16lé ñéédš åñ évéñ ñûmbéŕ öf býtéš; léåvé ît öût tö çömpŕéšš býtéš]"),
+            (Message::HintDumpTable, "[wŕîté thé tåblé wîth `huffman-rs compress --dump-table {0} FILE`]"),
+            (Message::NotUtf8Name, "[Ñöt å UTF-8 fîlé ñåmé]"),
+            (Message::SameName, "[Åñöthéŕ fîlé îš ålšö ñåméd {0}]"),
+            (Message::RemovingOutput, "[Réfûšîñg tö ŕémövé thé öûtpût]"),
+            (Message::OutputIsInput, "[Réfûšîñg tö övéŕwŕîté thé îñpût {0}; gîvé åñöthéŕ öûtpût wîth -o]"),
+            (Message::RefusingToExtract, "[Réfûšîñg tö éxtŕåçt {0}]"),
+            (Message::InEntry, "[éñtŕý {0}]"),
+            (Message::VerifyMismatch, "[Döéš ñöt déçömpŕéšš tö {0}]"),
+            (Message::VersionFileFormat, "[fîlé föŕmåt:    wŕîtéš véŕšîöñ {0}, ŕéådš {1} tö {0}]"),
+            (Message::VersionArchiveFormat, "[åŕçhîvé föŕmåt: véŕšîöñ {0}]"),
+            (Message::VersionFeatures, "[féåtûŕéš:       {0}]"),
+            (Message::VersionTarget, "[tåŕgét:         {0} {1}]"),
+            (Message::VersionSimd, "[SIMD:           ñöñé ûšéd; thé CPU håš {0}]"),
+            (Message::VersionBlockSize, "[blöçk šîzé:     {0} šýmbölš]"),
+            (Message::VersionThreads, "[thŕéådš:        {0}, öñé péŕ CPU]"),
+            (Message::VersionCache, "[çåçhé:          {0}, öf åt möšt {1} býtéš]"),
+            (Message::VersionNoCache, "[çåçhé:          ñöñé, åš ñéîthéŕ $XDG_CONFIG_HOME ñöŕ $HOME îš šét]"),
+            (Message::Nothing, "[ñöñé]"),
+            (Message::Crashed, "[huffman-rs çŕåšhéd, whîçh îš å bûg: {0}; pléåšé ŕépöŕt ît wîth {1}]"),
+            (Message::CrashedWithoutReport, "[huffman-rs çŕåšhéd, whîçh îš å bûg: {0}; pléåšé ŕépöŕt ît wîth thîš]"),
+            (Message::OriginalSize, "[öŕîgîñål šîzé:       {0} býtéš]"),
+            (Message::CompressedSize, "[çömpŕéššéd šîzé:     {1}% öf thé öŕîgîñål, {0} býtéš]"),
+            (Message::PredictedSize, "[pŕédîçtéd šîzé:      {0} býtéš ({1}% öf thé öŕîgîñål)]"),
+            (Message::Entropy, "[éñtŕöpý:             {0} bîtš péŕ šýmböl]"),
+            (Message::AverageCodeLength, "[åvéŕågé çödé léñgth: {0} bîtš péŕ šýmböl]"),
+            (Message::HeaderOverhead, "[héådéŕ övéŕhéåd:     {0} býtéš ({1}% öf thé öûtpût)]"),
+            (Message::Block, "[blöçk {0}: {1} šýmbölš, {2} îñ thé tåblé{3}, štöŕéd åš {4}; {5} héådéŕ býtéš, {6} éñçödéd býtéš]"),
+            (Message::BlockEscape, "[, thé ŕéšt éšçåpéd åš {0} bît lîtéŕålš]"),
+            (Message::StoredAsCodeLengths, "[çödé léñgthš]"),
+            (Message::StoredAsTopology, "[töpölögý]"),
+            (Message::StoredAsSharedTable, "[å šhåŕéd tåblé]"),
+            (Message::ProgressOfTotal, "[ / {0} ({1}%)]"),
+            (Message::ProgressLeft, "[, {1}š léft åt {0}/š]"),
+            (Message::ProgressRate, "[, {0}/š]"),
+        ],
+    };
+
+    /// The indices of the arguments `text` shows.
+    fn arguments(text: &str) -> Vec<usize> {
+        let markers: Vec<String> = (0..10).map(|index| format!("\u{0}{}\u{0}", index)).collect();
+        let args: Vec<&dyn Display> = markers.iter().map(|marker| marker as &dyn Display).collect();
+        let filled = fill(text, &args);
+        (0..markers.len()).filter(|&index| filled.contains(&markers[index])).collect()
+    }
+
+    #[test]
+    fn test_english() {
+        assert_eq!(text!(NeedsFile, "compress"), "compress needs a file");
+        assert_eq!(text!(HintArchive, "a.huf"), "a.huf is an archive; did you mean `huffman-rs extract a.huf`?");
+        let size = text!(CompressedSize, 3, format!("{:.1}", 37.5));
+        assert_eq!(size, "compressed size:     3 bytes (37.5% of the original)");
+    }
+
+    #[test]
+    fn test_translation() {
+        let catalog = Catalog::new("xx_ZZ.UTF-8", TEST_TRANSLATIONS);
+        assert_eq!(catalog.text(Message::NeedsFile, &[&"stats"]), "stats braucht eine Datei");
+        // Left out of the translation.
+        assert_eq!(catalog.text(Message::NoCommand, &[]), "No command given");
+
+        let catalog = Catalog::new("xx_YY", TEST_TRANSLATIONS);
+        assert_eq!(catalog.text(Message::SameName, &[&"a"]), "a und a");
+        assert_eq!(catalog.text(Message::NeedsFile, &[&"stats"]), "stats needs a file");
+
+        let catalog = Catalog::new("C", TEST_TRANSLATIONS);
+        assert_eq!(catalog.text(Message::NeedsFile, &[&"stats"]), "stats needs a file");
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("{} of {}", &[&1, &2]), "1 of 2");
+        assert_eq!(fill("{1} of {0}", &[&1, &2]), "2 of 1");
+        assert_eq!(fill("{} and {}", &[&1]), "1 and ");
+        assert_eq!(fill("{x} {", &[&1]), "{x} {");
+    }
+
+    #[test]
+    fn test_pseudo_translation() {
+        let catalog = Catalog::new("en_XA.UTF-8", std::slice::from_ref(&PSEUDO));
+        assert_eq!(catalog.text(Message::NeedsFile, &[&"stats"]), "[stats ñéédš å fîlé]");
+        assert_eq!(catalog.text(Message::ProgressLeft, &[&"3 KiB", &7]), "[, 7š léft åt 3 KiB/š]");
+        let format = catalog.text(Message::VersionFileFormat, &[&4, &1]);
+        assert_eq!(format, "[fîlé föŕmåt:    wŕîtéš véŕšîöñ 4, ŕéådš 1 tö 4]");
+        assert_eq!(catalog.text(Message::Usage, &[]), USAGE);
+    }
+
+    #[test]
+    fn test_arguments() {
+        assert_eq!(arguments(english(Message::Block)), [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(arguments(english(Message::HintArchive)), [0]);
+        assert_eq!(arguments("{1} of {1}"), [1]);
+        assert_eq!(arguments("{x}"), [] as [usize; 0]);
+    }
+
+    #[test]
+    fn test_translations_are_complete() {
+        for translation in TRANSLATIONS.iter().chain([&PSEUDO]) {
+            for &(message, text) in translation.texts {
+                let language = translation.language;
+                assert_eq!(arguments(text), arguments(english(message)), "{:?} in {}", message, language);
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Showing how far along reading the input is, on standard error.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::messages::text;
+
+/// How often the line is redrawn at most.
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// A line of progress through an input of `total` bytes, if known, redrawn as
+/// it is updated and ended when dropped. Nothing is drawn until a moment has
+/// passed, when there is a rate to tell.
+#[derive(Debug)]
+pub struct Meter {
+    total: Option<u64>,
+    start: Instant,
+    drawn: Option<Instant>,
+    done: u64,
+}
+
+impl Meter {
+    pub fn new(total: Option<u64>) -> Meter {
+        Meter { total, start: Instant::now(), drawn: None, done: 0 }
+    }
+
+    /// Records that `done` bytes have been processed.
+    pub fn update(&mut self, done: u64) {
+        self.done = done;
+        let now = Instant::now();
+        if now - self.drawn.unwrap_or(self.start) >= INTERVAL {
+            self.drawn = Some(now);
+            self.draw(now - self.start);
+        }
+    }
+
+    fn draw(&self, elapsed: Duration) {
+        eprint!("\r{}\x1b[K", line(self.done, self.total, elapsed));
+        let _ = std::io::stderr().flush();
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        self.draw(self.start.elapsed());
+        eprintln!();
+    }
+}
+
+/// The line for `done` of `total` bytes after `elapsed`.
+fn line(done: u64, total: Option<u64>, elapsed: Duration) -> String {
+    let rate = done as f64 / elapsed.as_secs_f64().max(1e-3);
+    let mut line = bytes(done as f64);
+    if let Some(total) = total.filter(|&total| total > 0) {
+        let left = total.saturating_sub(done) as f64 / rate.max(1.0);
+        line += &text!(ProgressOfTotal, bytes(total as f64), done.min(total) * 100 / total);
+        line += &text!(ProgressLeft, bytes(rate), left.ceil() as u64);
+    } else {
+        line += &text!(ProgressRate, bytes(rate));
+    }
+
+    line
+}
+
+/// `count` bytes in the largest unit that keeps them at least 1.
+fn bytes(count: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut count = count;
+    let mut unit = 0;
+    while count >= 1024.0 && unit < units.len() - 1 {
+        count /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", count as u64)
+    } else {
+        format!("{:.1} {}", count, units[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let second = Duration::from_secs(1);
+        assert_eq!(line(512, Some(2048), second), "512 B / 2.0 KiB (25%), 512 B/s, 3s left");
+        assert_eq!(line(3 << 20, None, 2 * second), "3.0 MiB, 1.5 MiB/s");
+        assert_eq!(line(10, Some(10), second), "10 B / 10 B (100%), 10 B/s, 0s left");
+        assert_eq!(bytes((5u64 << 40) as f64 * 1024.0), "5120.0 TiB");
+    }
+}
@@ -0,0 +1,151 @@
+//! Memory mapping of the input, for `--mmap`.
+//!
+//! This and the call of [`Mapped::new`] that reads the input are the only
+//! `unsafe` code of the tool, and are only built with the `mmap` feature; the
+//! library has none. It calls the C library's `mmap`
+//! directly on 64-bit Unix, and elsewhere falls back to reading the file.
+
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+
+/// The contents of a file, mapped read-only into memory where the platform
+/// allows.
+///
+/// The bytes are the file's own pages rather than a copy, so they are only
+/// what they seem while no one changes the file; see [`Mapped::new`].
+#[derive(Debug)]
+pub struct Mapped(sys::Mapping);
+
+impl Mapped {
+    /// Maps the whole of `file`, which may be closed afterwards.
+    ///
+    /// # Safety
+    ///
+    /// The file must be neither written nor truncated, by this or any other
+    /// process, until the mapping is dropped. A write would change bytes that
+    /// are borrowed as immutable, and a read past a new end of the file raises
+    /// `SIGBUS`. Nothing here can prevent either, so the caller must know.
+    pub unsafe fn new(file: &File) -> io::Result<Mapped> {
+        let length = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "File too large to map"))?;
+
+        sys::Mapping::new(file, length).map(Mapped)
+    }
+}
+
+impl Deref for Mapped {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0.bytes()
+    }
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+mod sys {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    const PROT_READ: i32 = 1;
+    const MAP_PRIVATE: i32 = 2;
+
+    extern "C" {
+        fn mmap(address: *mut c_void, length: usize, protection: i32, flags: i32, fd: i32, offset: i64) -> *mut c_void;
+        fn munmap(address: *mut c_void, length: usize) -> i32;
+    }
+
+    #[derive(Debug)]
+    pub struct Mapping {
+        /// Null for an empty file, which cannot be mapped.
+        address: *mut c_void,
+        length: usize,
+    }
+
+    impl Mapping {
+        pub fn new(file: &File, length: usize) -> io::Result<Mapping> {
+            if length == 0 {
+                return Ok(Mapping { address: std::ptr::null_mut(), length });
+            }
+
+            // SAFETY: a new private mapping aliases no memory of the program.
+            let address = unsafe { mmap(std::ptr::null_mut(), length, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+            // MAP_FAILED.
+            if address as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Mapping { address, length })
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            if self.address.is_null() {
+                return &[];
+            }
+
+            // SAFETY: the mapping is `length` readable bytes until dropped,
+            // which nothing changes as the caller of `Mapped::new` promised.
+            unsafe { std::slice::from_raw_parts(self.address as *const u8, self.length) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            if !self.address.is_null() {
+                // SAFETY: unmaps what `new` mapped, which `bytes` no longer
+                // borrows once the mapping is dropped.
+                unsafe { munmap(self.address, self.length) };
+            }
+        }
+    }
+}
+
+#[cfg(not(all(unix, target_pointer_width = "64")))]
+mod sys {
+    use std::fs::File;
+    use std::io::{self, Read};
+
+    #[derive(Debug)]
+    pub struct Mapping(Vec<u8>);
+
+    impl Mapping {
+        pub fn new(mut file: &File, length: usize) -> io::Result<Mapping> {
+            let mut bytes = Vec::with_capacity(length);
+            file.read_to_end(&mut bytes)?;
+            Ok(Mapping(bytes))
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            &self.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapped(name: &str, contents: &[u8]) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("huffman-rs-mmap-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        // SAFETY: the file is this test's own, and is removed unchanged.
+        let mapped = unsafe { Mapped::new(&File::open(&path).unwrap()) }.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Still readable once the file is closed and removed.
+        mapped.to_vec()
+    }
+
+    #[test]
+    fn test_mapped() {
+        let contents: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        assert_eq!(mapped("contents", &contents), contents);
+    }
+
+    #[test]
+    fn test_mapped_empty() {
+        assert_eq!(mapped("empty", b""), b"");
+    }
+}
@@ -0,0 +1,188 @@
+//! Chooses how to store each block so it takes as few bytes as possible, and
+//! optionally where blocks start and end.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::compress::DEFAULT_BLOCK_SIZE;
+use crate::escape::{EscapeTable, Escaped};
+use crate::format::{BlockHeader, Escape, SymbolKind, Table, TableEncoding};
+use crate::frequency::count_symbols;
+use crate::symbol::Symbol;
+
+/// The distance between the boundaries [`split_blocks`] considers. Blocks are
+/// made of whole steps, except the last.
+const SPLIT_STEP: usize = 1 << 14;
+
+/// How a block is coded and its table stored.
+#[derive(Debug, Clone)]
+pub(crate) struct BlockPlan<S> {
+    pub(crate) table: EscapeTable<S>,
+    pub(crate) encoding: TableEncoding,
+}
+
+impl<S: Symbol> BlockPlan<S> {
+    /// Weighs each of the [`EscapeTable::candidates`] for `frequencies` in
+    /// each [`TableEncoding`], and picks the one giving the smallest block.
+    ///
+    /// `frequencies` must not be empty.
+    pub(crate) fn choose(kind: SymbolKind, frequencies: &HashMap<S, usize>) -> BlockPlan<S> {
+        BlockPlan::choose_with_size(kind, frequencies).1
+    }
+
+    /// Like [`BlockPlan::choose`], also returning the size of the block in
+    /// bytes, header included.
+    pub(crate) fn choose_with_size(kind: SymbolKind, frequencies: &HashMap<S, usize>) -> (u64, BlockPlan<S>) {
+        let length = frequencies.values().sum::<usize>() as u64;
+
+        let mut best: Option<(u64, BlockPlan<S>)> = None;
+        for table in EscapeTable::candidates(frequencies) {
+            let encoded_size = table.encoded_bits(frequencies).div_ceil(8);
+
+            let mut plan = BlockPlan { table, encoding: TableEncoding::CodeLengths };
+            let mut header = plan.header(length, encoded_size);
+            let lengths_size = header.size(kind);
+            header.encoding = TableEncoding::Topology;
+            if header.size(kind) < lengths_size {
+                plan.encoding = TableEncoding::Topology;
+            }
+
+            let size = header.size(kind).min(lengths_size) + encoded_size;
+            if best.as_ref().is_none_or(|(best_size, _)| size < *best_size) {
+                best = Some((size, plan));
+            }
+        }
+
+        best.expect("at least one candidate")
+    }
+
+    /// The header of a block of `length` symbols coded with this plan into
+    /// `encoded_size` bytes.
+    pub(crate) fn header(&self, length: u64, encoded_size: u64) -> BlockHeader {
+        let mut lengths = HashMap::new();
+        let mut escape = None;
+        for (symbol, code_length) in self.table.codes.code_lengths() {
+            match symbol {
+                Escaped::Symbol(symbol) => {
+                    lengths.insert(symbol.to_u32(), code_length);
+                }
+                Escaped::Escape => escape = Some(Escape { code_length, literal_width: self.table.literal_width }),
+            }
+        }
+
+        BlockHeader {
+            length,
+            table: Table::CodeLengths(lengths),
+            encoding: self.encoding,
+            escape,
+            encoded_size: Some(encoded_size),
+        }
+    }
+}
+
+/// Splits `symbols` into the blocks that take the fewest bytes in total, each
+/// of at most [`DEFAULT_BLOCK_SIZE`] symbols.
+///
+/// Only boundaries every [`SPLIT_STEP`] symbols are considered, and each
+/// candidate block is sized with [`BlockPlan::choose`], so this is far slower
+/// than fixed-size blocks.
+pub(crate) fn split_blocks<S: Symbol>(kind: SymbolKind, symbols: &[S]) -> Vec<Range<usize>> {
+    let steps: Vec<HashMap<S, usize>> =
+        symbols.chunks(SPLIT_STEP).map(|step| count_symbols(step.iter().copied())).collect();
+    let max_steps = DEFAULT_BLOCK_SIZE / SPLIT_STEP;
+
+    // The smallest size of the first `end` steps, and where its last block
+    // starts.
+    let mut best: Vec<(u64, usize)> = vec![(u64::MAX, 0); steps.len() + 1];
+    best[0] = (0, 0);
+    for start in 0..steps.len() {
+        let mut frequencies = HashMap::new();
+        for end in start + 1..=steps.len().min(start + max_steps) {
+            for (&symbol, &frequency) in &steps[end - 1] {
+                *frequencies.entry(symbol).or_insert(0) += frequency;
+            }
+
+            let size = best[start].0 + BlockPlan::choose_with_size(kind, &frequencies).0;
+            if size < best[end].0 {
+                best[end] = (size, start);
+            }
+        }
+    }
+
+    let mut blocks = Vec::new();
+    let mut end = steps.len();
+    while end > 0 {
+        let start = best[end].1;
+        blocks.push(start * SPLIT_STEP..(end * SPLIT_STEP).min(symbols.len()));
+        end = start;
+    }
+    blocks.reverse();
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_size<S: Symbol>(kind: SymbolKind, plan: &BlockPlan<S>, frequencies: &HashMap<S, usize>) -> u64 {
+        let encoded_size = plan.table.encoded_bits(frequencies).div_ceil(8);
+        plan.header(0, encoded_size).size(kind) + encoded_size
+    }
+
+    fn assert_smallest<S: Symbol>(kind: SymbolKind, input: impl IntoIterator<Item = S>) {
+        let frequencies = count_symbols(input);
+        let size = block_size(kind, &BlockPlan::choose(kind, &frequencies), &frequencies);
+
+        for table in EscapeTable::candidates(&frequencies) {
+            for encoding in [TableEncoding::CodeLengths, TableEncoding::Topology] {
+                let other = BlockPlan { table: table.clone(), encoding };
+                assert!(size <= block_size(kind, &other, &frequencies));
+            }
+        }
+    }
+
+    #[test]
+    fn test_smallest_plan() {
+        assert_smallest(SymbolKind::Chars, "aaaabbbbccccdddd".chars().chain('\u{1F600}'..'\u{1F640}'));
+        assert_smallest(SymbolKind::Bytes, b"abbcccdddd".iter().copied());
+        assert_smallest(SymbolKind::U16, (0..1000u16).chain(0..1000));
+    }
+
+    #[test]
+    fn test_rare_symbols_escaped() {
+        let input = "the quick brown fox ".repeat(50).chars().chain('\u{1F600}'..'\u{1F640}').collect::<Vec<char>>();
+        let plan = BlockPlan::choose(SymbolKind::Chars, &count_symbols(input));
+
+        assert!(plan.table.codes.get(Escaped::Escape).is_some());
+        assert_eq!(plan.table.codes.get(Escaped::Symbol('\u{1F600}')), None);
+        assert!(plan.table.codes.get(Escaped::Symbol('q')).is_some());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "too slow under Miri")]
+    fn test_split_blocks() {
+        assert!(split_blocks::<u8>(SymbolKind::Bytes, &[]).is_empty());
+
+        // Text, then bytes of no use to the text's table.
+        let mut input = b"quick brown fox ".repeat(2 * SPLIT_STEP / 16);
+        input.extend((0..3 * SPLIT_STEP as u32).map(|i| 128 + (i * i % 127) as u8));
+        input.extend(b"quick brown fox ".repeat(SPLIT_STEP / 16));
+
+        let blocks = split_blocks(SymbolKind::Bytes, &input);
+        assert_eq!(blocks.first().unwrap().start, 0);
+        assert_eq!(blocks.last().unwrap().end, input.len());
+        assert!(blocks.windows(2).all(|pair| pair[0].end == pair[1].start));
+        assert!(blocks.iter().any(|block| block.start == 2 * SPLIT_STEP));
+        assert!(blocks.iter().any(|block| block.start == 5 * SPLIT_STEP));
+    }
+
+    #[test]
+    fn test_large_table_as_topology() {
+        let input: Vec<u8> = (0..=255).flat_map(|byte| std::iter::repeat_n(byte, 20)).collect();
+        let plan = BlockPlan::choose(SymbolKind::Bytes, &count_symbols(input));
+
+        assert_eq!(plan.encoding, TableEncoding::Topology);
+        assert_eq!(plan.table.codes.len(), 256);
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// Wraps a reader or writer, calling a callback with the total number of bytes
+/// moved through it after each read or write, to report progress.
+///
+/// Wrap the input of a [`HuffmanEncoder`](crate::HuffmanEncoder) or
+/// [`HuffmanDecoder`](crate::HuffmanDecoder), whose size is usually known, to
+/// follow how far along it is:
+///
+/// ```
+/// use std::io::Read;
+///
+/// let compressed = huffman::compress("abbcccdddd");
+/// let mut seen = 0;
+/// let reader = huffman::Progress::new(compressed.as_slice(), |total| seen = total);
+///
+/// huffman::HuffmanDecoder::new(reader).read_to_end(&mut Vec::new()).unwrap();
+/// assert_eq!(seen, compressed.len() as u64);
+/// ```
+pub struct Progress<T, F: FnMut(u64)> {
+    inner: T,
+    callback: F,
+    total: u64,
+}
+
+impl<T, F: FnMut(u64)> Progress<T, F> {
+    pub fn new(inner: T, callback: F) -> Progress<T, F> {
+        Progress { inner, callback, total: 0 }
+    }
+
+    /// The number of bytes read or written so far.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Reading or writing through the inner value directly is not counted.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn advance(&mut self, length: usize) {
+        self.total += length as u64;
+        (self.callback)(self.total);
+    }
+}
+
+impl<T: fmt::Debug, F: FnMut(u64)> fmt::Debug for Progress<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Progress").field("inner", &self.inner).field("total", &self.total).finish_non_exhaustive()
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for Progress<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = self.inner.read(buf)?;
+        self.advance(length);
+
+        Ok(length)
+    }
+}
+
+impl<W: Write, F: FnMut(u64)> Write for Progress<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = self.inner.write(buf)?;
+        self.advance(length);
+
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HuffmanEncoder;
+
+    #[test]
+    fn test_progress() {
+        let mut totals = Vec::new();
+        let mut encoder = HuffmanEncoder::with_block_size(Progress::new(Vec::new(), |total| totals.push(total)), 4);
+        encoder.write_all(b"abbcccdddd").unwrap();
+        let writer = encoder.finish().unwrap();
+        assert_eq!(writer.total(), writer.get_ref().len() as u64);
+        drop(writer);
+
+        assert!(totals.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(totals.len() > 3);
+    }
+}
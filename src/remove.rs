@@ -0,0 +1,160 @@
+//! Removing sources once their compressed output is safely on disk.
+//!
+//! A crash between removing a source and its output reaching the disk would
+//! lose the data, so the output, already checked and renamed into place, and
+//! the directory entry naming it are synced first; only then are the sources
+//! removed. The steps go through [`Disk`] so tests can fail them.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use huffman::HuffmanError;
+
+use crate::messages::text;
+use crate::{at, same_file, Failure};
+
+/// The file system operations [`remove_sources`] needs.
+pub trait Disk {
+    /// Waits until the contents of the file at `path` are on disk.
+    fn sync_file(&self, path: &Path) -> io::Result<()>;
+    /// Waits until the entries of the directory at `path` are on disk.
+    fn sync_dir(&self, path: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+}
+
+/// The real file system.
+#[derive(Debug)]
+pub struct RealDisk;
+
+impl Disk for RealDisk {
+    fn sync_file(&self, path: &Path) -> io::Result<()> {
+        File::open(path)?.sync_all()
+    }
+
+    #[cfg(unix)]
+    fn sync_dir(&self, path: &Path) -> io::Result<()> {
+        File::open(path)?.sync_all()
+    }
+
+    /// Directories cannot be opened to sync elsewhere; their entries are
+    /// written through by the file system.
+    #[cfg(not(unix))]
+    fn sync_dir(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+}
+
+/// Syncs `output` and its directory, and then removes `inputs`. Nothing is
+/// removed if any step before fails, or if one of `inputs` is `output`.
+pub fn remove_sources(disk: &impl Disk, inputs: &[PathBuf], output: &Path) -> Result<(), Failure> {
+    if let Some(input) = inputs.iter().find(|&input| input == output || same_file(input, output)) {
+        return Err(at(input)(HuffmanError::InvalidInput(text!(RemovingOutput))));
+    }
+    disk.sync_file(output).map_err(at(output))?;
+    let dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    disk.sync_dir(dir).map_err(at(dir))?;
+
+    for input in inputs {
+        disk.remove_file(input).map_err(at(input))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Records the operations asked of it, and fails the one named.
+    #[derive(Default)]
+    struct FaultyDisk {
+        fail: Option<&'static str>,
+        log: RefCell<Vec<String>>,
+    }
+
+    impl FaultyDisk {
+        fn run(&self, operation: &'static str, path: &Path) -> io::Result<()> {
+            self.log.borrow_mut().push(format!("{} {}", operation, path.display()));
+            match self.fail {
+                Some(fail) if fail == operation => Err(io::Error::other("injected")),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Disk for FaultyDisk {
+        fn sync_file(&self, path: &Path) -> io::Result<()> {
+            self.run("sync_file", path)
+        }
+
+        fn sync_dir(&self, path: &Path) -> io::Result<()> {
+            self.run("sync_dir", path)
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.run("remove_file", path)
+        }
+    }
+
+    fn inputs() -> Vec<PathBuf> {
+        vec!["a.txt".into(), "b.txt".into()]
+    }
+
+    #[test]
+    fn test_order() {
+        let disk = FaultyDisk::default();
+        remove_sources(&disk, &inputs(), Path::new("out/all.huf")).unwrap();
+        let log = ["sync_file out/all.huf", "sync_dir out", "remove_file a.txt", "remove_file b.txt"];
+        assert_eq!(*disk.log.borrow(), log);
+
+        let disk = FaultyDisk::default();
+        remove_sources(&disk, &inputs(), Path::new("all.huf")).unwrap();
+        assert_eq!(disk.log.borrow()[1], "sync_dir .");
+    }
+
+    #[test]
+    fn test_failures_keep_sources() {
+        for fail in ["sync_file", "sync_dir"] {
+            let disk = FaultyDisk { fail: Some(fail), ..FaultyDisk::default() };
+            let failure = remove_sources(&disk, &inputs(), Path::new("all.huf")).unwrap_err();
+            assert!(matches!(failure.error, HuffmanError::Io(_)));
+            assert!(!disk.log.borrow().iter().any(|operation| operation.starts_with("remove_file")));
+        }
+
+        // The output is never removed, whatever it is called.
+        let disk = FaultyDisk::default();
+        assert!(remove_sources(&disk, &inputs(), Path::new("b.txt")).is_err());
+        assert!(disk.log.borrow().is_empty());
+
+        // A failed removal stops before the next source.
+        let disk = FaultyDisk { fail: Some("remove_file"), ..FaultyDisk::default() };
+        let failure = remove_sources(&disk, &inputs(), Path::new("all.huf")).unwrap_err();
+        assert_eq!(failure.path, Path::new("a.txt"));
+        assert_eq!(disk.log.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_real_disk() {
+        let dir = std::env::temp_dir().join(format!("huffman-rs-remove-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("a.txt"), dir.join("a.txt.huf"));
+        fs::write(&input, "abbcccdddd").unwrap();
+        fs::write(&output, huffman::compress("abbcccdddd")).unwrap();
+
+        let same = dir.join(".").join("a.txt.huf");
+        assert!(remove_sources(&RealDisk, std::slice::from_ref(&same), &output).is_err());
+        remove_sources(&RealDisk, std::slice::from_ref(&input), &output).unwrap();
+        assert!(!input.exists() && output.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,435 @@
+//! Tables shared by many streams, so that each need not store its own.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::compress::{block_table, DEFAULT_BLOCK_SIZE};
+use crate::crc::{crc32, Crc32};
+use crate::error::{HuffmanError, Result};
+use crate::escape::Escaped;
+use crate::format::{self, BlockHeader, Escape, Header, SymbolKind, Table, TableEncoding};
+use crate::frequency::count_symbols;
+use crate::symbol::Symbol;
+use crate::{CodeTable, HuffmanDecoder, Tree};
+
+/// Codes built once from the frequencies of a corpus, such as a sample of many
+/// small files alike, that blocks refer to by id instead of storing a table of
+/// their own. A table can take more bytes than the bits of a small file, so
+/// sharing one makes such files smaller.
+///
+/// Streams compressed with a table can only be decompressed with it, by
+/// [`SharedTable::decompress`] or [`HuffmanDecoder::with_table`]. Symbols the
+/// corpus lacks are escaped, so any input of the table's kind compresses, if
+/// less well.
+///
+/// ```
+/// use huffman::format::SymbolKind;
+///
+/// let table = huffman::SharedTable::from_input(SymbolKind::Chars, b"the quick brown fox")?;
+/// let compressed = table.compress(b"the lazy dog")?;
+///
+/// assert_eq!(table.decompress(&compressed)?, b"the lazy dog");
+/// assert!(huffman::decompress(&compressed).is_err());
+/// # Ok::<(), huffman::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedTable {
+    kind: SymbolKind,
+    frequencies: HashMap<u32, usize>,
+    /// The code length of each symbol of `frequencies`, built from them.
+    lengths: HashMap<u32, u8>,
+    escape: Escape,
+    id: u32,
+}
+
+impl SharedTable {
+    /// Builds the codes for symbols of `kind` occurring with `frequencies`,
+    /// keyed by their `u32` value (see [`Symbol`](crate::Symbol)), and an
+    /// escape for all others.
+    ///
+    /// Fails with [`HuffmanError::InvalidTable`] if a value is not a symbol of
+    /// `kind` or a frequency is 0.
+    pub fn new(kind: SymbolKind, frequencies: HashMap<u32, usize>) -> Result<SharedTable> {
+        if frequencies.values().any(|&frequency| frequency == 0) {
+            return Err(invalid("a frequency is 0"));
+        }
+
+        let (lengths, escape) = match kind {
+            SymbolKind::Chars => build::<char>(&frequencies, 21)?,
+            SymbolKind::Bytes => build::<u8>(&frequencies, 8)?,
+            SymbolKind::U16 => build::<u16>(&frequencies, 16)?,
+        };
+        let id = table_id(kind, &frequencies);
+
+        Ok(SharedTable { kind, frequencies, lengths, escape, id })
+    }
+
+    /// Builds the table from the frequencies of `input`, given as
+    /// [`decompress_bytes`](crate::decompress_bytes) returns it.
+    ///
+    /// Fails like [`SharedTable::compress`].
+    pub fn from_input(kind: SymbolKind, input: &[u8]) -> Result<SharedTable> {
+        let frequencies = match kind {
+            SymbolKind::Chars => to_values(count_symbols(text(input)?.chars())),
+            SymbolKind::Bytes => to_values(count_symbols(input.iter().copied())),
+            SymbolKind::U16 => to_values(count_symbols(pairs(input)?)),
+        };
+
+        SharedTable::new(kind, frequencies)
+    }
+
+    /// The kind of symbols the table codes.
+    pub fn kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    pub fn frequencies(&self) -> &HashMap<u32, usize> {
+        &self.frequencies
+    }
+
+    /// The CRC-32 of the kind and frequencies, which blocks store to refer to
+    /// the table.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Compresses `input`, given as [`decompress_bytes`](crate::decompress_bytes)
+    /// returns it, into blocks of [`DEFAULT_BLOCK_SIZE`] symbols coded with
+    /// this table.
+    ///
+    /// Fails with [`HuffmanError::NotUtf8`] if characters are coded and `input`
+    /// is not text, and with [`HuffmanError::OddLength`] if `u16`s are and its
+    /// length is odd.
+    pub fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        match self.kind {
+            SymbolKind::Chars => self.compress_symbols(&text(input)?.chars().collect::<Vec<char>>(), input),
+            SymbolKind::Bytes => self.compress_symbols(input, input),
+            SymbolKind::U16 => self.compress_symbols(&pairs(input)?, input),
+        }
+    }
+
+    fn compress_symbols<S: Symbol>(&self, symbols: &[S], input: &[u8]) -> Result<Vec<u8>> {
+        let table = block_table::<S>(&self.lengths, Some(self.escape))?;
+
+        let mut output = Vec::new();
+        Header::new(self.kind).write(&mut output)?;
+        for block in symbols.chunks(DEFAULT_BLOCK_SIZE) {
+            let encoded = table.encode(block);
+            let header = BlockHeader {
+                length: block.len() as u64,
+                table: Table::Shared(self.id),
+                encoding: TableEncoding::Shared,
+                escape: None,
+                encoded_size: Some(encoded.len() as u64),
+            };
+            header.write(self.kind, &mut output)?;
+            output.extend_from_slice(&encoded);
+        }
+        BlockHeader::write_end(&mut output)?;
+        format::write_checksum(&mut output, crc32(input))?;
+
+        Ok(output)
+    }
+
+    /// Reverses [`SharedTable::compress`].
+    ///
+    /// Fails like [`decompress_bytes`](crate::decompress_bytes), and with
+    /// [`HuffmanError::MissingTable`] if `compressed` was coded with another
+    /// table.
+    pub fn decompress(&self, compressed: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::new();
+        HuffmanDecoder::new(compressed).with_table(self.clone()).read_to_end(&mut output)?;
+
+        Ok(output)
+    }
+
+    /// `block` with the codes of this table in place of a reference to it.
+    ///
+    /// Fails with [`HuffmanError::MissingTable`] if it refers to another table.
+    pub(crate) fn expand(&self, block: BlockHeader) -> Result<BlockHeader> {
+        match block.table {
+            Table::Shared(id) if id == self.id => {
+                Ok(BlockHeader { table: Table::CodeLengths(self.lengths.clone()), escape: Some(self.escape), ..block })
+            }
+            Table::Shared(_) => Err(HuffmanError::MissingTable),
+            _ => Ok(block),
+        }
+    }
+
+    /// The kind and frequencies as JSON, which [`SharedTable::from_json`]
+    /// reads back:
+    ///
+    /// ```json
+    /// {
+    ///   "kind": "bytes",
+    ///   "frequencies": {
+    ///     "97": 1,
+    ///     "98": 2
+    ///   }
+    /// }
+    /// ```
+    ///
+    /// The kind is `chars`, `bytes` or `u16`, and symbols are keyed by their
+    /// `u32` value in ascending order.
+    pub fn to_json(&self) -> String {
+        let kind = match self.kind {
+            SymbolKind::Chars => "chars",
+            SymbolKind::Bytes => "bytes",
+            SymbolKind::U16 => "u16",
+        };
+        let mut frequencies: Vec<(&u32, &usize)> = self.frequencies.iter().collect();
+        frequencies.sort_unstable();
+
+        let entries: Vec<String> =
+            frequencies.iter().map(|(symbol, frequency)| format!("    \"{}\": {}", symbol, frequency)).collect();
+        format!("{{\n  \"kind\": \"{}\",\n  \"frequencies\": {{\n{}\n  }}\n}}\n", kind, entries.join(",\n"))
+    }
+
+    /// Reads a table written by [`SharedTable::to_json`], with any spacing.
+    ///
+    /// Fails with [`HuffmanError::InvalidTable`] if `json` is not such a
+    /// table, or like [`SharedTable::new`].
+    pub fn from_json(json: &str) -> Result<SharedTable> {
+        let mut parser = Parser { text: json, position: 0 };
+        let (mut kind, mut frequencies) = (None, None);
+        parser.object(|parser, key| {
+            match key {
+                "kind" => {
+                    kind = Some(match parser.string()? {
+                        "chars" => SymbolKind::Chars,
+                        "bytes" => SymbolKind::Bytes,
+                        "u16" => SymbolKind::U16,
+                        kind => return Err(invalid(&format!("unknown kind '{}'", kind))),
+                    });
+                }
+                "frequencies" => {
+                    let mut table = HashMap::new();
+                    parser.object(|parser, symbol| {
+                        let symbol = symbol.parse::<u32>().map_err(|_| invalid(&format!("'{}' is not a symbol", symbol)))?;
+                        if table.insert(symbol, parser.number()?).is_some() {
+                            return Err(invalid(&format!("{} is repeated", symbol)));
+                        }
+                        Ok(())
+                    })?;
+                    frequencies = Some(table);
+                }
+                key => return Err(invalid(&format!("unknown field '{}'", key))),
+            }
+            Ok(())
+        })?;
+        parser.end()?;
+
+        SharedTable::new(kind.ok_or_else(|| invalid("no kind"))?, frequencies.ok_or_else(|| invalid("no frequencies"))?)
+    }
+}
+
+/// The code lengths for the symbols of `frequencies` and an escape for the
+/// rest, which is as rare as can be, followed by literals of `literal_width`
+/// bits.
+fn build<S: Symbol>(frequencies: &HashMap<u32, usize>, literal_width: u8) -> Result<(HashMap<u32, u8>, Escape)> {
+    let mut weights = HashMap::new();
+    for (&value, &frequency) in frequencies {
+        let symbol = S::from_u32(value).ok_or_else(|| invalid(&format!("{} is not a symbol of the kind", value)))?;
+        weights.insert(Escaped::Symbol(symbol), frequency);
+    }
+    weights.insert(Escaped::Escape, 1);
+
+    let mut lengths = HashMap::new();
+    let mut escape = Escape { code_length: 0, literal_width };
+    for (symbol, length) in CodeTable::from_tree(&Tree::from_frequencies(&weights)?).code_lengths() {
+        match symbol {
+            Escaped::Symbol(symbol) => {
+                lengths.insert(symbol.to_u32(), length);
+            }
+            Escaped::Escape => escape.code_length = length,
+        }
+    }
+
+    Ok((lengths, escape))
+}
+
+fn table_id(kind: SymbolKind, frequencies: &HashMap<u32, usize>) -> u32 {
+    let mut entries: Vec<(&u32, &usize)> = frequencies.iter().collect();
+    entries.sort_unstable();
+
+    let mut crc = Crc32::new();
+    crc.update(&[format::kind_to_u8(kind)]);
+    for (symbol, &frequency) in entries {
+        crc.update(&symbol.to_le_bytes());
+        crc.update(&(frequency as u64).to_le_bytes());
+    }
+
+    crc.finish()
+}
+
+fn to_values<S: Symbol>(frequencies: HashMap<S, usize>) -> HashMap<u32, usize> {
+    frequencies.into_iter().map(|(symbol, frequency)| (symbol.to_u32(), frequency)).collect()
+}
+
+fn text(input: &[u8]) -> Result<&str> {
+    std::str::from_utf8(input).map_err(|_| HuffmanError::NotUtf8)
+}
+
+fn pairs(input: &[u8]) -> Result<Vec<u16>> {
+    if !input.len().is_multiple_of(2) {
+        return Err(HuffmanError::OddLength);
+    }
+
+    Ok(input.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect())
+}
+
+fn invalid(message: &str) -> HuffmanError {
+    HuffmanError::InvalidTable(message.to_string())
+}
+
+/// Reads the subset of JSON that [`SharedTable::to_json`] writes: objects,
+/// strings without escapes and whole numbers.
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.text.as_bytes().get(self.position) {
+            self.position += 1;
+        }
+
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        if self.peek() != Some(byte) {
+            return Err(invalid(&format!("expected '{}' at byte {}", byte as char, self.position)));
+        }
+        self.position += 1;
+
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<&'a str> {
+        self.expect(b'"')?;
+        let length = self.text[self.position..].find(['"', '\\'])
+            .filter(|&length| self.text.as_bytes()[self.position + length] == b'"')
+            .ok_or_else(|| invalid(&format!("unsupported string at byte {}", self.position)))?;
+
+        let string = &self.text[self.position..self.position + length];
+        self.position += length + 1;
+        Ok(string)
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        self.peek();
+        let length = self.text[self.position..].bytes().take_while(u8::is_ascii_digit).count();
+        let number = self.text[self.position..self.position + length].parse()
+            .map_err(|_| invalid(&format!("expected a number at byte {}", self.position)))?;
+
+        self.position += length;
+        Ok(number)
+    }
+
+    /// Reads an object, calling `field` with each key to read its value.
+    fn object(&mut self, mut field: impl FnMut(&mut Parser<'a>, &'a str) -> Result<()>) -> Result<()> {
+        self.expect(b'{')?;
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(());
+        }
+
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            field(self, key)?;
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                _ => return self.expect(b'}'),
+            }
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(invalid(&format!("unexpected text at byte {}", self.position))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress_bytes, decompress_bytes};
+
+    #[test]
+    fn test_round_trip() {
+        let corpus = "the quick brown fox jumps over the lazy dog ".repeat(20);
+        let inputs: [(SymbolKind, &[u8], &[u8]); 3] = [
+            (SymbolKind::Chars, corpus.as_bytes(), "the dog, the fox と the zebra".as_bytes()),
+            (SymbolKind::Bytes, b"abbcccdddd", b"dcba\xFF\x00"),
+            (SymbolKind::U16, &[1, 0, 2, 0, 2, 0], &[2, 0, 1, 0, 0x50, 0xC3]),
+        ];
+
+        for (kind, corpus, input) in inputs {
+            let table = SharedTable::from_input(kind, corpus).unwrap();
+            assert_eq!(table.kind(), kind);
+
+            for input in [input, corpus, b""] {
+                let compressed = table.compress(input).unwrap();
+                assert_eq!(table.decompress(&compressed).unwrap(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_smaller_than_own_table() {
+        let table = SharedTable::from_input(SymbolKind::Bytes, &b"GET /index.html HTTP/1.1\r\n".repeat(100)).unwrap();
+        let input = b"GET /home.html HTTP/1.1\r\n";
+
+        assert!(table.compress(input).unwrap().len() < compress_bytes(input).len());
+    }
+
+    #[test]
+    fn test_missing_table() {
+        let table = SharedTable::from_input(SymbolKind::Bytes, b"abbcccdddd").unwrap();
+        let compressed = table.compress(b"abcd").unwrap();
+        assert!(matches!(decompress_bytes(&compressed).unwrap_err(), HuffmanError::MissingTable));
+
+        let other = SharedTable::from_input(SymbolKind::Bytes, b"abbcccddddd").unwrap();
+        assert_ne!(other.id(), table.id());
+        assert!(matches!(other.decompress(&compressed).unwrap_err(), HuffmanError::MissingTable));
+
+        // A table passes streams with their own tables through.
+        assert_eq!(table.decompress(&compress_bytes(b"xyz")).unwrap(), b"xyz");
+    }
+
+    #[test]
+    fn test_empty_table() {
+        let table = SharedTable::new(SymbolKind::Chars, HashMap::new()).unwrap();
+        let compressed = table.compress("ünïcödé".as_bytes()).unwrap();
+        assert_eq!(table.decompress(&compressed).unwrap(), "ünïcödé".as_bytes());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let table = SharedTable::from_input(SymbolKind::U16, &[1, 0, 2, 0, 2, 0, 0xFF, 0xFF]).unwrap();
+        let json = table.to_json();
+        assert_eq!(json, "{\n  \"kind\": \"u16\",\n  \"frequencies\": {\n    \"1\": 1,\n    \"2\": 2,\n    \"65535\": 1\n  }\n}\n");
+        assert_eq!(SharedTable::from_json(&json).unwrap(), table);
+
+        let table = SharedTable::from_json(" {\"frequencies\":{},\"kind\":\"bytes\"} ").unwrap();
+        assert!(table.frequencies().is_empty());
+        assert_eq!(SharedTable::from_json(&table.to_json()).unwrap(), table);
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        for json in ["", "{}", "{\"kind\": \"bytes\"}", "{\"kind\": \"words\", \"frequencies\": {}}",
+            "{\"kind\": \"bytes\", \"frequencies\": {\"256\": 1}}", "{\"kind\": \"bytes\", \"frequencies\": {\"97\": 0}}",
+            "{\"kind\": \"bytes\", \"frequencies\": {\"a\": 1}}", "{\"kind\": \"bytes\", \"frequencies\": {\"97\": -1}}",
+            "{\"kind\": \"bytes\", \"frequencies\": {\"97\": 1, \"97\": 2}}", "{\"kind\": \"bytes\", \"frequencies\": {}} x",
+            "{\"kind\": \"by\\tes\", \"frequencies\": {}}", "{\"kind\": \"bytes\", \"frequencies\": {}, \"extra\": 1}",
+            "{\"kind\": \"chars\", \"frequencies\": {\"55296\": 1}}", "{\"kind\": \"bytes\" \"frequencies\": {}}"] {
+            assert!(matches!(SharedTable::from_json(json).unwrap_err(), HuffmanError::InvalidTable(_)), "{}", json);
+        }
+    }
+}
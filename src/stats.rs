@@ -0,0 +1,167 @@
+//! Measuring how well input compresses: its entropy, and where the bytes of a
+//! compressed stream go.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::compress::DEFAULT_BLOCK_SIZE;
+use crate::error::{HuffmanError, Result};
+use crate::format::{AdaptiveBlockHeader, BlockHeader, Coding, Header, SymbolKind};
+use crate::frequency::count_symbols;
+use crate::optimize::BlockPlan;
+use crate::symbol::Symbol;
+
+/// The Shannon entropy of symbols occurring with `frequencies`, in bits per
+/// symbol: the fewest bits any code of the single symbols can average.
+///
+/// ```
+/// let frequencies = huffman::get_frequencies("abcd");
+/// assert_eq!(huffman::stats::entropy(&frequencies), 2.0);
+/// ```
+pub fn entropy<S: Eq + Hash>(frequencies: &HashMap<S, usize>) -> f64 {
+    let total: usize = frequencies.values().sum();
+    frequencies.values()
+        .filter(|&&frequency| frequency > 0)
+        .map(|&frequency| {
+            let probability = frequency as f64 / total as f64;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// What a compressed stream is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// The number of symbols coded.
+    pub symbols: u64,
+    /// The size of the whole stream in bytes.
+    pub compressed_size: u64,
+    /// The bytes of encoded bits, including the padding of each block.
+    pub encoded_size: u64,
+}
+
+impl Report {
+    /// Walks the headers of `compressed`.
+    ///
+    /// Fails like [`decompress_bytes`](crate::decompress_bytes) if they are
+    /// malformed, without decoding the bits.
+    pub fn from_compressed(compressed: &[u8]) -> Result<Report> {
+        let mut rest = compressed;
+        let header = Header::read(&mut rest)?;
+
+        let mut report = Report { symbols: 0, compressed_size: compressed.len() as u64, encoded_size: 0 };
+        loop {
+            let (length, encoded_size) = match header.coding {
+                Coding::Static => match BlockHeader::read(&header, &mut rest)? {
+                    Some(block) => (block.length, block.encoded_size.unwrap_or(rest.len() as u64)),
+                    None => break,
+                },
+                Coding::Adaptive => match AdaptiveBlockHeader::read(&mut rest)? {
+                    Some(block) => (block.length, block.encoded_size),
+                    None => break,
+                },
+            };
+            rest = rest.get(encoded_size as usize..).ok_or(HuffmanError::Truncated)?;
+            report.symbols += length;
+            report.encoded_size += encoded_size;
+        }
+
+        Ok(report)
+    }
+
+    /// The report [`Report::from_compressed`] gives for
+    /// [`compress`](crate::compress) of `text`, found from the frequencies of
+    /// its blocks without coding them.
+    ///
+    /// ```
+    /// let text = "abbcccdddd".repeat(100);
+    /// let predicted = huffman::stats::Report::predict(&text);
+    /// assert_eq!(predicted.compressed_size, huffman::compress(&text).len() as u64);
+    /// ```
+    pub fn predict(text: &str) -> Report {
+        let chars: Vec<char> = text.chars().collect();
+        predict(SymbolKind::Chars, chars.chunks(DEFAULT_BLOCK_SIZE))
+    }
+
+    /// Like [`Report::predict`], for [`compress_bytes`](crate::compress_bytes).
+    pub fn predict_bytes(input: &[u8]) -> Report {
+        predict(SymbolKind::Bytes, input.chunks(DEFAULT_BLOCK_SIZE))
+    }
+
+    /// Like [`Report::predict`], for [`compress_u16`](crate::compress_u16).
+    pub fn predict_u16(input: &[u16]) -> Report {
+        predict(SymbolKind::U16, input.chunks(DEFAULT_BLOCK_SIZE))
+    }
+
+    /// The bytes spent on anything but encoded bits: the headers of the file
+    /// and its blocks, the end of the stream and the checksum.
+    pub fn header_size(&self) -> u64 {
+        self.compressed_size - self.encoded_size
+    }
+
+    /// The encoded bits per symbol, or 0 if there are none.
+    pub fn average_code_length(&self) -> f64 {
+        if self.symbols == 0 {
+            return 0.0;
+        }
+
+        (self.encoded_size * 8) as f64 / self.symbols as f64
+    }
+}
+
+/// The report of a stream of `blocks` of `kind`, each stored as
+/// [`BlockPlan::choose`] would store it.
+fn predict<'a, S: Symbol + 'a>(kind: SymbolKind, blocks: impl Iterator<Item = &'a [S]>) -> Report {
+    let header = Header::new(kind);
+    let mut report = Report { symbols: 0, compressed_size: header.size() + header.trailer_size(), encoded_size: 0 };
+    for block in blocks {
+        let frequencies = count_symbols(block.iter().copied());
+        let (size, plan) = BlockPlan::choose_with_size(kind, &frequencies);
+        report.symbols += block.len() as u64;
+        report.compressed_size += size;
+        report.encoded_size += plan.table.encoded_bits(&frequencies).div_ceil(8);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, compress_adaptive, compress_bytes, get_frequencies};
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(&get_frequencies("aaaa")), 0.0);
+        assert_eq!(entropy(&HashMap::<u8, usize>::new()), 0.0);
+
+        let entropy = entropy(&get_frequencies("abbcccdddd"));
+        assert!((entropy - 1.846).abs() < 1e-3, "{}", entropy);
+    }
+
+    #[test]
+    fn test_report() {
+        let compressed = compress_bytes(b"abbcccdddd");
+        let report = Report::from_compressed(&compressed).unwrap();
+        assert_eq!(report, Report { symbols: 10, compressed_size: compressed.len() as u64, encoded_size: 3 });
+        assert_eq!(report.header_size(), compressed.len() as u64 - 3);
+        assert_eq!(report.average_code_length(), 2.4);
+
+        let report = Report::from_compressed(&compress_adaptive(b"abracadabra")).unwrap();
+        assert_eq!(report.symbols, 11);
+        assert_eq!(Report::from_compressed(&compress("")).unwrap().average_code_length(), 0.0);
+    }
+
+    #[test]
+    fn test_predict() {
+        let bytes: Vec<u8> = (0..3000u32).map(|i| (i * i % 251) as u8).collect();
+        for input in [&b""[..], b"a", b"abbcccdddd", &[7; 1000], &bytes] {
+            assert_eq!(Report::predict_bytes(input), Report::from_compressed(&compress_bytes(input)).unwrap());
+        }
+
+        let text = format!("{}ünïcödé", "the quick brown fox jumps over the lazy dog. ".repeat(40));
+        assert_eq!(Report::predict(&text), Report::from_compressed(&compress(&text)).unwrap());
+        let symbols = [1, 2, 2, 300, 300, 300];
+        assert_eq!(Report::predict_u16(&symbols), Report::from_compressed(&crate::compress_u16(&symbols)).unwrap());
+    }
+}
@@ -0,0 +1,42 @@
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// A type whose values can be Huffman coded.
+///
+/// Symbols are stored in headers by their `u32` value.
+pub trait Symbol: Copy + Ord + Hash + Debug {
+    fn to_u32(self) -> u32;
+
+    /// Returns `None` if `value` is not a symbol of this type.
+    fn from_u32(value: u32) -> Option<Self>;
+}
+
+impl Symbol for char {
+    fn to_u32(self) -> u32 {
+        self as u32
+    }
+
+    fn from_u32(value: u32) -> Option<char> {
+        char::from_u32(value)
+    }
+}
+
+impl Symbol for u8 {
+    fn to_u32(self) -> u32 {
+        self as u32
+    }
+
+    fn from_u32(value: u32) -> Option<u8> {
+        u8::try_from(value).ok()
+    }
+}
+
+impl Symbol for u16 {
+    fn to_u32(self) -> u32 {
+        self as u32
+    }
+
+    fn from_u32(value: u32) -> Option<u16> {
+        u16::try_from(value).ok()
+    }
+}
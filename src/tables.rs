@@ -0,0 +1,189 @@
+//! Reusing the tables of a compressed stream to compress the same input again.
+
+use std::io::{Read, Write};
+
+use crate::compress::{block_table, write_planned_block};
+use crate::crc::crc32;
+use crate::error::{HuffmanError, Result};
+use crate::format::{self, BlockHeader, Coding, Header, SymbolKind, Table, VERSION};
+use crate::optimize::BlockPlan;
+use crate::symbol::Symbol;
+
+/// The blocks of a compressed stream and how each was coded, without the
+/// encoded bits.
+///
+/// Choosing the tables is most of the work of compressing, so input known to
+/// be unchanged, for example by a hash of it, can be compressed again with
+/// [`Tables::compress`] without counting it. The output is the same as the
+/// first time.
+///
+/// ```
+/// let compressed = huffman::compress("abbcccdddd");
+/// let tables = huffman::Tables::from_compressed(&compressed)?;
+///
+/// assert_eq!(tables.compress(b"abbcccdddd")?, compressed);
+/// # Ok::<(), huffman::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tables {
+    kind: SymbolKind,
+    blocks: Vec<BlockHeader>,
+}
+
+impl Tables {
+    /// Collects the tables of `compressed`, which must be statically coded and
+    /// of the latest version.
+    ///
+    /// Fails like [`decompress_bytes`](crate::decompress_bytes) if the headers
+    /// are malformed, and with [`HuffmanError::InvalidInput`] if the stream is
+    /// of another version or adaptively coded.
+    pub fn from_compressed(compressed: &[u8]) -> Result<Tables> {
+        let mut rest = compressed;
+        let header = Header::read(&mut rest)?;
+        if header.version != VERSION || header.coding != Coding::Static {
+            return Err(HuffmanError::InvalidInput("Only tables of the latest static format can be reused".to_string()));
+        }
+
+        let mut blocks = Vec::new();
+        while let Some(block) = BlockHeader::read(&header, &mut rest)? {
+            let encoded_size = block.encoded_size.unwrap_or_default() as usize;
+            rest = rest.get(encoded_size..).ok_or(HuffmanError::Truncated)?;
+            blocks.push(block);
+        }
+
+        Ok(Tables { kind: header.kind, blocks })
+    }
+
+    /// The kind of symbols the tables code.
+    pub fn kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    /// Compresses `input`, given as [`decompress_bytes`](crate::decompress_bytes)
+    /// returns it, into the blocks and with the codes of these tables.
+    ///
+    /// Fails with [`HuffmanError::InvalidInput`] if the tables cannot code
+    /// `input`, such as when its length differs or a symbol has no code, and
+    /// with [`HuffmanError::NotUtf8`] if characters are coded and `input` is
+    /// not text.
+    pub fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        match self.kind {
+            SymbolKind::Chars => {
+                let text = std::str::from_utf8(input).map_err(|_| HuffmanError::NotUtf8)?;
+                self.compress_symbols(&text.chars().collect::<Vec<char>>(), input)
+            }
+            SymbolKind::Bytes => self.compress_symbols(input, input),
+            SymbolKind::U16 => {
+                if !input.len().is_multiple_of(2) {
+                    return Err(mismatch());
+                }
+                let symbols: Vec<u16> = input.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+                self.compress_symbols(&symbols, input)
+            }
+        }
+    }
+
+    fn compress_symbols<S: Symbol>(&self, symbols: &[S], input: &[u8]) -> Result<Vec<u8>> {
+        if self.blocks.iter().map(|block| block.length).sum::<u64>() != symbols.len() as u64 {
+            return Err(mismatch());
+        }
+
+        let mut output = Vec::new();
+        Header::new(self.kind).write(&mut output)?;
+
+        let mut rest = symbols;
+        for block in &self.blocks {
+            let Table::CodeLengths(lengths) = &block.table else {
+                return Err(mismatch());
+            };
+            let (symbols, tail) = rest.split_at(block.length as usize);
+            rest = tail;
+
+            let plan = BlockPlan { table: block_table(lengths, block.escape)?, encoding: block.encoding };
+            if !symbols.iter().all(|&symbol| plan.table.can_encode(symbol)) {
+                return Err(mismatch());
+            }
+            write_planned_block(self.kind, &plan, symbols, &mut output)?;
+        }
+        BlockHeader::write_end(&mut output)?;
+        format::write_checksum(&mut output, crc32(input))?;
+
+        Ok(output)
+    }
+
+    /// Writes the tables in the layout of a compressed stream, without the
+    /// encoded bits or the checksum.
+    pub fn write<W: Write>(&self, output: &mut W) -> Result<()> {
+        Header::new(self.kind).write(output)?;
+        for block in &self.blocks {
+            block.write(self.kind, output)?;
+        }
+
+        BlockHeader::write_end(output)
+    }
+
+    /// Reads tables written by [`Tables::write`].
+    ///
+    /// Fails like [`Header::read`].
+    pub fn read<R: Read>(input: &mut R) -> Result<Tables> {
+        let header = Header::read(input)?;
+        if header.coding != Coding::Static || header.is_single_block() {
+            return Err(HuffmanError::BadHeader("Not a table of blocks".to_string()));
+        }
+
+        let mut blocks = Vec::new();
+        while let Some(block) = BlockHeader::read(&header, input)? {
+            blocks.push(block);
+        }
+
+        Ok(Tables { kind: header.kind, blocks })
+    }
+}
+
+fn mismatch() -> HuffmanError {
+    HuffmanError::InvalidInput("Input does not match the tables".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, compress_bytes, compress_u16};
+
+    #[test]
+    fn test_recompress() {
+        let text = format!("{}{}", "the quick brown fox ".repeat(50), ('\u{1F600}'..'\u{1F650}').collect::<String>());
+        let compressed = compress(&text);
+        assert_eq!(Tables::from_compressed(&compressed).unwrap().compress(text.as_bytes()).unwrap(), compressed);
+
+        let compressed = compress_bytes(b"");
+        assert_eq!(Tables::from_compressed(&compressed).unwrap().compress(b"").unwrap(), compressed);
+
+        let compressed = compress_u16(&[1, 2, 2, 50_000]);
+        let tables = Tables::from_compressed(&compressed).unwrap();
+        assert_eq!(tables.kind(), SymbolKind::U16);
+        assert_eq!(tables.compress(&[1, 0, 2, 0, 2, 0, 0x50, 0xC3]).unwrap(), compressed);
+    }
+
+    #[test]
+    fn test_read_write() {
+        let tables = Tables::from_compressed(&compress("abbcccdddd")).unwrap();
+        let mut bytes = Vec::new();
+        tables.write(&mut bytes).unwrap();
+
+        assert_eq!(Tables::read(&mut bytes.as_slice()).unwrap(), tables);
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let tables = Tables::from_compressed(&compress_bytes(b"abbcccdddd")).unwrap();
+
+        for input in [b"abbcccddd".as_slice(), b"abbcccddde", b"abbcccdddda"] {
+            assert!(matches!(tables.compress(input).unwrap_err(), HuffmanError::InvalidInput(_)));
+        }
+        // A different order of the same symbols still codes.
+        assert!(tables.compress(b"ddddcccbba").is_ok());
+
+        let error = Tables::from_compressed(&crate::compress_adaptive(b"abc")).unwrap_err();
+        assert!(matches!(error, HuffmanError::InvalidInput(_)));
+    }
+}
@@ -0,0 +1,118 @@
+use alloc::boxed::Box;
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::error::{HuffmanError, Result};
+use crate::symbol::Symbol;
+use crate::Map;
+
+/// A node of a Huffman tree.
+///
+/// The ordering is the one used while building the tree: lower frequencies
+/// compare greater, so that [`BinaryHeap`] pops them first.
+#[derive(Debug, Clone)]
+pub enum HuffmanNode<S = char> {
+    Internal { left: Box<HuffmanNode<S>>, right: Box<HuffmanNode<S>> },
+    Leaf { symbol: S, frequency: usize },
+}
+
+impl<S> HuffmanNode<S> {
+    /// The total frequency of all leaves below this node.
+    pub fn frequency(&self) -> usize {
+        match self {
+            HuffmanNode::Internal { left, right } => left.frequency() + right.frequency(),
+            HuffmanNode::Leaf { frequency, .. } => *frequency,
+        }
+    }
+}
+
+impl<S: Symbol> Ord for HuffmanNode<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ordering = other.frequency().cmp(&self.frequency());
+        if ordering == Ordering::Equal {
+            let HuffmanNode::Leaf { symbol: self_symbol, frequency: _ } = self else { return ordering; };
+            let HuffmanNode::Leaf { symbol, frequency: _ } = other else { return ordering; };
+            symbol.cmp(self_symbol)
+        } else {
+            ordering
+        }
+    }
+}
+
+impl<S: Symbol> PartialOrd for HuffmanNode<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S> PartialEq for HuffmanNode<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.frequency() == other.frequency()
+    }
+}
+
+impl<S> Eq for HuffmanNode<S> {}
+
+/// A Huffman tree built from a frequency table.
+#[derive(Debug, Clone)]
+pub struct Tree<S = char> {
+    root: HuffmanNode<S>,
+}
+
+impl<S: Symbol> Tree<S> {
+    /// Builds the tree for `frequencies`.
+    ///
+    /// Fails with [`HuffmanError::EmptyInput`] if `frequencies` is empty.
+    pub fn from_frequencies(frequencies: &Map<S, usize>) -> Result<Tree<S>> {
+        if frequencies.is_empty() {
+            return Err(HuffmanError::EmptyInput);
+        }
+
+        Ok(Tree { root: build_huffman_tree(frequencies) })
+    }
+
+    pub fn root(&self) -> &HuffmanNode<S> {
+        &self.root
+    }
+}
+
+fn build_huffman_tree<S: Symbol>(freq_map: &Map<S, usize>) -> HuffmanNode<S> {
+    // Taken from https://opendsa-server.cs.vt.edu/ODSA/Books/CS3/html/Huffman.html
+    let mut heap = BinaryHeap::new();
+
+    // Internal nodes of equal frequency compare equal, so the shape of the
+    // tree depends on insertion order. Insert in a fixed order so that the
+    // decoder rebuilds exactly the tree the encoder used.
+    let mut leaves: Vec<(S, usize)> = freq_map.iter().map(|(&s, &f)| (s, f)).collect();
+    leaves.sort_unstable();
+
+    for (symbol, frequency) in leaves {
+        heap.push(HuffmanNode::Leaf { symbol, frequency });
+    }
+
+    while heap.len() > 1 {
+        let left = heap.pop().unwrap();
+        let right = heap.pop().unwrap();
+
+        let internal = HuffmanNode::Internal {
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+
+        heap.push(internal);
+    }
+
+    heap.pop().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_frequencies() {
+        let error = Tree::<char>::from_frequencies(&Map::new()).unwrap_err();
+        assert!(matches!(error, HuffmanError::EmptyInput));
+    }
+}
@@ -0,0 +1,59 @@
+//! That the coder allocates only through the global allocator, which a program
+//! can replace with `#[global_allocator]`, and gives back all it takes.
+//! Allocations are counted per thread, so the test harness's own do not count.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// The system allocator, counting the allocations and live bytes of each
+/// thread.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        LIVE.with(|live| live.set(live.get() + layout.size() as isize));
+        // SAFETY: passed on from the caller.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.with(|live| live.set(live.get() - layout.size() as isize));
+        // SAFETY: passed on from the caller, who had it from `alloc`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// How many allocations `function` makes, and how many bytes it leaves
+/// allocated once its result is dropped.
+fn count(function: impl FnOnce()) -> (usize, isize) {
+    let (allocations, live) = (ALLOCATIONS.with(Cell::get), LIVE.with(Cell::get));
+    function();
+    (ALLOCATIONS.with(Cell::get) - allocations, LIVE.with(Cell::get) - live)
+}
+
+#[test]
+fn test_allocations() {
+    let text = "the quick brown fox jumps over the lazy dog. ".repeat(100);
+    let compressed = huffman::compress(&text);
+
+    let runs: [(&str, &dyn Fn()); 4] = [
+        ("tree", &|| drop(huffman::Tree::from_frequencies(&huffman::get_frequencies(&text)))),
+        ("compress", &|| drop(huffman::compress(&text))),
+        ("compress_bytes", &|| drop(huffman::compress_bytes(text.as_bytes()))),
+        ("decompress", &|| drop(huffman::decompress(&compressed))),
+    ];
+    for (name, run) in runs {
+        let (allocations, live) = count(run);
+        assert!(allocations > 0, "{} allocated without the global allocator", name);
+        assert_eq!(live, 0, "{} kept {} bytes", name, live);
+    }
+}
@@ -0,0 +1,315 @@
+//! The public API, spelled out so that a breaking change to it fails to
+//! compile here before it reaches a release.
+//!
+//! Every public function is coerced to a function pointer of its exact
+//! signature, every public struct is built with all of its fields, every
+//! public enum is matched without a wildcard, and the traits callers rely on
+//! are required of each type. Changing any of these on purpose means changing
+//! this file too, which makes the break visible in review along with the need
+//! for a new major version. Adding to the API is not a break, and should be
+//! recorded here as well.
+
+// The signatures are spelled out in full, however long, as that is the point.
+#![allow(clippy::type_complexity)]
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::io::{self, BufRead, Read, Write};
+
+use huffman::archive::{self, Entry};
+use huffman::format::{
+    self, AdaptiveBlockHeader, BlockHeader, Coding, Escape, Header, Source, SymbolKind, Table, TableEncoding,
+};
+use huffman::stats::{self, Report};
+use huffman::{
+    AdaptiveEncoder, CodeTable, Container, HuffmanDecoder, HuffmanEncoder, HuffmanError, HuffmanNode, Progress, Result,
+    SharedTable, Symbol, Tables, Tree,
+};
+
+fn is_value<T: Debug + Clone + PartialEq + Eq + Send + Sync + 'static>() {}
+fn is_copy<T: Copy>() {}
+fn is_debug<T: Debug + Send + Sync>() {}
+
+#[test]
+fn test_functions() {
+    let _: fn(&str) -> Vec<u8> = huffman::compress;
+    let _: fn(&[u8]) -> Vec<u8> = huffman::compress_bytes;
+    let _: fn(&[u16]) -> Vec<u8> = huffman::compress_u16;
+    let _: fn(&str) -> Vec<u8> = huffman::compress_optimal;
+    let _: fn(&[u8]) -> Vec<u8> = huffman::compress_bytes_optimal;
+    let _: fn(&[u16]) -> Vec<u8> = huffman::compress_u16_optimal;
+    let _: fn(&str, usize) -> Vec<u8> = huffman::compress_parallel;
+    let _: fn(&[u8], usize) -> Vec<u8> = huffman::compress_bytes_parallel;
+    let _: fn(&[u16], usize) -> Vec<u8> = huffman::compress_u16_parallel;
+    let _: fn(&[u8]) -> Vec<u8> = huffman::compress_adaptive;
+    let _: fn(&[u8]) -> Result<String> = huffman::decompress;
+    let _: fn(&[u8]) -> Result<Vec<u8>> = huffman::decompress_bytes;
+    let _: fn(&[u8]) -> Result<Vec<u16>> = huffman::decompress_u16;
+    let _: usize = huffman::DEFAULT_BLOCK_SIZE;
+
+    // With std, which the rest of the API needs.
+    let _: huffman::Map<char, usize> = HashMap::new();
+    let _: fn(&str) -> HashMap<char, usize> = huffman::get_frequencies;
+    let _: fn(&[u8]) -> HashMap<u8, usize> = huffman::get_byte_frequencies;
+
+    let _: fn(Vec<char>, &CodeTable) -> Result<Vec<u8>> = huffman::encode::<char>;
+    let _: fn(&[u8], &Tree<u8>, usize) -> Result<Vec<u8>> = huffman::decode::<u8>;
+    let _: fn(&[u8], &CodeTable<u16>, usize) -> Result<Vec<u16>> = huffman::decode_canonical::<u16>;
+    generic_functions::<io::Cursor<&[u8]>>();
+}
+
+fn generic_functions<R: BufRead>() {
+    let _: fn(&mut R) -> Result<HashMap<char, usize>> = huffman::get_frequencies_from_reader::<R>;
+    let _: fn(&mut R) -> Result<HashMap<u8, usize>> = huffman::get_byte_frequencies_from_reader::<R>;
+}
+
+#[test]
+fn test_codes() {
+    codes::<char>();
+    codes::<u8>();
+    codes::<u16>();
+    is_debug::<CodeTable>();
+    is_debug::<HuffmanNode>();
+    is_debug::<Tree>();
+    let _ = CodeTable::<char>::default().clone();
+}
+
+fn codes<S: Symbol>() {
+    let _: fn(S) -> u32 = S::to_u32;
+    let _: fn(u32) -> Option<S> = S::from_u32;
+
+    let _: fn(&HashMap<S, usize>) -> Result<Tree<S>> = Tree::from_frequencies;
+    let _: fn(&Tree<S>) -> &HuffmanNode<S> = Tree::root;
+    let _: fn(&HuffmanNode<S>) -> usize = HuffmanNode::frequency;
+    let _ = |node: HuffmanNode<S>| match node {
+        HuffmanNode::Internal { left, right } => {
+            let _: (Box<HuffmanNode<S>>, Box<HuffmanNode<S>>) = (left, right);
+        }
+        HuffmanNode::Leaf { symbol, frequency } => {
+            let _: (S, usize) = (symbol, frequency);
+        }
+    };
+
+    let _: fn(&Tree<S>) -> CodeTable<S> = CodeTable::from_tree;
+    let _: fn(&HashMap<S, u8>) -> Option<CodeTable<S>> = CodeTable::from_code_lengths;
+    let _: fn(&CodeTable<S>) -> CodeTable<S> = CodeTable::canonicalize;
+    let _: fn(&CodeTable<S>) -> HashMap<S, u8> = CodeTable::code_lengths;
+    let _: fn(&CodeTable<S>, S) -> Option<&str> = CodeTable::get;
+    let _: fn(&CodeTable<S>) -> usize = CodeTable::len;
+    let _: fn(&CodeTable<S>) -> bool = CodeTable::is_empty;
+    fn iter<S: Symbol>(codes: &CodeTable<S>) -> Vec<(S, &str)> {
+        codes.iter().collect()
+    }
+    let _ = iter::<S>;
+}
+
+#[test]
+fn test_streams() {
+    streams::<Vec<u8>, io::Cursor<Vec<u8>>>();
+    readers::<&[u8]>();
+    is_debug::<HuffmanEncoder<Vec<u8>>>();
+    is_debug::<HuffmanDecoder<io::Cursor<Vec<u8>>>>();
+    is_debug::<AdaptiveEncoder<Vec<u8>>>();
+    is_debug::<Progress<Vec<u8>, fn(u64)>>();
+}
+
+fn streams<W: Write, R: Read>() {
+    let _: fn(W) -> HuffmanEncoder<W> = HuffmanEncoder::new;
+    let _: fn(W, usize) -> HuffmanEncoder<W> = HuffmanEncoder::with_block_size;
+    let _: fn(&HuffmanEncoder<W>) -> &W = HuffmanEncoder::get_ref;
+    let _: fn(&mut HuffmanEncoder<W>) -> &mut W = HuffmanEncoder::get_mut;
+    let _: fn(HuffmanEncoder<W>) -> Result<W> = HuffmanEncoder::finish;
+    is_write::<HuffmanEncoder<W>>();
+
+    let _: fn(W) -> AdaptiveEncoder<W> = AdaptiveEncoder::new;
+    let _: fn(&AdaptiveEncoder<W>) -> &W = AdaptiveEncoder::get_ref;
+    let _: fn(&mut AdaptiveEncoder<W>) -> &mut W = AdaptiveEncoder::get_mut;
+    let _: fn(AdaptiveEncoder<W>) -> Result<W> = AdaptiveEncoder::finish;
+    is_write::<AdaptiveEncoder<W>>();
+
+    let _: fn(R) -> HuffmanDecoder<R> = HuffmanDecoder::new;
+    let _: fn(R, usize) -> HuffmanDecoder<R> = HuffmanDecoder::with_threads;
+    let _: fn(HuffmanDecoder<R>, SharedTable) -> HuffmanDecoder<R> = HuffmanDecoder::with_table;
+    let _: fn(&HuffmanDecoder<R>) -> &R = HuffmanDecoder::get_ref;
+    let _: fn(&mut HuffmanDecoder<R>) -> &mut R = HuffmanDecoder::get_mut;
+    let _: fn(HuffmanDecoder<R>) -> R = HuffmanDecoder::into_inner;
+    is_read::<HuffmanDecoder<R>>();
+
+    let _: fn(R, fn(u64)) -> Progress<R, fn(u64)> = Progress::new;
+    let _: fn(&Progress<R, fn(u64)>) -> u64 = Progress::total;
+    let _: fn(&Progress<R, fn(u64)>) -> &R = Progress::get_ref;
+    let _: fn(&mut Progress<R, fn(u64)>) -> &mut R = Progress::get_mut;
+    let _: fn(Progress<R, fn(u64)>) -> R = Progress::into_inner;
+    is_read::<Progress<R, fn(u64)>>();
+    is_write::<Progress<W, fn(u64)>>();
+}
+
+fn readers<R: Read>() {
+    let _: fn(&mut R) -> Result<Tables> = Tables::read;
+    let _: fn(&mut R) -> Result<Header> = Header::read;
+    let _: fn(&mut R) -> Result<u32> = format::read_checksum;
+    let _: fn(&Header, &mut R) -> Result<Option<BlockHeader>> = BlockHeader::read;
+    let _: fn(&mut R) -> Result<Option<AdaptiveBlockHeader>> = AdaptiveBlockHeader::read;
+    let _: fn(&mut R) -> Result<Vec<Entry>> = archive::read_index;
+}
+
+fn is_read<T: Read>() {}
+fn is_write<T: Write>() {}
+
+#[test]
+fn test_tables() {
+    is_value::<Tables>();
+    let _: fn(&[u8]) -> Result<Tables> = Tables::from_compressed;
+    let _: fn(&Tables) -> SymbolKind = Tables::kind;
+    let _: fn(&Tables, &[u8]) -> Result<Vec<u8>> = Tables::compress;
+    let _: fn(&Tables, &mut Vec<u8>) -> Result<()> = Tables::write;
+
+    is_value::<SharedTable>();
+    let _: fn(SymbolKind, HashMap<u32, usize>) -> Result<SharedTable> = SharedTable::new;
+    let _: fn(SymbolKind, &[u8]) -> Result<SharedTable> = SharedTable::from_input;
+    let _: fn(&SharedTable) -> SymbolKind = SharedTable::kind;
+    let _: fn(&SharedTable) -> &HashMap<u32, usize> = SharedTable::frequencies;
+    let _: fn(&SharedTable) -> u32 = SharedTable::id;
+    let _: fn(&SharedTable, &[u8]) -> Result<Vec<u8>> = SharedTable::compress;
+    let _: fn(&SharedTable, &[u8]) -> Result<Vec<u8>> = SharedTable::decompress;
+    let _: fn(&SharedTable) -> String = SharedTable::to_json;
+    let _: fn(&str) -> Result<SharedTable> = SharedTable::from_json;
+}
+
+#[test]
+fn test_errors() {
+    fn is_error<E: Error + Send + Sync + 'static>() {}
+    is_error::<HuffmanError>();
+    is_value::<Container>();
+    is_copy::<Container>();
+
+    let _: fn(io::Error) -> HuffmanError = HuffmanError::from;
+    let _: fn(HuffmanError) -> io::Error = io::Error::from;
+    let _ = |error: HuffmanError| match error {
+        HuffmanError::Io(error) => {
+            let _: io::Error = error;
+        }
+        HuffmanError::BadMagic(container) => {
+            let _: Container = container;
+        }
+        HuffmanError::UnsupportedVersion { container, version } => {
+            let _: (Container, u8) = (container, version);
+        }
+        HuffmanError::EmptyInput
+        | HuffmanError::OddLength
+        | HuffmanError::Truncated
+        | HuffmanError::ChecksumMismatch
+        | HuffmanError::NotUtf8
+        | HuffmanError::WrongSymbolKind
+        | HuffmanError::MissingTable => {}
+        HuffmanError::BadHeader(message)
+        | HuffmanError::CorruptStream(message)
+        | HuffmanError::InvalidTable(message)
+        | HuffmanError::InvalidInput(message) => {
+            let _: String = message;
+        }
+        HuffmanError::Context { error, context, hint } => {
+            let _: (Box<HuffmanError>, Option<String>, Option<String>) = (error, context, hint);
+        }
+    };
+    let _: fn(HuffmanError, String) -> HuffmanError = HuffmanError::context;
+    let _: fn(HuffmanError, &'static str) -> HuffmanError = HuffmanError::with_hint;
+    let _: fn(&HuffmanError) -> &HuffmanError = HuffmanError::root;
+    let _: fn(&HuffmanError) -> Option<&str> = HuffmanError::hint;
+}
+
+#[test]
+fn test_format() {
+    let _: [u8; 4] = format::MAGIC;
+    let _: u8 = format::VERSION;
+    let _: u8 = format::OLDEST_VERSION;
+
+    is_value::<Header>();
+    is_copy::<Header>();
+    let source = Source { size: 0, modified: 0, checksum: 0, settings: 0 };
+    let header =
+        Header { version: format::VERSION, kind: SymbolKind::Bytes, coding: Coding::Static, source: Some(source) };
+    let _: fn(SymbolKind) -> Header = Header::new;
+    let _: fn(&Header, &mut Vec<u8>) -> Result<()> = Header::write;
+    let _: fn(&Header) -> u64 = Header::size;
+    let _: fn(&Header) -> u64 = Header::trailer_size;
+    let _: fn(&Header) -> bool = Header::is_single_block;
+    let _: fn(&Header) -> bool = Header::has_checksum;
+    let _: fn(&[u8]) -> u64 = Source::checksum;
+    let _: fn(&mut Vec<u8>, u32) -> Result<()> = format::write_checksum;
+    let _: fn(&[u8], &Header) -> Result<Vec<u8>> = format::with_header;
+
+    is_value::<BlockHeader>();
+    let block = BlockHeader {
+        length: 0,
+        table: Table::CodeLengths(HashMap::new()),
+        encoding: TableEncoding::CodeLengths,
+        escape: Some(Escape { code_length: 1, literal_width: 8 }),
+        encoded_size: None,
+    };
+    let _: fn(&BlockHeader, SymbolKind, &mut Vec<u8>) -> Result<()> = BlockHeader::write;
+    let _: fn(&BlockHeader, SymbolKind) -> u64 = BlockHeader::size;
+    let _: fn(&mut Vec<u8>) -> Result<()> = BlockHeader::write_end;
+
+    is_value::<AdaptiveBlockHeader>();
+    is_copy::<AdaptiveBlockHeader>();
+    let _ = AdaptiveBlockHeader { length: 0, encoded_size: 0 };
+    let _: fn(&AdaptiveBlockHeader, &mut Vec<u8>) -> Result<()> = AdaptiveBlockHeader::write;
+    let _: fn(&mut Vec<u8>) -> Result<()> = AdaptiveBlockHeader::write_end;
+
+    is_value::<Escape>();
+    is_copy::<Escape>();
+    is_value::<Table>();
+    match block.table {
+        Table::Frequencies(frequencies) => {
+            let _: HashMap<u32, usize> = frequencies;
+        }
+        Table::CodeLengths(lengths) => {
+            let _: HashMap<u32, u8> = lengths;
+        }
+        Table::Shared(id) => {
+            let _: u32 = id;
+        }
+    }
+    is_copy::<TableEncoding>();
+    match block.encoding {
+        TableEncoding::CodeLengths | TableEncoding::Topology | TableEncoding::Shared => {}
+    }
+    is_copy::<Coding>();
+    match header.coding {
+        Coding::Static | Coding::Adaptive => {}
+    }
+    is_copy::<SymbolKind>();
+    match header.kind {
+        SymbolKind::Chars | SymbolKind::Bytes | SymbolKind::U16 => {}
+    }
+}
+
+#[test]
+fn test_archive() {
+    let _: [u8; 4] = archive::MAGIC;
+    let _: u8 = archive::VERSION;
+
+    is_value::<Entry>();
+    let _ = Entry { name: String::new(), size: 0, offset: 0, compressed_size: 0 };
+    let _: fn(&str) -> Entry = Entry::new;
+    let _: fn(&[Entry]) -> u64 = archive::index_size;
+    let _: fn(&mut Vec<u8>, &[Entry]) -> Result<()> = archive::write_index;
+}
+
+#[test]
+fn test_stats() {
+    let _: fn(&HashMap<char, usize>) -> f64 = stats::entropy;
+    let _: fn(&HashMap<u32, usize>) -> f64 = stats::entropy;
+
+    is_value::<Report>();
+    is_copy::<Report>();
+    let _ = Report { symbols: 0, compressed_size: 0, encoded_size: 0 };
+    let _: fn(&[u8]) -> Result<Report> = Report::from_compressed;
+    let _: fn(&str) -> Report = Report::predict;
+    let _: fn(&[u8]) -> Report = Report::predict_bytes;
+    let _: fn(&[u16]) -> Report = Report::predict_u16;
+    let _: fn(&Report) -> u64 = Report::header_size;
+    let _: fn(&Report) -> f64 = Report::average_code_length;
+}
@@ -0,0 +1,98 @@
+//! Builds and tests the library and the tool with each combination of their
+//! features, so that code behind a feature that the default build leaves out
+//! cannot rot.
+//!
+//! The features are read from `Cargo.toml`: the crate is tested without any,
+//! which leaves the library `no_std`, with each alone, and with all of them.
+//! Each build goes to a target directory of its own under `target/features`,
+//! so that it neither waits on nor undoes the build running this test.
+//!
+//! A plain `cargo test` only checks that each combination compiles, which
+//! shares one target directory and takes seconds. Building and testing each
+//! is a full build per combination, so that is left out; run it before a
+//! release or from CI with `cargo test --test features -- --ignored`.
+
+use std::path::Path;
+use std::process::Command;
+
+/// The features declared in `Cargo.toml`, other than `default`.
+fn features(manifest: &str) -> Vec<String> {
+    manifest
+        .lines()
+        .skip_while(|line| line.trim() != "[features]")
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with('['))
+        .filter_map(|line| line.split_once('=').map(|(name, _)| name.trim().to_string()))
+        .filter(|name| !name.is_empty() && !name.starts_with('#') && name != "default")
+        .collect()
+}
+
+/// The combinations to test: none, each alone, and all together.
+fn combinations(features: &[String]) -> Vec<Vec<String>> {
+    let mut combinations = vec![Vec::new()];
+    combinations.extend(features.iter().map(|feature| vec![feature.clone()]));
+    if features.len() > 1 {
+        combinations.push(features.to_vec());
+    }
+
+    combinations
+}
+
+#[test]
+fn test_combinations() {
+    let manifest = "[package]\nname = \"a\"\n\n[features]\ndefault = [\"std\"]\nstd = []\n# Not a feature\n\
+        serde = [\"dep:serde\"]\n\n[dependencies]\nserde = \"1\"\n";
+    let declared = features(manifest);
+    assert_eq!(declared, ["std", "serde"]);
+    assert_eq!(combinations(&declared), [vec![], vec!["std"], vec!["serde"], vec!["std", "serde"]]);
+    assert!(features("[package]\nname = \"a\"\n").is_empty());
+}
+
+/// Runs cargo with `args` and the features of `combination`, building into
+/// `target`, and fails the test if it does.
+fn cargo(args: &[&str], combination: &[String], target: &Path) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = Command::new(&cargo)
+        .args(args)
+        .args(["--no-default-features", "--features", &combination.join(",")])
+        .env("CARGO_TARGET_DIR", target)
+        .current_dir(root)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "cargo {} fails with features [{}]:\n{}{}",
+        args.join(" "),
+        combination.join(", "),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    );
+}
+
+#[test]
+fn test_feature_check() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let manifest = std::fs::read_to_string(root.join("Cargo.toml")).unwrap();
+
+    for combination in combinations(&features(&manifest)) {
+        cargo(&["check", "--lib", "--bins"], &combination, &root.join("target/features/check"));
+    }
+}
+
+#[test]
+#[ignore = "builds the crate once per combination of features"]
+fn test_feature_matrix() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let manifest = std::fs::read_to_string(root.join("Cargo.toml")).unwrap();
+
+    for (index, combination) in combinations(&features(&manifest)).iter().enumerate() {
+        let target = root.join("target/features").join(index.to_string());
+        // The tests link std whatever the features, so the library is also
+        // built on its own to check that it does without.
+        for args in [&["build", "--lib"][..], &["test", "--lib", "--bins"]] {
+            cargo(args, combination, &target);
+        }
+    }
+}
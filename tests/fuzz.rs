@@ -0,0 +1,136 @@
+//! The targets of the cargo-fuzz harness in `fuzz/`, run on every `cargo test`
+//! over some thousands of mutations of valid compressed data, so that the
+//! decoder's promise to fail with an error rather than panic, overflow or hang
+//! is kept between fuzzing runs. The bodies match `fuzz/fuzz_targets`.
+
+use std::io::Read;
+
+use huffman::format::{BlockHeader, Header, SymbolKind};
+use huffman::{archive, HuffmanDecoder, SharedTable};
+
+/// `fuzz/fuzz_targets/header.rs`: the header and the block headers after it.
+fn header(data: &[u8]) {
+    let mut rest = data;
+    let Ok(header) = Header::read(&mut rest) else {
+        return;
+    };
+    while let Ok(Some(block)) = BlockHeader::read(&header, &mut rest) {
+        let encoded_size = block.encoded_size.unwrap_or(rest.len() as u64).min(rest.len() as u64);
+        rest = &rest[encoded_size as usize..];
+    }
+    let _ = archive::read_index(&mut &data[..]);
+}
+
+/// `fuzz/fuzz_targets/decode.rs`: whole streams, also on several threads.
+fn decode(data: &[u8]) {
+    let _ = huffman::decompress_bytes(data);
+    let _ = HuffmanDecoder::with_threads(data, 2).read_to_end(&mut Vec::new());
+    if let Ok(table) = SharedTable::from_input(SymbolKind::Bytes, b"abbcccdddd") {
+        let _ = table.decompress(data);
+    }
+    if let Ok(json) = std::str::from_utf8(data) {
+        let _ = SharedTable::from_json(json);
+    }
+}
+
+/// `fuzz/fuzz_targets/round_trip.rs`: whatever is compressed decompresses.
+fn round_trip(data: &[u8]) {
+    assert_eq!(huffman::decompress_bytes(&huffman::compress_bytes(data)).unwrap(), data);
+    assert_eq!(huffman::decompress_bytes(&huffman::compress_adaptive(data)).unwrap(), data);
+    if let Ok(text) = std::str::from_utf8(data) {
+        assert_eq!(huffman::decompress(&huffman::compress(text)).unwrap(), text);
+    }
+}
+
+/// A xorshift generator, so that every run tries the same inputs.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound.max(1) as u64) as usize
+    }
+}
+
+/// `data` changed in one of the ways a fuzzer would: bits flipped, bytes
+/// overwritten with interesting values, cut short, or parts repeated.
+fn mutate(rng: &mut Rng, data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    for _ in 0..=rng.below(3) {
+        let at = rng.below(data.len());
+        match rng.below(5) {
+            _ if data.is_empty() => data.push(rng.next() as u8),
+            0 => data[at] ^= 1 << rng.below(8),
+            1 => data[at] = [0, 1, 0x7F, 0x80, 0xFF][rng.below(5)],
+            2 => data.truncate(at),
+            3 => {
+                let end = (at + 1 + rng.below(8)).min(data.len());
+                let chunk = data[at..end].to_vec();
+                data.splice(at..at, chunk);
+            }
+            _ => data.insert(at, rng.next() as u8),
+        }
+    }
+
+    data
+}
+
+/// Valid compressed data of each kind to start mutating from.
+fn seeds() -> Vec<Vec<u8>> {
+    let text = "the quick brown fox jumps over the lazy dog, ünïcödé ".repeat(20);
+    let bytes: Vec<u8> = (0..3000u32).map(|i| (i * i % 251) as u8).collect();
+    let table = SharedTable::from_input(SymbolKind::Bytes, b"abbcccdddd").unwrap();
+
+    let mut archive = Vec::new();
+    let entry = huffman::compress_bytes(b"abbcccdddd");
+    let mut entries = [archive::Entry::new("a.txt")];
+    entries[0].size = 10;
+    entries[0].offset = archive::index_size(&entries);
+    entries[0].compressed_size = entry.len() as u64;
+    archive::write_index(&mut archive, &entries).unwrap();
+    archive.extend(entry);
+
+    vec![
+        huffman::compress(&text),
+        huffman::compress_bytes(&bytes),
+        huffman::compress_bytes_optimal(&bytes),
+        huffman::compress_u16(&[1, 2, 2, 300, 300, 300]),
+        huffman::compress_adaptive(&bytes),
+        huffman::compress_bytes(&[7; 100]),
+        huffman::compress_bytes(b""),
+        table.compress(&b"abbcccdddd".repeat(10)).unwrap(),
+        table.to_json().into_bytes(),
+        archive,
+    ]
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "too slow")]
+fn test_mutated_inputs_fail_cleanly() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for seed in seeds() {
+        for _ in 0..500 {
+            let data = mutate(&mut rng, &seed);
+            header(&data);
+            decode(&data);
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "too slow")]
+fn test_round_trip() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for length in [0, 1, 2, 255, 256, 1000] {
+        let alphabet = 1 + rng.below(256);
+        let data: Vec<u8> = (0..length).map(|_| rng.below(alphabet) as u8).collect();
+        round_trip(&data);
+        round_trip(&mutate(&mut rng, &data));
+    }
+}
@@ -0,0 +1,148 @@
+//! Properties of the coder checked on generated inputs: that everything
+//! decodes to what was encoded, and that the codes are prefix-free and as
+//! short as Huffman's construction makes them.
+//!
+//! The inputs come from a seeded generator, so a failure names the case that
+//! fails and happens again on every run. Besides random bytes over alphabets of
+//! every size, with skewed and even distributions, they include the empty
+//! input, a lone symbol, runs of one symbol and all 256 byte values.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use huffman::{CodeTable, Map, Tree};
+
+/// A xorshift generator, so that every run checks the same inputs.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound.max(1) as u64) as usize
+    }
+}
+
+/// The inputs to check, each with a name to tell which failed.
+fn cases() -> Vec<(String, Vec<u8>)> {
+    let mut rng = Rng(0x853c_49e6_748f_ea9b);
+    let mut cases = vec![
+        ("empty".to_string(), Vec::new()),
+        ("one symbol".to_string(), vec![b'a']),
+        ("two symbols".to_string(), vec![0, 255]),
+        ("every byte once".to_string(), (0..=255).collect()),
+    ];
+    for length in [2, 3, 100, 5000] {
+        cases.push((format!("{} identical", length), vec![rng.next() as u8; length]));
+    }
+    for index in 0..150 {
+        let length = rng.below(2000);
+        let alphabet = 1 + rng.below(256);
+        let skewed = index % 2 == 0;
+        let bytes = (0..length)
+            .map(|_| match skewed {
+                // Geometric, for long codes.
+                true => (rng.next().trailing_zeros() as usize % alphabet) as u8,
+                false => rng.below(alphabet) as u8,
+            })
+            .collect();
+        cases.push((format!("case {}: {} bytes of {}, skewed {}", index, length, alphabet, skewed), bytes));
+    }
+    let mut every: Vec<u8> = (0..4096).map(|i| (i % 256) as u8).collect();
+    for i in (1..every.len()).rev() {
+        every.swap(i, rng.below(i + 1));
+    }
+    cases.push(("every byte, shuffled".to_string(), every));
+
+    cases
+}
+
+/// The fewest bits any prefix code can code `frequencies` in, by merging the
+/// two rarest weights until one is left.
+fn optimal_bits(frequencies: &Map<u8, usize>) -> usize {
+    if frequencies.len() == 1 {
+        return frequencies.values().sum();
+    }
+    let mut heap: BinaryHeap<Reverse<usize>> = frequencies.values().map(|&frequency| Reverse(frequency)).collect();
+    let mut bits = 0;
+    while heap.len() > 1 {
+        let (Reverse(a), Reverse(b)) = (heap.pop().unwrap(), heap.pop().unwrap());
+        bits += a + b;
+        heap.push(Reverse(a + b));
+    }
+
+    bits
+}
+
+/// Checks that no code of `codes` starts another, and that together they
+/// leave no code unused.
+fn assert_prefix_free(name: &str, codes: &CodeTable<u8>) {
+    let mut all: Vec<&str> = codes.iter().map(|(_, code)| code).collect();
+    all.sort_unstable();
+    // Sorted, a code that starts another comes just before one it starts.
+    for pair in all.windows(2) {
+        assert!(!pair[1].starts_with(pair[0]), "{}: {} starts {}", name, pair[0], pair[1]);
+    }
+
+    if all.len() > 1 {
+        let longest = all.iter().map(|code| code.len()).max().unwrap();
+        assert!(longest < 128, "{}: a code of {} bits", name, longest);
+        let kraft: u128 = all.iter().map(|code| 1u128 << (longest - code.len())).sum();
+        assert_eq!(kraft, 1u128 << longest, "{}: the codes leave some unused", name);
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    for (name, input) in cases() {
+        let decompress = |compressed: Vec<u8>| huffman::decompress_bytes(&compressed).unwrap();
+        assert_eq!(decompress(huffman::compress_bytes(&input)), input, "{}", name);
+        assert_eq!(decompress(huffman::compress_bytes_optimal(&input)), input, "{}", name);
+        assert_eq!(decompress(huffman::compress_bytes_parallel(&input, 3)), input, "{}", name);
+        assert_eq!(decompress(huffman::compress_adaptive(&input)), input, "{}", name);
+    }
+}
+
+#[test]
+fn test_decode_encode() {
+    for (name, input) in cases().into_iter().filter(|(_, input)| !input.is_empty()) {
+        let tree = Tree::from_frequencies(&huffman::get_byte_frequencies(&input)).unwrap();
+        let codes = CodeTable::from_tree(&tree);
+        let encoded = huffman::encode(input.iter().copied(), &codes).unwrap();
+        assert_eq!(huffman::decode(&encoded, &tree, input.len()).unwrap(), input, "{}", name);
+
+        let canonical = codes.canonicalize();
+        let encoded = huffman::encode(input.iter().copied(), &canonical).unwrap();
+        assert_eq!(huffman::decode_canonical(&encoded, &canonical, input.len()).unwrap(), input, "{}", name);
+    }
+}
+
+#[test]
+fn test_codes() {
+    for (name, input) in cases().into_iter().filter(|(_, input)| !input.is_empty()) {
+        let frequencies = huffman::get_byte_frequencies(&input);
+        let codes = CodeTable::from_tree(&Tree::from_frequencies(&frequencies).unwrap());
+        let canonical = codes.canonicalize();
+        assert_eq!(codes.len(), frequencies.len(), "{}", name);
+        assert_eq!(canonical.code_lengths(), codes.code_lengths(), "{}", name);
+        assert_prefix_free(&name, &codes);
+        assert_prefix_free(&name, &canonical);
+
+        // Rarer symbols never get shorter codes, and no code does better.
+        let lengths = codes.code_lengths();
+        for (a, &frequency_a) in &frequencies {
+            for (b, &frequency_b) in &frequencies {
+                if frequency_a > frequency_b {
+                    assert!(lengths[a] <= lengths[b], "{}: {} is commoner than {} but coded longer", name, a, b);
+                }
+            }
+        }
+        let bits: usize = frequencies.iter().map(|(symbol, &frequency)| frequency * lengths[symbol] as usize).sum();
+        assert_eq!(bits, optimal_bits(&frequencies), "{}", name);
+    }
+}
@@ -0,0 +1,492 @@
+//! A slow, obviously correct model of the latest statically coded format, and
+//! differential tests of the optimized encoders and decoders against it.
+//!
+//! The model shares no code with the crate: it parses streams byte by byte,
+//! decodes them a bit at a time against a map of canonical codes, and encodes
+//! one block per input with textbook Huffman code lengths. Inputs and configs
+//! come from a seeded generator, so a failing case can be replayed. Blocks
+//! coded with a shared table are left out, as they hold no table to check.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use huffman::HuffmanDecoder;
+
+const KIND_CHARS: u8 = 0;
+const KIND_BYTES: u8 = 1;
+const KIND_U16: u8 = 2;
+
+/// A decoded block and the table it was decoded with.
+#[derive(Debug)]
+struct Block {
+    symbols: Vec<u32>,
+    lengths: HashMap<u32, u8>,
+    /// The escape's code length and literal width.
+    escape: Option<(u8, u8)>,
+}
+
+/// A decoded stream.
+#[derive(Debug)]
+struct Decoded {
+    kind: u8,
+    blocks: Vec<Block>,
+    /// The symbols as [`huffman::decompress_bytes`] returns them.
+    output: Vec<u8>,
+}
+
+/// Reads the input front to back, panicking if it runs out.
+struct Input<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, count: usize) -> &'a [u8] {
+        assert!(self.bytes.len() >= count, "Truncated stream");
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        taken
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take(1)[0]
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+
+    fn symbol(&mut self, kind: u8) -> u32 {
+        match kind {
+            KIND_CHARS => self.u32(),
+            KIND_BYTES => self.u8() as u32,
+            KIND_U16 => u16::from_le_bytes(self.take(2).try_into().unwrap()) as u32,
+            kind => panic!("Unknown symbol kind {}", kind),
+        }
+    }
+}
+
+/// The bits of `bytes`, most significant first.
+fn bits(bytes: &[u8]) -> Vec<bool> {
+    bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| byte >> i & 1 == 1)).collect()
+}
+
+/// `bits` packed most significant first, padded with zeros.
+fn pack(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| chunk.iter().enumerate().fold(0, |byte, (i, &bit)| byte | (bit as u8) << (7 - i)))
+        .collect()
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { crc >> 1 ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+/// The canonical code of each leaf, as `(length, code)`, with `None` for the
+/// escape. Leaves are ordered by code length, then symbol, with the escape
+/// last among its length, and each takes the next code of its length.
+fn canonical_codes(lengths: &HashMap<u32, u8>, escape: Option<u8>) -> HashMap<(u8, u64), Option<u32>> {
+    let mut leaves: Vec<(u8, bool, u32)> = lengths.iter().map(|(&symbol, &length)| (length, false, symbol)).collect();
+    leaves.extend(escape.map(|length| (length, true, 0)));
+    leaves.sort();
+
+    let mut codes = HashMap::new();
+    let (mut code, mut previous) = (0u64, 0);
+    for (length, is_escape, symbol) in leaves {
+        code <<= length - previous;
+        codes.insert((length, code), (!is_escape).then_some(symbol));
+        code += 1;
+        previous = length;
+    }
+
+    codes
+}
+
+/// Reads the tree shape at `depth`, pushing the depth of each leaf: 1 for an
+/// internal node followed by both children, 0 for a leaf.
+fn read_shape(shape: &[bool], position: &mut usize, depth: u8, depths: &mut Vec<u8>) {
+    let bit = shape[*position];
+    *position += 1;
+    if bit {
+        read_shape(shape, position, depth + 1, depths);
+        read_shape(shape, position, depth + 1, depths);
+    } else {
+        depths.push(depth);
+    }
+}
+
+fn read_block(input: &mut Input, kind: u8, length: u64) -> Block {
+    let encoding = input.u8();
+    let count = input.u32();
+    let mut lengths = HashMap::new();
+    let escape = match encoding {
+        0 => {
+            for _ in 0..count {
+                let symbol = input.symbol(kind);
+                assert!(lengths.insert(symbol, input.u8()).is_none(), "Repeated symbol");
+            }
+            read_escape(input)
+        }
+        1 => {
+            let symbols: Vec<u32> = (0..count).map(|_| input.symbol(kind)).collect();
+            let escape = read_escape(input);
+
+            let leaves = symbols.len() + escape.is_some() as usize;
+            let shape = bits(input.take((2 * leaves - 1).div_ceil(8)));
+            let mut depths = Vec::new();
+            read_shape(&shape, &mut 0, 0, &mut depths);
+            assert_eq!(depths.len(), leaves, "Tree shape does not match the table");
+            if depths == [0] {
+                depths[0] = 1;
+            }
+
+            // The escape is the last leaf of its depth.
+            if let Some((code_length, _)) = escape {
+                let position = depths.iter().rposition(|&depth| depth == code_length).unwrap();
+                depths.remove(position);
+            }
+            lengths = symbols.into_iter().zip(depths).collect();
+            escape
+        }
+        encoding => panic!("Unknown table encoding {}", encoding),
+    };
+
+    let encoded_size = input.u64() as usize;
+    let encoded = bits(input.take(encoded_size));
+    let codes = canonical_codes(&lengths, escape.map(|(code_length, _)| code_length));
+
+    let mut position = 0;
+    let mut next_bit = || {
+        assert!(position < encoded.len(), "Encoded bits run out");
+        position += 1;
+        encoded[position - 1]
+    };
+    let symbols = (0..length)
+        .map(|_| {
+            let (mut code_length, mut code) = (0, 0);
+            loop {
+                code = code << 1 | next_bit() as u64;
+                code_length += 1;
+                assert!(code_length <= 64, "No code matches");
+                match codes.get(&(code_length, code)) {
+                    Some(Some(symbol)) => return *symbol,
+                    Some(None) => {
+                        let width = escape.unwrap().1;
+                        return (0..width).fold(0, |value, _| value << 1 | next_bit() as u32);
+                    }
+                    None => {}
+                }
+            }
+        })
+        .collect();
+    assert_eq!(position.div_ceil(8), encoded_size, "Encoded size does not match the bits");
+
+    Block { symbols, lengths, escape }
+}
+
+fn read_escape(input: &mut Input) -> Option<(u8, u8)> {
+    match input.u8() {
+        0 => None,
+        code_length => Some((code_length, input.u8())),
+    }
+}
+
+/// Decodes a statically coded stream of the latest version, panicking if it
+/// is malformed in any way.
+fn reference_decode(compressed: &[u8]) -> Decoded {
+    let mut input = Input { bytes: compressed };
+    assert_eq!(input.take(4), b"HUFF");
+    assert_eq!(input.u8(), 11, "Not the latest version");
+    let kind = input.u8();
+    assert_eq!(input.u8(), 0, "Not statically coded");
+    if input.u8() == 1 {
+        input.take(32);
+    }
+
+    let mut blocks = Vec::new();
+    loop {
+        let length = input.u64();
+        if length == 0 {
+            break;
+        }
+        blocks.push(read_block(&mut input, kind, length));
+    }
+
+    let mut output = Vec::new();
+    for &symbol in blocks.iter().flat_map(|block| &block.symbols) {
+        match kind {
+            KIND_CHARS => output.extend(char::from_u32(symbol).unwrap().to_string().bytes()),
+            KIND_BYTES => output.push(u8::try_from(symbol).unwrap()),
+            _ => output.extend(u16::try_from(symbol).unwrap().to_le_bytes()),
+        }
+    }
+    assert_eq!(input.u32(), crc32(&output), "Checksum does not match");
+    assert!(input.bytes.is_empty(), "Bytes after the checksum");
+
+    Decoded { kind, blocks, output }
+}
+
+/// Huffman code lengths for `frequencies`, by repeatedly merging the two
+/// lightest subtrees, each merge adding a bit to every symbol below it. A lone
+/// symbol still takes one bit.
+fn huffman_lengths(frequencies: &HashMap<u32, usize>) -> HashMap<u32, u8> {
+    let mut lengths: HashMap<u32, u8> = frequencies.keys().map(|&symbol| (symbol, 0)).collect();
+    let mut trees: Vec<(usize, Vec<u32>)> =
+        frequencies.iter().map(|(&symbol, &frequency)| (frequency, vec![symbol])).collect();
+    if trees.len() == 1 {
+        lengths.insert(trees[0].1[0], 1);
+    }
+
+    while trees.len() > 1 {
+        trees.sort_by_key(|&(weight, _)| std::cmp::Reverse(weight));
+        let (lightest, next) = (trees.pop().unwrap(), trees.pop().unwrap());
+        for symbol in lightest.1.iter().chain(&next.1) {
+            *lengths.get_mut(symbol).unwrap() += 1;
+        }
+        trees.push((lightest.0 + next.0, [lightest.1, next.1].concat()));
+    }
+
+    lengths
+}
+
+fn frequencies(symbols: &[u32]) -> HashMap<u32, usize> {
+    let mut frequencies = HashMap::new();
+    for &symbol in symbols {
+        *frequencies.entry(symbol).or_insert(0) += 1;
+    }
+
+    frequencies
+}
+
+/// The fewest bits any code of the single symbols takes for `symbols`.
+fn optimal_bits(symbols: &[u32]) -> u64 {
+    let frequencies = frequencies(symbols);
+    let lengths = huffman_lengths(&frequencies);
+    frequencies.iter().map(|(symbol, &frequency)| (frequency * lengths[symbol] as usize) as u64).sum()
+}
+
+/// The bits the table of `block` takes for its symbols.
+fn block_bits(block: &Block) -> u64 {
+    block.symbols.iter()
+        .map(|symbol| match (block.lengths.get(symbol), block.escape) {
+            (Some(&length), _) => length as u64,
+            (None, Some((code_length, literal_width))) => (code_length + literal_width) as u64,
+            (None, None) => panic!("Symbol without a code"),
+        })
+        .sum()
+}
+
+/// Encodes `symbols` as one block of code lengths, without an escape.
+fn reference_encode(kind: u8, symbols: &[u32], output: &[u8]) -> Vec<u8> {
+    let mut compressed = b"HUFF".to_vec();
+    compressed.extend([11, kind, 0, 0]);
+
+    if !symbols.is_empty() {
+        let lengths = huffman_lengths(&frequencies(symbols));
+        let codes: HashMap<u32, (u8, u64)> = canonical_codes(&lengths, None)
+            .into_iter()
+            .map(|(code, symbol)| (symbol.unwrap(), code))
+            .collect();
+
+        let mut bits = Vec::new();
+        for symbol in symbols {
+            let (length, code) = codes[symbol];
+            bits.extend((0..length).rev().map(|i| code >> i & 1 == 1));
+        }
+        let encoded = pack(&bits);
+
+        let mut table: Vec<(u32, u8)> = lengths.into_iter().collect();
+        table.sort();
+        compressed.extend((symbols.len() as u64).to_le_bytes());
+        compressed.push(0);
+        compressed.extend((table.len() as u32).to_le_bytes());
+        for (symbol, length) in table {
+            match kind {
+                KIND_CHARS => compressed.extend(symbol.to_le_bytes()),
+                KIND_BYTES => compressed.push(symbol as u8),
+                _ => compressed.extend((symbol as u16).to_le_bytes()),
+            }
+            compressed.push(length);
+        }
+        compressed.push(0);
+        compressed.extend((encoded.len() as u64).to_le_bytes());
+        compressed.extend(encoded);
+    }
+
+    compressed.extend(0u64.to_le_bytes());
+    compressed.extend(crc32(output).to_le_bytes());
+    compressed
+}
+
+/// A xorshift generator, good enough to vary the cases.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// A random input of symbols of `kind` and the bytes they decompress to.
+///
+/// The alphabets range from one symbol to far more than tables hold, so that
+/// blocks get escapes, and are skewed to varying degrees.
+fn random_input(rng: &mut Rng, kind: u8) -> (Vec<u32>, Vec<u8>) {
+    let length = [0, 1, 2, 50, 500, 3000][rng.below(6) as usize];
+    let alphabet = [1, 2, 5, 30, 200, 1000][rng.below(6) as usize];
+    let skew = rng.below(4);
+    let base = rng.below(512) as u32;
+
+    let symbols: Vec<u32> = (0..length)
+        .map(|_| {
+            let index = (0..=skew).map(|_| rng.below(alphabet)).min().unwrap() as u32;
+            match kind {
+                KIND_CHARS => {
+                    let value = base * 64 + index * 7;
+                    char::from_u32(value).map_or(index, |_| value)
+                }
+                KIND_BYTES => (base + index) % 256,
+                _ => (base * 128 + index * 61) % 65536,
+            }
+        })
+        .collect();
+
+    let mut output = Vec::new();
+    for &symbol in &symbols {
+        match kind {
+            KIND_CHARS => output.extend(char::from_u32(symbol).unwrap().to_string().bytes()),
+            KIND_BYTES => output.push(symbol as u8),
+            _ => output.extend((symbol as u16).to_le_bytes()),
+        }
+    }
+
+    (symbols, output)
+}
+
+/// Compresses `symbols` of `kind` with one of the optimized paths chosen by
+/// `rng`, returning its name with the output.
+fn optimized_compress(rng: &mut Rng, kind: u8, symbols: &[u32], output: &[u8]) -> (String, Vec<u8>) {
+    let threads = 1 + rng.below(4) as usize;
+    let path = rng.below(if kind == KIND_BYTES { 4 } else { 3 });
+    let name = format!("path {} on {} threads", path, threads);
+
+    let compressed = match kind {
+        KIND_CHARS => {
+            let text = std::str::from_utf8(output).unwrap();
+            match path {
+                0 => huffman::compress(text),
+                1 => huffman::compress_optimal(text),
+                _ => huffman::compress_parallel(text, threads),
+            }
+        }
+        KIND_BYTES => match path {
+            0 => huffman::compress_bytes(output),
+            1 => huffman::compress_bytes_optimal(output),
+            2 => huffman::compress_bytes_parallel(output, threads),
+            _ => {
+                let block_size = 1 + rng.below(700) as usize;
+                let mut encoder = huffman::HuffmanEncoder::with_block_size(Vec::new(), block_size);
+                let mut rest = output;
+                while !rest.is_empty() {
+                    let (chunk, tail) = rest.split_at((1 + rng.below(300) as usize).min(rest.len()));
+                    encoder.write_all(chunk).unwrap();
+                    rest = tail;
+                }
+                return (format!("encoder with blocks of {}", block_size), encoder.finish().unwrap());
+            }
+        },
+        _ => {
+            let values: Vec<u16> = symbols.iter().map(|&symbol| symbol as u16).collect();
+            match path {
+                0 => huffman::compress_u16(&values),
+                1 => huffman::compress_u16_optimal(&values),
+                _ => huffman::compress_u16_parallel(&values, threads),
+            }
+        }
+    };
+
+    (name, compressed)
+}
+
+#[test]
+fn test_reference_round_trip() {
+    let symbols: Vec<u32> = b"abbcccdddd".iter().map(|&byte| byte as u32).collect();
+    let compressed = reference_encode(KIND_BYTES, &symbols, b"abbcccdddd");
+    let decoded = reference_decode(&compressed);
+    assert_eq!(decoded.output, b"abbcccdddd");
+    assert_eq!(block_bits(&decoded.blocks[0]), optimal_bits(&symbols));
+    assert_eq!(optimal_bits(&symbols), 19);
+
+    assert!(reference_decode(&reference_encode(KIND_U16, &[], &[])).blocks.is_empty());
+    assert_eq!(reference_decode(&huffman::compress_bytes(b"abbcccdddd")).output, b"abbcccdddd");
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "too slow under Miri")]
+fn test_encoders_against_reference() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for case in 0..300 {
+        let kind = rng.below(3) as u8;
+        let (symbols, output) = random_input(&mut rng, kind);
+        let (path, compressed) = optimized_compress(&mut rng, kind, &symbols, &output);
+
+        let decoded = reference_decode(&compressed);
+        assert_eq!((decoded.kind, &decoded.output), (kind, &output), "case {}, {}", case, path);
+        assert_eq!(huffman::decompress_bytes(&compressed).unwrap(), output, "case {}, {}", case, path);
+
+        // Without an escape, a block's table must be as good as a Huffman
+        // code, and an escape only pays off for the table.
+        for block in &decoded.blocks {
+            let (bits, optimal) = (block_bits(block), optimal_bits(&block.symbols));
+            if block.escape.is_some() {
+                assert!(bits >= optimal, "case {}, {}", case, path);
+            } else {
+                assert_eq!(bits, optimal, "case {}, {}", case, path);
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "too slow under Miri")]
+fn test_decoders_against_reference() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for case in 0..300 {
+        let kind = rng.below(3) as u8;
+        let (symbols, output) = random_input(&mut rng, kind);
+        let compressed = reference_encode(kind, &symbols, &output);
+
+        assert_eq!(huffman::decompress_bytes(&compressed).unwrap(), output, "case {}", case);
+        let threads = 1 + rng.below(4) as usize;
+        let mut decoded = Vec::new();
+        HuffmanDecoder::with_threads(compressed.as_slice(), threads).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, output, "case {} on {} threads", case, threads);
+
+        match kind {
+            KIND_CHARS => assert_eq!(huffman::decompress(&compressed).unwrap().as_bytes(), output, "case {}", case),
+            KIND_U16 => {
+                let values: Vec<u16> = symbols.iter().map(|&symbol| symbol as u16).collect();
+                assert_eq!(huffman::decompress_u16(&compressed).unwrap(), values, "case {}", case);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Snapshots of what the command line tool prints, so that changes to its
+//! output are deliberate and reviewed rather than accidental.
+//!
+//! Each test runs the tool on files of its own in a fresh directory and
+//! compares a transcript of the run with `tests/snapshots/<name>.snap`. After
+//! changing the output on purpose, run the tests with `UPDATE_SNAPSHOTS=1` to
+//! write the new transcripts, and review them in the diff.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A fresh directory for the test `name`, holding `files`.
+fn directory(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("huffman-rs-snapshot-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (file, contents) in files {
+        fs::write(dir.join(file), contents).unwrap();
+    }
+
+    dir
+}
+
+/// Runs the tool with `args` in `dir`, returning the exit code and everything
+/// it printed.
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_huffman-rs")).args(args).current_dir(dir).output().unwrap();
+
+    format!(
+        "$ huffman-rs {}\nexit: {}\n--- stdout\n{}--- stderr\n{}",
+        args.join(" "),
+        output.status.code().map_or("signal".to_string(), |code| code.to_string()),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+/// Compares `transcript` with the snapshot `name`, or writes it if
+/// `UPDATE_SNAPSHOTS` is set.
+fn assert_snapshot(name: &str, transcript: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(format!("{}.snap", name));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, transcript).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("No snapshot at {}; run with UPDATE_SNAPSHOTS=1 to write it", path.display()));
+    assert!(
+        transcript == expected,
+        "The output of {} changed; run with UPDATE_SNAPSHOTS=1 to accept it\n--- expected\n{}--- actual\n{}",
+        name,
+        expected,
+        transcript,
+    );
+}
+
+/// Runs each of `runs` in a directory holding `files`, and compares their
+/// transcripts with the snapshot `name`.
+fn snapshot(name: &str, files: &[(&str, &[u8])], runs: &[&[&str]]) {
+    let dir = directory(name, files);
+    let transcript: Vec<String> = runs.iter().map(|args| run(&dir, args)).collect();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_snapshot(name, &transcript.join("\n"));
+}
+
+fn text() -> Vec<u8> {
+    format!("{}{}", "the quick brown fox jumps over the lazy dog. ".repeat(40), "ünïcödé").into_bytes()
+}
+
+#[test]
+fn test_stats() {
+    let files: [(&str, &[u8]); 2] = [("a.txt", b"abbcccdddd"), ("a.bin", &[0xFF, 0x00, 0x00, 0x80])];
+    snapshot("stats", &files, &[&["stats", "a.txt"], &["stats", "a.bin"]]);
+}
+
+#[test]
+fn test_stats_verbose() {
+    snapshot("stats_verbose", &[("fox.txt", &text())], &[&["stats", "-v", "fox.txt"]]);
+}
+
+#[test]
+fn test_compress_stats() {
+    let runs: [&[&str]; 2] = [
+        &["compress", "--stats", "fox.txt"],
+        &["compress", "--stats", "--optimal", "-o", "x", "fox.txt"],
+    ];
+    snapshot("compress_stats", &[("fox.txt", &text())], &runs);
+}
+
+#[test]
+fn test_dry_run() {
+    let runs: [&[&str]; 3] = [
+        &["compress", "--dry-run", "fox.txt"],
+        &["compress", "--dry-run", "--symbols", "u16le", "fox.txt"],
+        &["compress", "--dry-run", "-o", "x", "fox.txt"],
+    ];
+    snapshot("dry_run", &[("fox.txt", &text())], &runs);
+}
+
+#[test]
+fn test_remove_output_is_input() {
+    let dir = directory("remove_output_is_input", &[("a.txt", b"abbcccdddd")]);
+    let runs: [&[&str]; 3] = [
+        &["compress", "--rm", "-o", "a.txt", "a.txt"],
+        &["compress", "--verify", "--rm", "-o", "./a.txt", "a.txt"],
+        &["compress", "--archive", "--rm", "-o", "a.txt", "a.txt"],
+    ];
+    let transcript: Vec<String> = runs.iter().map(|args| run(&dir, args)).collect();
+    let contents = fs::read(dir.join("a.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(contents.unwrap(), b"abbcccdddd");
+    assert_snapshot("remove_output_is_input", &transcript.join("\n"));
+}
+
+#[test]
+fn test_help() {
+    snapshot("help", &[], &[&["--help"], &["version"]]);
+}
+
+#[test]
+fn test_errors() {
+    let runs: [&[&str]; 4] = [
+        &["compress"],
+        &["frobnicate", "a.txt"],
+        &["decompress", "a.txt"],
+        &["decompress", "missing.huf"],
+    ];
+    snapshot("errors", &[("a.txt", b"abbcccdddd")], &runs);
+}
+
+#[test]
+fn test_json_errors() {
+    let compressed = huffman::compress("abbcccdddd".repeat(10).as_str());
+    let runs: [&[&str]; 4] = [
+        &["compress", "--json", "--frobnicate", "a.txt"],
+        &["decompress", "--json", "a.txt"],
+        &["decompress", "--json", "missing.huf"],
+        &["decompress", "--json", "short.huf"],
+    ];
+    snapshot("json_errors", &[("a.txt", b"abbcccdddd"), ("short.huf", &compressed[..compressed.len() - 4])], &runs);
+}
+
+#[test]
+fn test_hints() {
+    let runs: [&[&str]; 6] = [
+        &["compress", "-o", "both.huf", "a.txt", "b.txt"],
+        &["decompress", "both.huf"],
+        &["compress", "a.txt"],
+        &["extract", "a.txt.huf"],
+        &["compress", "--symbols", "u16le", "b.txt"],
+        &["compress", "--use-table", "b.txt", "a.txt"],
+    ];
+    snapshot("hints", &[("a.txt", b"abbcccdddd"), ("b.txt", b"odd")], &runs);
+}
@@ -0,0 +1,29 @@
+//! The tool on Windows, whose console takes UTF-16 rather than bytes. Rust
+//! writes to a console with `WriteConsoleW` and to a pipe or file as UTF-8,
+//! so symbol tables print as they do elsewhere whatever the code page; these
+//! tests check that the CJK symbols of a table come out whole through a pipe,
+//! as scripts read them.
+
+#![cfg(windows)]
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_cjk_symbol_table() {
+    let dir = std::env::temp_dir().join(format!("huffman-rs-windows-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("cjk.txt"), "霍夫曼编码编码").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_huffman-rs"))
+        .args(["stats", "cjk.txt"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).expect("The table is UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines, ["夫 1", "曼 1", "码 2", "编 2", "霍 1"]);
+}